@@ -0,0 +1,77 @@
+use dioxus::prelude::*;
+use dioxus_logger::tracing::{debug, error};
+use subsonic_types::response::{ResponseBody, ResponseStatus};
+
+use super::udrome::SubsonicResponseOuter;
+use crate::model::{
+    credentials::Credentials,
+    globals::{BaseUrl, CREDENTIALS},
+};
+
+// checks the credentials against `ping.view` before keeping them
+async fn login(base_url: &str, credentials: &Credentials) -> Result<(), String> {
+    let url = format!("{base_url}/rest/ping.view?{}&f=json", credentials.query());
+    let response: SubsonicResponseOuter = reqwest::get(url)
+        .await
+        .map_err(|e| format!("{e}"))?
+        .json()
+        .await
+        .map_err(|e| format!("{e}"))?;
+    let response = response.subsonic_response;
+    match (response.status, response.body) {
+        (ResponseStatus::Ok, _) => Ok(()),
+        (_, ResponseBody::Error(e)) => Err(e.message.unwrap_or_else(|| "login failed".into())),
+        _ => Err("login failed".into()),
+    }
+}
+
+#[component]
+pub fn Login() -> Element {
+    let base_url = use_context::<BaseUrl>();
+    let mut username = use_signal(String::new);
+    let mut password = use_signal(String::new);
+    let mut login_error = use_signal(|| None::<String>);
+
+    rsx! {
+        div { id: "login", class: "sm:ml-64 p-4",
+            form {
+                class: "flex flex-col gap-2 w-64",
+                onsubmit: move |ev| {
+                    ev.prevent_default();
+                    to_owned![base_url];
+                    async move {
+                        let credentials = Credentials::new(username(), &password());
+                        match login(&base_url, &credentials).await {
+                            Ok(()) => {
+                                debug!("logged in as {}", credentials.username);
+                                credentials.store();
+                                *CREDENTIALS.write() = Some(credentials);
+                            }
+                            Err(e) => {
+                                error!("login: {e}");
+                                login_error.set(Some(e));
+                            }
+                        }
+                    }
+                },
+                input {
+                    class: "input",
+                    placeholder: "username",
+                    value: "{username}",
+                    oninput: move |ev| username.set(ev.value()),
+                }
+                input {
+                    class: "input",
+                    r#type: "password",
+                    placeholder: "password",
+                    value: "{password}",
+                    oninput: move |ev| password.set(ev.value()),
+                }
+                button { class: "btn", r#type: "submit", "log in" }
+                if let Some(e) = login_error() {
+                    p { class: "text-red-500", "{e}" }
+                }
+            }
+        }
+    }
+}
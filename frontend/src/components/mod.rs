@@ -3,9 +3,13 @@ pub use navbar::Navbar;
 
 mod udrome;
 pub use udrome::Udrome;
+pub(crate) use udrome::SubsonicResponseOuter;
 
 mod search_result;
 pub use search_result::SearchResult;
 
 mod player;
 pub use player::Player;
+
+mod login;
+pub use login::Login;
@@ -1,7 +1,13 @@
 use dioxus::prelude::*;
 use dioxus_logger::tracing::{debug, error};
 
-use crate::{model::globals::SONG, Route};
+use crate::{
+    model::{
+        credentials::Credentials,
+        globals::{CREDENTIALS, SONG},
+    },
+    Route,
+};
 const NAVBAR_CSS: Asset = asset!("/assets/styling/blog.css");
 
 #[component]
@@ -75,6 +81,16 @@ pub fn Navbar() -> Element {
                             class: "flex items-center p-2 text-gray-900 rounded-lg dark:text-white hover:bg-gray-100 dark:hover:bg-gray-700 group",
                             span { class: "ms-3", "Playlists" }
                         }
+                        if CREDENTIALS.read().is_some() {
+                            button {
+                                class: "flex items-center p-2 text-gray-900 rounded-lg dark:text-white hover:bg-gray-100 dark:hover:bg-gray-700 group",
+                                onclick: move |_ev| {
+                                    Credentials::clear();
+                                    *CREDENTIALS.write() = None;
+                                },
+                                span { class: "ms-3", "Log out" }
+                            }
+                        }
                     }
                 }
             }
@@ -3,7 +3,33 @@ use dioxus_logger::tracing::warn;
 use wasm_bindgen::JsCast;
 use web_sys::HtmlAudioElement;
 
-use crate::model::globals::{BaseUrl, PLAYER, SONG};
+use crate::model::{
+    client,
+    globals::{BaseUrl, PLAYER, SONG},
+};
+
+/// saves what's playing, and where, so other devices can pick up from there
+fn save_play_queue(base_url: &str) {
+    let Some(id) = SONG.peek().as_ref().map(|song| song.id.clone()) else {
+        return;
+    };
+    let position = PLAYER
+        .peek()
+        .as_ref()
+        .map(|player| (player.current_time() * 1000.0) as u64)
+        .unwrap_or_default();
+    let base_url = base_url.to_string();
+    spawn(async move {
+        let params = [
+            ("id", id.clone()),
+            ("current", id),
+            ("position", position.to_string()),
+        ];
+        if let Err(e) = client::get(&base_url, "savePlayQueue", &params).await {
+            warn!("saving play queue: {e}");
+        }
+    });
+}
 #[component]
 pub fn Player(onfocus: EventHandler<FocusEvent>, onblur: EventHandler<FocusEvent>) -> Element {
     let song_lock = SONG.read();
@@ -37,6 +63,14 @@ pub fn Player(onfocus: EventHandler<FocusEvent>, onblur: EventHandler<FocusEvent
                 onmounted,
                 onfocus,
                 onblur,
+                onplay: {
+                    to_owned![base_url];
+                    move |_| save_play_queue(&base_url)
+                },
+                onpause: {
+                    to_owned![base_url];
+                    move |_| save_play_queue(&base_url)
+                },
 
                 controls: true,
                 source { src, r#type: "audio/mpeg" }
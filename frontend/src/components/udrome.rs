@@ -7,7 +7,7 @@ use futures::StreamExt;
 use serde::Deserialize;
 use subsonic_types::{
     common::{Format, Version},
-    request::{search::Search3, Authentication, Request as SRequest, SubsonicRequest},
+    request::{search::Search3, Request as SRequest, SubsonicRequest},
     response::{Response as SubsonicResponse, ResponseBody},
 };
 use url::Url;
@@ -17,7 +17,8 @@ use web_sys::{HtmlElement, HtmlInputElement};
 use crate::{
     components::SearchResult,
     model::{
-        globals::{BaseUrl, Command, CommandBroadcast, Focus, FOCUS, SONG},
+        credentials::{Credentials, CLIENT},
+        globals::{BaseUrl, Command, CommandBroadcast, Focus, CREDENTIALS, FOCUS, SONG},
         SongInfo,
     },
 };
@@ -86,13 +87,14 @@ impl Paginator {
 }
 
 #[derive(Debug, Deserialize)]
-struct SubsonicResponseOuter {
+pub(crate) struct SubsonicResponseOuter {
     #[serde(rename = "subsonic-response")]
-    subsonic_response: SubsonicResponse,
+    pub(crate) subsonic_response: SubsonicResponse,
 }
 
 struct Request00r {
     base_url: String,
+    credentials: Credentials,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -103,9 +105,10 @@ struct Page {
 }
 
 impl Request00r {
-    fn new(base_url: impl AsRef<str>) -> Self {
+    fn new(base_url: impl AsRef<str>, credentials: Credentials) -> Self {
         Self {
             base_url: base_url.as_ref().to_string(),
+            credentials,
         }
     }
 
@@ -123,10 +126,10 @@ impl Request00r {
         };
 
         let request = SRequest {
-            username: "todo".into(),
-            authentication: Authentication::Password("todo".into()),
+            username: self.credentials.username.clone(),
+            authentication: self.credentials.authentication(),
             version: Version::LATEST,
-            client: "udrome-dx".into(),
+            client: CLIENT.into(),
             format: Some(Format::Json.to_string()),
             body: search,
         };
@@ -194,7 +197,12 @@ pub fn Udrome() -> Element {
     let tx = use_coroutine(move |mut rx: UnboundedReceiver<Page>| {
         to_owned![base_url];
         async move {
-            let req = Request00r::new(base_url.as_str());
+            // `Home` only renders us once logged in
+            let Some(credentials) = CREDENTIALS.peek().clone() else {
+                error!("no credentials");
+                return;
+            };
+            let req = Request00r::new(base_url.as_str(), credentials);
 
             let client = reqwest::Client::new();
             let mut cache: HashMap<Url, SubsonicResponse> = HashMap::new();
@@ -0,0 +1,37 @@
+use dioxus::prelude::*;
+use subsonic_types::response::{ResponseBody, ResponseStatus};
+use url::form_urlencoded::Serializer;
+
+use super::globals::CREDENTIALS;
+use crate::components::SubsonicResponseOuter;
+
+/// `GET {base_url}/rest/{endpoint}.view` as the logged in user, `Err` carries a displayable message
+pub(crate) async fn get(
+    base_url: &str,
+    endpoint: &str,
+    params: &[(&str, String)],
+) -> Result<ResponseBody, String> {
+    let Some(credentials) = CREDENTIALS.peek().clone() else {
+        return Err("not logged in".into());
+    };
+    let params = Serializer::new(String::new())
+        .extend_pairs(params.iter().map(|(k, v)| (*k, v.as_str())))
+        .finish();
+    let url = format!(
+        "{base_url}/rest/{endpoint}.view?{}&f=json&{params}",
+        credentials.query()
+    );
+
+    let response: SubsonicResponseOuter = reqwest::get(url)
+        .await
+        .map_err(|e| format!("{e}"))?
+        .json()
+        .await
+        .map_err(|e| format!("{e}"))?;
+    let response = response.subsonic_response;
+    match (response.status, response.body) {
+        (ResponseStatus::Ok, body) => Ok(body),
+        (_, ResponseBody::Error(e)) => Err(e.message.unwrap_or_else(|| "request failed".into())),
+        _ => Err("request failed".into()),
+    }
+}
@@ -0,0 +1,97 @@
+use dioxus_logger::tracing::error;
+use subsonic_types::{common::Version, request::Authentication};
+use url::form_urlencoded::byte_serialize;
+use web_sys::Storage;
+
+pub(crate) const CLIENT: &str = "udrome-dx";
+
+const KEY_USERNAME: &str = "udrome.username";
+const KEY_TOKEN: &str = "udrome.token";
+const KEY_SALT: &str = "udrome.salt";
+
+/// Subsonic token auth parameters - the password itself is never stored
+#[derive(Debug, Clone, PartialEq)]
+pub struct Credentials {
+    pub username: String,
+    pub token: String,
+    pub salt: String,
+}
+
+fn storage() -> Option<Storage> {
+    web_sys::window()
+        .and_then(|win| {
+            win.local_storage()
+                .inspect_err(|e| error!("local storage: {e:?}"))
+                .ok()
+        })
+        .flatten()
+}
+
+impl Credentials {
+    pub fn new(username: impl Into<String>, password: &str) -> Self {
+        let salt = format!(
+            "{:08x}{:08x}",
+            (js_sys::Math::random() * u32::MAX as f64) as u32,
+            (js_sys::Math::random() * u32::MAX as f64) as u32
+        );
+        let token = format!("{:x}", md5::compute(format!("{password}{salt}")));
+        Self {
+            username: username.into(),
+            token,
+            salt,
+        }
+    }
+
+    pub fn authentication(&self) -> Authentication {
+        Authentication::Token {
+            token: self.token.clone(),
+            salt: self.salt.clone(),
+        }
+    }
+
+    /// common request parameters, for URLs that the browser fetches on its own (`<audio>`, `<img>`)
+    pub fn query(&self) -> String {
+        let username: String = byte_serialize(self.username.as_bytes()).collect();
+        format!(
+            "u={username}&t={}&s={}&v={}&c={CLIENT}",
+            self.token,
+            self.salt,
+            Version::LATEST
+        )
+    }
+
+    pub fn load() -> Option<Self> {
+        let storage = storage()?;
+        let get = |key| storage.get_item(key).ok().flatten();
+        Some(Self {
+            username: get(KEY_USERNAME)?,
+            token: get(KEY_TOKEN)?,
+            salt: get(KEY_SALT)?,
+        })
+    }
+
+    pub fn store(&self) {
+        let Some(storage) = storage() else {
+            return;
+        };
+        for (key, val) in [
+            (KEY_USERNAME, &self.username),
+            (KEY_TOKEN, &self.token),
+            (KEY_SALT, &self.salt),
+        ] {
+            storage
+                .set_item(key, val)
+                .inspect_err(|e| error!("storing credentials: {e:?}"))
+                .ok();
+        }
+    }
+
+    pub fn clear() {
+        let Some(storage) = storage() else {
+            return;
+        };
+        for key in [KEY_USERNAME, KEY_TOKEN, KEY_SALT] {
+            storage.remove_item(key).ok();
+        }
+    }
+}
@@ -6,13 +6,16 @@ use futures::{lock::Mutex, StreamExt};
 use shrinkwraprs::Shrinkwrap;
 use web_sys::HtmlAudioElement;
 
-use super::SongInfo;
+use super::{credentials::Credentials, SongInfo};
 
 #[derive(Clone, Shrinkwrap)]
 pub struct BaseUrl(pub String);
 
 pub(crate) static SONG: GlobalSignal<Option<SongInfo>> = Signal::global(|| None);
 
+pub(crate) static CREDENTIALS: GlobalSignal<Option<Credentials>> =
+    Signal::global(Credentials::load);
+
 #[derive(Debug, Copy, Clone)]
 pub(crate) enum Command {
     Noop,
@@ -1,3 +1,5 @@
+use dioxus::prelude::*;
+use globals::CREDENTIALS;
 use shrinkwraprs::Shrinkwrap;
 use subsonic_types::response::Child as Song;
 
@@ -6,7 +8,18 @@ use subsonic_types::response::Child as Song;
 #[derive(Shrinkwrap, Debug, Clone, PartialEq)]
 pub struct SongInfo(pub Song);
 
+pub(crate) mod client;
+pub(crate) mod credentials;
 pub(crate) mod globals;
+
+fn auth_query() -> String {
+    CREDENTIALS
+        .read()
+        .as_ref()
+        .map(|creds| creds.query())
+        .unwrap_or_default()
+}
+
 impl SongInfo {
     pub fn duration_humanized(&self) -> String {
         let total_secs = self.duration.unwrap_or_default().to_duration().as_secs();
@@ -33,11 +46,21 @@ impl SongInfo {
     }
 
     pub fn cover_art_url(&self, base_url: &str) -> Option<String> {
-        self.cover_art
-            .as_ref()
-            .map(|id| format!("{}/rest/getCoverArt.view?id={}", base_url, id))
+        self.cover_art.as_ref().map(|id| {
+            format!(
+                "{}/rest/getCoverArt.view?id={}&{}",
+                base_url,
+                id,
+                auth_query()
+            )
+        })
     }
     pub fn stream_url(&self, base_url: &str) -> String {
-        format!("{}/rest/stream.view?id={}", base_url, self.id)
+        format!(
+            "{}/rest/stream.view?id={}&{}",
+            base_url,
+            self.id,
+            auth_query()
+        )
     }
 }
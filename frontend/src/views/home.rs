@@ -1,9 +1,17 @@
 use dioxus::prelude::*;
 
-use crate::components::Udrome;
+use crate::{
+    components::{Login, Udrome},
+    model::globals::CREDENTIALS,
+};
 
 #[component]
 pub fn Home() -> Element {
+    if CREDENTIALS.read().is_none() {
+        return rsx! {
+            Login {}
+        };
+    }
     rsx! {
         Udrome {}
     }
@@ -2,6 +2,9 @@ mod home;
 use dioxus::prelude::*;
 pub use home::Home;
 
+mod playlists;
+pub use playlists::{Playlist, Playlists};
+
 #[component]
 pub fn Song(id: i32) -> Element {
     rsx! { "song {id}" }
@@ -26,13 +29,3 @@ pub fn Albums() -> Element {
 pub fn Album(id: i32) -> Element {
     rsx! { "album {id}" }
 }
-
-#[component]
-pub fn Playlists() -> Element {
-    rsx! { "playlists" }
-}
-
-#[component]
-pub fn Playlist(id: i32) -> Element {
-    rsx! { "playlist {id}" }
-}
@@ -0,0 +1,159 @@
+use dioxus::prelude::*;
+use dioxus_logger::tracing::error;
+use subsonic_types::response::ResponseBody;
+
+use crate::{
+    components::SearchResult,
+    model::{
+        client,
+        globals::{BaseUrl, SONG},
+        SongInfo,
+    },
+    Route,
+};
+
+#[component]
+pub fn Playlists() -> Element {
+    let base_url = use_context::<BaseUrl>();
+    let mut name = use_signal(String::new);
+    let mut create_error = use_signal(|| None::<String>);
+
+    let mut playlists = use_resource({
+        to_owned![base_url];
+        move || {
+            to_owned![base_url];
+            async move { client::get(&base_url, "getPlaylists", &[]).await }
+        }
+    });
+
+    let list = match &*playlists.read() {
+        None => rsx! { "loading" },
+        Some(Ok(ResponseBody::Playlists(res))) => {
+            let rows = res.playlist.iter().map(|pl| {
+                let id = pl
+                    .id
+                    .strip_prefix("pl-")
+                    .and_then(|id| id.parse().ok())
+                    .unwrap_or_default();
+                rsx! {
+                    tr { key: "{pl.id}", class: "hover:bg-base-200",
+                        td {
+                            Link { to: Route::Playlist { id }, "{pl.name}" }
+                        }
+                        td { "{pl.song_count}" }
+                        td { "{pl.owner.clone().unwrap_or_default()}" }
+                        td { "{pl.comment.clone().unwrap_or_default()}" }
+                    }
+                }
+            });
+            rsx! {
+                table { class: "table",
+                    thead {
+                        tr {
+                            th { "Name" }
+                            th { "Songs" }
+                            th { "Owner" }
+                            th { "Comment" }
+                        }
+                    }
+                    tbody { class: "text-slate-300", {rows} }
+                }
+            }
+        }
+        Some(Ok(_)) => rsx! { "wrong response" },
+        Some(Err(e)) => rsx! { "could not load playlists: {e}" },
+    };
+
+    rsx! {
+        div { id: "playlists", class: "sm:ml-64 p-4",
+            form {
+                class: "flex gap-2",
+                onsubmit: move |ev| {
+                    ev.prevent_default();
+                    to_owned![base_url];
+                    async move {
+                        let params = [("name", name())];
+                        match client::get(&base_url, "createPlaylist", &params).await {
+                            Ok(_) => {
+                                name.set(String::new());
+                                create_error.set(None);
+                                playlists.restart();
+                            }
+                            Err(e) => {
+                                error!("create playlist: {e}");
+                                create_error.set(Some(e));
+                            }
+                        }
+                    }
+                },
+                input {
+                    class: "input",
+                    placeholder: "new playlist",
+                    value: "{name}",
+                    oninput: move |ev| name.set(ev.value()),
+                }
+                button { class: "btn", r#type: "submit", "create" }
+                if let Some(e) = create_error() {
+                    p { class: "text-red-500", "{e}" }
+                }
+            }
+            {list}
+        }
+    }
+}
+
+#[component]
+pub fn Playlist(id: i32) -> Element {
+    let base_url = use_context::<BaseUrl>();
+
+    let playlist = use_resource({
+        to_owned![base_url];
+        move || {
+            to_owned![base_url];
+            async move { client::get(&base_url, "getPlaylist", &[("id", format!("pl-{id}"))]).await }
+        }
+    });
+
+    let content = match &*playlist.read() {
+        None => rsx! { "loading" },
+        Some(Ok(ResponseBody::Playlist(pl))) => {
+            let songs = pl.entry.iter().cloned().map(SongInfo).collect::<Vec<_>>();
+            rsx! {
+                h1 { class: "text-xl", "{pl.playlist.name}" }
+                p { class: "text-slate-400",
+                    "{pl.playlist.comment.clone().unwrap_or_default()}"
+                }
+                button {
+                    class: "btn",
+                    onclick: move |_| {
+                        to_owned![base_url];
+                        async move {
+                            match client::get(&base_url, "deletePlaylist", &[("id", format!("pl-{id}"))])
+                                .await
+                            {
+                                Ok(_) => {
+                                    navigator().push(Route::Playlists {});
+                                }
+                                Err(e) => error!("delete playlist: {e}"),
+                            }
+                        }
+                    },
+                    "delete"
+                }
+                SearchResult {
+                    offset: 0,
+                    content: songs,
+                    onclick: move |song: SongInfo| {
+                        *SONG.write() = Some(song);
+                    },
+                }
+            }
+        }
+        Some(Ok(_)) => rsx! { "wrong response" },
+        Some(Err(e)) => rsx! { "could not load playlist: {e}" },
+    };
+
+    rsx! {
+        div { id: "playlist", class: "sm:ml-64 p-4", {content} }
+    }
+}
@@ -0,0 +1,433 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::Body, extract::State, http::Request, middleware::Next, response::Response, routing::get,
+    Extension, Router,
+};
+use serde::Deserialize;
+use subsonic_types::{
+    common::{DateTime, UserRating},
+    request::annotation::{Scrobble, Star, Unstar},
+    response::{
+        AlbumID3, Artist as IndexArtist, ArtistID3, Child, ErrorCode, Response as SubsonicResponse,
+        ResponseBody,
+    },
+};
+use tracing::debug;
+
+use super::{
+    bookmarks,
+    browsing::{album_visible, directory_visible, music_folders, song_visible},
+    timestamp, AppState, Params, SR,
+};
+use crate::{
+    entity::{annotation::Item, song, user},
+    indexer::db::unix_now_ms,
+};
+
+// how long an entry outlives the song it is about
+const NOW_PLAYING_GRACE: Duration = Duration::from_secs(10 * 60);
+
+// `subsonic_types::request::annotation::SetRating` doesn't accept 0, which removes the rating
+#[derive(Debug, Deserialize)]
+struct SetRating {
+    id: String,
+    rating: u32,
+}
+
+/// the client name, one of the common Subsonic parameters
+#[derive(Debug, Deserialize)]
+pub(super) struct Client {
+    #[serde(rename = "c")]
+    pub(super) name: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Playing {
+    pub(super) song: song::Model,
+    pub(super) username: String,
+    pub(super) player_id: u32,
+    pub(super) player_name: Option<String>,
+    pub(super) since: Instant,
+}
+
+impl Playing {
+    fn expired(&self) -> bool {
+        let duration = Duration::from_secs(self.song.duration.unwrap_or_default().into());
+        self.since.elapsed() > duration + NOW_PLAYING_GRACE
+    }
+}
+
+/// what is being played right now, one entry per user and client
+#[derive(Debug, Default)]
+pub(super) struct NowPlaying {
+    entries: Mutex<HashMap<(i32, Option<String>), Playing>>,
+    // player IDs stay the same for a given user and client
+    player_ids: Mutex<HashMap<(i32, Option<String>), u32>>,
+}
+
+impl NowPlaying {
+    fn set(&self, user: &user::Model, client: Option<String>, song: song::Model) {
+        let key = (user.id, client);
+        let player_id = {
+            let mut player_ids = self.player_ids.lock().unwrap();
+            let next = player_ids.len() as u32 + 1;
+            *player_ids.entry(key.clone()).or_insert(next)
+        };
+        let playing = Playing {
+            song,
+            username: user.username.clone(),
+            player_id,
+            player_name: key.1.clone(),
+            since: Instant::now(),
+        };
+        self.entries.lock().unwrap().insert(key, playing);
+    }
+
+    // a submission means the song is done
+    fn finished(&self, user: &user::Model, client: Option<String>, song_id: i32) {
+        let mut entries = self.entries.lock().unwrap();
+        let key = (user.id, client);
+        if entries.get(&key).is_some_and(|p| p.song.id == song_id) {
+            entries.remove(&key);
+        }
+    }
+
+    /// most recent first
+    pub(super) fn entries(&self) -> Vec<Playing> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, playing| !playing.expired());
+        let mut playing: Vec<_> = entries.values().cloned().collect();
+        playing.sort_by_key(|p| std::cmp::Reverse(p.since));
+        playing
+    }
+}
+
+// `time` is optional, but if given there is one per `id`
+async fn scrobble(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    Params(client): Params<Client>,
+    Params(query): Params<Scrobble>,
+) -> Result<SR, SR> {
+    if query.id.is_empty() {
+        return Err(SR::error(
+            ErrorCode::RequiredParameterMissing,
+            "required parameter is missing: id",
+        ));
+    }
+
+    let mut songs = vec![];
+    for id in &query.id {
+        match state.db.get_song(id).await {
+            Some(song) if song_visible(&state, &user, &song).await => songs.push(song),
+            _ => return Err(SR::not_found(id)),
+        }
+    }
+
+    if !query.submission.unwrap_or(true) {
+        // only the last one can actually be playing
+        if let Some(song) = songs.pop() {
+            debug!("{} is playing {}", user.username, song.id);
+            bookmarks::auto_bookmark(&state, &user, &song).await;
+            state.now_playing.set(&user, client.name, song);
+        }
+        return Ok(SR::ok(ResponseBody::Empty));
+    }
+
+    for (i, song) in songs.into_iter().enumerate() {
+        let time = query
+            .time
+            .get(i)
+            .map(|time| time.to_duration().as_millis() as i64)
+            .unwrap_or_else(unix_now_ms);
+        state.db.scrobble(user.id, song.id, time).await?;
+        state
+            .now_playing
+            .finished(&user, client.name.clone(), song.id);
+    }
+    Ok(SR::ok(ResponseBody::Empty))
+}
+
+// whether `user` may see what `item` refers to
+async fn item_visible(state: &AppState, user: &user::Model, item: Item) -> bool {
+    match item {
+        Item::Song(id) => match state.db.get_song(id.to_string()).await {
+            Some(song) => song_visible(state, user, &song).await,
+            None => false,
+        },
+        Item::Album(id) => album_visible(state, user, id).await,
+        Item::Artist(id) => {
+            let folders = music_folders(state, user, None).await;
+            state.db.get_artist(id, &folders).await.is_some()
+        }
+        Item::Directory(id) => match state.db.get_directory(id).await {
+            Some(dir) => directory_visible(state, user, &dir).await,
+            None => false,
+        },
+    }
+}
+
+async fn items(
+    state: &AppState,
+    user: &user::Model,
+    id: &[String],
+    album_id: &[String],
+    artist_id: &[String],
+) -> Result<Vec<Item>, SR> {
+    let parse = |id: &String, valid: fn(&Item) -> bool| {
+        Item::parse(id)
+            .filter(valid)
+            .ok_or_else(|| SR::not_found(id))
+    };
+    let items: Vec<_> = id
+        .iter()
+        .map(|id| parse(id, |_| true))
+        .chain(
+            album_id
+                .iter()
+                .map(|id| parse(id, |item| matches!(item, Item::Album(_)))),
+        )
+        .chain(
+            artist_id
+                .iter()
+                .map(|id| parse(id, |item| matches!(item, Item::Artist(_)))),
+        )
+        .collect::<Result<_, _>>()?;
+    if items.is_empty() {
+        return Err(SR::error(
+            ErrorCode::RequiredParameterMissing,
+            "required parameter is missing: id, albumId or artistId",
+        ));
+    }
+    for item in &items {
+        if !item_visible(state, user, *item).await {
+            return Err(SR::not_found(item));
+        }
+    }
+    Ok(items)
+}
+
+async fn star(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    query: Params<Star>,
+) -> Result<SR, SR> {
+    let items = items(&state, &user, &query.id, &query.album_id, &query.artist_id).await?;
+    state.db.set_starred(user.id, &items, true).await?;
+    Ok(SR::ok(ResponseBody::Empty))
+}
+
+async fn unstar(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    query: Params<Unstar>,
+) -> Result<SR, SR> {
+    let items = items(&state, &user, &query.id, &query.album_id, &query.artist_id).await?;
+    state.db.set_starred(user.id, &items, false).await?;
+    Ok(SR::ok(ResponseBody::Empty))
+}
+
+async fn set_rating(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    query: Params<SetRating>,
+) -> Result<SR, SR> {
+    let item = match Item::parse(&query.id) {
+        Some(item) if item_visible(&state, &user, item).await => item,
+        _ => return Err(SR::not_found(&query.id)),
+    };
+    let rating = match query.rating {
+        0 => None,
+        rating @ 1..=5 => Some(rating),
+        _ => {
+            return Err(SR::error(
+                ErrorCode::Generic,
+                "rating must be between 0 and 5",
+            ))
+        }
+    };
+    state.db.set_rating(user.id, item, rating).await?;
+    Ok(SR::ok(ResponseBody::Empty))
+}
+
+// the annotatable parts of a response element
+struct Annotated<'a> {
+    id: &'a str,
+    starred: &'a mut Option<DateTime>,
+    // not every element has a rating
+    user_rating: Option<&'a mut Option<UserRating>>,
+}
+
+impl<'a> From<&'a mut Child> for Annotated<'a> {
+    fn from(child: &'a mut Child) -> Self {
+        Annotated {
+            id: &child.id,
+            starred: &mut child.starred,
+            user_rating: Some(&mut child.user_rating),
+        }
+    }
+}
+
+impl<'a> From<&'a mut AlbumID3> for Annotated<'a> {
+    fn from(album: &'a mut AlbumID3) -> Self {
+        Annotated {
+            id: &album.id,
+            starred: &mut album.starred,
+            user_rating: None,
+        }
+    }
+}
+
+impl<'a> From<&'a mut ArtistID3> for Annotated<'a> {
+    fn from(artist: &'a mut ArtistID3) -> Self {
+        Annotated {
+            id: &artist.id,
+            starred: &mut artist.starred,
+            user_rating: None,
+        }
+    }
+}
+
+impl<'a> From<&'a mut IndexArtist> for Annotated<'a> {
+    fn from(artist: &'a mut IndexArtist) -> Self {
+        Annotated {
+            id: &artist.id,
+            starred: &mut artist.starred,
+            user_rating: Some(&mut artist.user_rating),
+        }
+    }
+}
+
+fn annotated<'a, T>(elements: &'a mut [T]) -> impl Iterator<Item = Annotated<'a>>
+where
+    &'a mut T: Into<Annotated<'a>>,
+{
+    elements.iter_mut().map(Into::into)
+}
+
+// every song, album, artist and directory in `body`
+fn annotatable(body: &mut ResponseBody) -> Vec<Annotated<'_>> {
+    match body {
+        ResponseBody::Indexes(indexes) => annotated(&mut indexes.shortcut)
+            .chain(
+                indexes
+                    .index
+                    .iter_mut()
+                    .flat_map(|index| annotated(&mut index.artist)),
+            )
+            .chain(annotated(&mut indexes.child))
+            .collect(),
+        ResponseBody::Directory(dir) => annotated(&mut dir.child)
+            .chain([Annotated {
+                id: &dir.id,
+                starred: &mut dir.starred,
+                user_rating: Some(&mut dir.user_rating),
+            }])
+            .collect(),
+        ResponseBody::Artists(artists) => artists
+            .index
+            .iter_mut()
+            .flat_map(|index| annotated(&mut index.artist))
+            .collect(),
+        ResponseBody::Artist(artist) => annotated(&mut artist.album)
+            .chain([(&mut artist.artist).into()])
+            .collect(),
+        ResponseBody::Album(album) => annotated(&mut album.song)
+            .chain([(&mut album.album).into()])
+            .collect(),
+        ResponseBody::Song(song) => vec![song.into()],
+        ResponseBody::NowPlaying(now_playing) => now_playing
+            .entry
+            .iter_mut()
+            .map(|entry| (&mut entry.child).into())
+            .collect(),
+        ResponseBody::SearchResult(result) => annotated(&mut result.matches).collect(),
+        ResponseBody::SearchResult2(result) => annotated(&mut result.artist)
+            .chain(annotated(&mut result.album))
+            .chain(annotated(&mut result.song))
+            .collect(),
+        ResponseBody::SearchResult3(result) => annotated(&mut result.artist)
+            .chain(annotated(&mut result.album))
+            .chain(annotated(&mut result.song))
+            .collect(),
+        ResponseBody::Playlist(playlist) => annotated(&mut playlist.entry).collect(),
+        ResponseBody::AlbumList(list) => annotated(&mut list.album).collect(),
+        ResponseBody::AlbumList2(list) => annotated(&mut list.album).collect(),
+        ResponseBody::RandomSongs(songs) | ResponseBody::SongsByGenre(songs) => {
+            annotated(&mut songs.song).collect()
+        }
+        ResponseBody::Bookmarks(bookmarks) => bookmarks
+            .bookmark
+            .iter_mut()
+            .flat_map(|bookmark| annotated(&mut bookmark.entry))
+            .collect(),
+        ResponseBody::PlayQueue(queue) => annotated(&mut queue.entry).collect(),
+        ResponseBody::Shares(shares) => shares
+            .share
+            .iter_mut()
+            .flat_map(|share| annotated(&mut share.entry))
+            .collect(),
+        ResponseBody::Starred(starred) => annotated(&mut starred.artist)
+            .chain(annotated(&mut starred.album))
+            .chain(annotated(&mut starred.song))
+            .collect(),
+        ResponseBody::Starred2(starred) => annotated(&mut starred.artist)
+            .chain(annotated(&mut starred.album))
+            .chain(annotated(&mut starred.song))
+            .collect(),
+        ResponseBody::SimilarSongs(songs) => annotated(&mut songs.song).collect(),
+        ResponseBody::SimilarSongs2(songs) => annotated(&mut songs.song).collect(),
+        ResponseBody::TopSongs(songs) => annotated(&mut songs.song).collect(),
+        _ => vec![],
+    }
+}
+
+async fn annotate(state: &AppState, user: &user::Model, response: &mut SubsonicResponse) {
+    let mut elements = annotatable(&mut response.body);
+    let items: Vec<_> = elements
+        .iter()
+        .filter_map(|element| Item::parse(element.id))
+        .collect();
+    let annotations = state.db.get_annotations(user.id, &items).await;
+    if annotations.is_empty() {
+        return;
+    }
+
+    for element in &mut elements {
+        let Some(annotation) = Item::parse(element.id).and_then(|item| annotations.get(&item))
+        else {
+            continue;
+        };
+        *element.starred = annotation.starred.map(timestamp);
+        if let Some(user_rating) = element.user_rating.as_mut() {
+            **user_rating = annotation.rating.and_then(|r| UserRating::new(r).ok());
+        }
+    }
+}
+
+/// fills in the stars and ratings of the authenticated user in `SR` responses
+pub(super) async fn annotation_middleware(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    if let Some(subsonic_response) = response.extensions_mut().get_mut::<Box<SubsonicResponse>>() {
+        annotate(&state, &user, subsonic_response).await;
+    }
+    response
+}
+
+pub(super) fn router() -> Router<AppState> {
+    Router::new()
+        .route("/scrobble.view", get(scrobble))
+        .route("/star.view", get(star))
+        .route("/unstar.view", get(unstar))
+        .route("/setRating.view", get(set_rating))
+}
@@ -0,0 +1,183 @@
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::Request,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use subsonic_types::response::ErrorCode;
+use tracing::{debug, warn};
+
+use super::{AppState, SR};
+use crate::entity::user::{self, Role};
+
+// OpenSubsonic error codes not covered by `ErrorCode`
+const CONFLICTING_AUTH: u32 = 43;
+
+// what endpoints need beyond logging in, by name without `.view`. Handlers that
+// depend on who is affected (`getUser`, `changePassword`) check on their own
+const ROLES: &[(&str, Role)] = &[
+    ("getUsers", Role::Admin),
+    ("createUser", Role::Admin),
+    ("updateUser", Role::Admin),
+    ("deleteUser", Role::Admin),
+    ("startScan", Role::Admin),
+    ("createInternetRadioStation", Role::Admin),
+    ("updateInternetRadioStation", Role::Admin),
+    ("deleteInternetRadioStation", Role::Admin),
+    ("stream", Role::Stream),
+    ("radioProxy", Role::Stream),
+    ("download", Role::Download),
+    ("createPlaylist", Role::Playlist),
+    ("updatePlaylist", Role::Playlist),
+    ("deletePlaylist", Role::Playlist),
+    ("setRating", Role::Comment),
+    ("createShare", Role::Share),
+    ("updateShare", Role::Share),
+    ("deleteShare", Role::Share),
+];
+
+/// the authentication related subset of the common Subsonic parameters
+#[derive(Debug, Deserialize)]
+pub(super) struct Credentials {
+    #[serde(rename = "u")]
+    username: Option<String>,
+    #[serde(rename = "p")]
+    password: Option<String>,
+    #[serde(rename = "t")]
+    token: Option<String>,
+    #[serde(rename = "s")]
+    salt: Option<String>,
+    #[serde(rename = "apiKey")]
+    api_key: Option<String>,
+}
+
+/// `enc:`-prefixed passwords are hex encoded
+pub(super) fn decode_password(password: &str) -> Option<String> {
+    match password.strip_prefix("enc:") {
+        Some(hex) => hex::decode(hex)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok()),
+        None => Some(password.to_string()),
+    }
+}
+
+fn token_matches(password: &str, salt: &str, token: &str) -> bool {
+    let expected = format!("{:x}", md5::compute(format!("{password}{salt}")));
+    expected.eq_ignore_ascii_case(token)
+}
+
+async fn authenticate(state: &AppState, creds: Credentials) -> Result<user::Model, SR> {
+    let wrong_credentials = || {
+        SR::error(
+            ErrorCode::WrongUsernameOrPassword,
+            "wrong username or password",
+        )
+    };
+
+    if let Some(api_key) = creds.api_key {
+        if creds.username.is_some() || creds.password.is_some() || creds.token.is_some() {
+            return Err(SR::error(
+                ErrorCode::Other(CONFLICTING_AUTH),
+                "apiKey must not be combined with other credentials",
+            ));
+        }
+        return state
+            .db
+            .get_user_by_api_key(&api_key)
+            .await
+            .ok_or_else(wrong_credentials);
+    }
+
+    let Some(username) = creds.username else {
+        return Err(SR::error(
+            ErrorCode::RequiredParameterMissing,
+            "required parameter is missing: u",
+        ));
+    };
+    let Some(user) = state.db.get_user(&username).await else {
+        debug!("unknown user {username}");
+        return Err(wrong_credentials());
+    };
+
+    match (creds.token, creds.salt, creds.password) {
+        (Some(token), Some(salt), _) => {
+            let Some(password) = user.password.as_deref() else {
+                return Err(SR::error(
+                    ErrorCode::TokenAuthenticationNotSupported,
+                    "token authentication not supported for this user",
+                ));
+            };
+            if token_matches(password, &salt, &token) {
+                Ok(user)
+            } else {
+                Err(wrong_credentials())
+            }
+        }
+        (_, _, Some(password)) => {
+            let given = decode_password(&password);
+            if given.is_some() && given.as_deref() == user.password.as_deref() {
+                Ok(user)
+            } else {
+                Err(wrong_credentials())
+            }
+        }
+        _ => Err(SR::error(
+            ErrorCode::RequiredParameterMissing,
+            "required parameter is missing: p or t/s",
+        )),
+    }
+}
+
+fn required_role(path: &str) -> Option<Role> {
+    let endpoint = path.trim_start_matches('/');
+    let endpoint = endpoint.strip_suffix(".view").unwrap_or(endpoint);
+    ROLES
+        .iter()
+        .find(|(name, _)| *name == endpoint)
+        .map(|(_, role)| *role)
+}
+
+pub(super) async fn auth_middleware(
+    State(state): State<AppState>,
+    Query(creds): Query<Credentials>,
+    mut request: Request<Body>,
+    next: Next,
+) -> Response {
+    let user = match authenticate(&state, creds).await {
+        Ok(user) => user,
+        Err(e) => {
+            warn!("authentication failed for {}", request.uri().path());
+            return e.into_response();
+        }
+    };
+    if let Some(role) = required_role(request.uri().path()) {
+        if let Err(e) = require(&user, role) {
+            debug!(
+                "{} lacks {} for {}",
+                user.username,
+                role.name(),
+                request.uri().path()
+            );
+            return e.into_response();
+        }
+    }
+    // handlers get at the authenticated user via `Extension<user::Model>`
+    request.extensions_mut().insert(user);
+    next.run(request).await
+}
+
+pub(super) fn require(user: &user::Model, role: Role) -> Result<(), SR> {
+    if user.has(role) {
+        return Ok(());
+    }
+    Err(SR::error(
+        ErrorCode::UserNotAuthorizedForTheGivenOperation,
+        format!(
+            "{} is not authorized: {} required",
+            user.username,
+            role.name()
+        ),
+    ))
+}
@@ -0,0 +1,286 @@
+use axum::{extract::State, routing::get, Extension, Router};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use subsonic_types::{
+    common::{Milliseconds, Version},
+    request::bookmark::{CreateBookmark, DeleteBookmark, SavePlayQueue},
+    response::{
+        Bookmark, Bookmarks, ErrorCode, PlayQueue, Response as SubsonicResponse, ResponseBody,
+    },
+};
+use tracing::error;
+
+use super::{
+    annotation::Client,
+    browsing::{music_folders, song_visible},
+    format::merge,
+    open_subsonic::song_fields,
+    playlists::song_ids,
+    timestamp, AppState, Params, SR,
+};
+use crate::{
+    entity::{song, user},
+    indexer::types::PlayQueueWithEntries,
+};
+
+// OpenSubsonic `indexBasedQueue`, for queues holding a song more than once
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SavePlayQueueByIndex {
+    #[serde(default)]
+    id: Vec<String>,
+    current_index: Option<u32>,
+    position: Option<u64>,
+}
+
+fn play_queue(queue: PlayQueueWithEntries, user: &user::Model) -> PlayQueue {
+    let PlayQueueWithEntries { play_queue, songs } = queue;
+    PlayQueue {
+        current: play_queue
+            .current
+            .and_then(|index| songs.get(index as usize))
+            .map(|song| song.id as u64),
+        position: Some(Milliseconds::new(play_queue.position.max(0) as u64)),
+        username: user.username.clone(),
+        changed: timestamp(play_queue.changed),
+        changed_by: play_queue.changed_by,
+        entry: songs.into_iter().map(|song| song.into()).collect(),
+    }
+}
+
+// `subsonic_types` only knows `playQueue`, so `playQueueByIndex` is made from its JSON
+fn play_queue_by_index(queue: PlayQueue, current_index: Option<u32>) -> Result<Value, SR> {
+    let value = SubsonicResponse::ok(Version::LATEST, ResponseBody::PlayQueue(queue))
+        .to_json()
+        .ok()
+        .and_then(|json| serde_json::from_str::<Value>(&json).ok());
+    let Some(mut value) = value else {
+        error!("serializing play queue");
+        return Err(SR::error(
+            ErrorCode::Generic,
+            "could not serialize play queue",
+        ));
+    };
+    let mut queue = value["subsonic-response"]["playQueue"].take();
+    if let Value::Object(fields) = &mut queue {
+        fields.remove("current");
+        if let Some(index) = current_index {
+            fields.insert("currentIndex".to_string(), index.into());
+        }
+    }
+    Ok(queue)
+}
+
+async fn save(
+    state: &AppState,
+    user: &user::Model,
+    client: Client,
+    ids: &[String],
+    current: Option<u32>,
+    position: Option<u64>,
+) -> Result<SR, SR> {
+    let songs = song_ids(state, user, ids).await?;
+    state
+        .db
+        .save_play_queue(
+            user.id,
+            songs,
+            current,
+            position.unwrap_or_default().try_into().unwrap_or(i64::MAX),
+            client.name.unwrap_or_default(),
+        )
+        .await?;
+    Ok(SR::ok(ResponseBody::Empty))
+}
+
+// without `id`, the queue is cleared
+async fn save_play_queue(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    Params(client): Params<Client>,
+    Params(query): Params<SavePlayQueue>,
+) -> Result<SR, SR> {
+    // the first occurrence, if the song is queued more than once
+    let current = query
+        .current
+        .as_ref()
+        .and_then(|current| query.id.iter().position(|id| id == current))
+        .map(|index| index as u32);
+    let position = query
+        .position
+        .map(|position| position.to_duration().as_millis() as u64);
+    save(&state, &user, client, &query.id, current, position).await
+}
+
+async fn save_play_queue_by_index(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    Params(client): Params<Client>,
+    Params(query): Params<SavePlayQueueByIndex>,
+) -> Result<SR, SR> {
+    if query
+        .current_index
+        .is_some_and(|index| index as usize >= query.id.len())
+    {
+        return Err(SR::error(
+            ErrorCode::Generic,
+            "currentIndex is out of range",
+        ));
+    }
+    save(
+        &state,
+        &user,
+        client,
+        &query.id,
+        query.current_index,
+        query.position,
+    )
+    .await
+}
+
+// `user`'s play queue without the songs they may no longer see, its current
+// song keeps its place among the rest
+async fn visible_play_queue(state: &AppState, user: &user::Model) -> Option<PlayQueueWithEntries> {
+    let mut queue = state.db.get_play_queue(user.id).await?;
+    let folders = music_folders(state, user, None).await;
+    let visible: Vec<_> = queue
+        .songs
+        .iter()
+        .map(|song| song.folder.is_some_and(|folder| folders.contains(&folder)))
+        .collect();
+    queue.play_queue.current = queue
+        .play_queue
+        .current
+        .filter(|index| visible.get(*index as usize) == Some(&true))
+        .map(|index| visible[..index as usize].iter().filter(|v| **v).count() as u32);
+    let mut visible = visible.into_iter();
+    queue.songs.retain(|_| visible.next().unwrap_or_default());
+    Some(queue)
+}
+
+// an empty response if nothing was saved yet
+async fn get_play_queue(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+) -> SR {
+    match visible_play_queue(&state, &user).await {
+        Some(queue) => {
+            let queue = play_queue(queue, &user);
+            let fields = song_fields(&state.db, &queue.entry).await;
+            SR::ok(ResponseBody::PlayQueue(queue)).with(json!({ "playQueue": { "entry": fields } }))
+        }
+        None => SR::ok(ResponseBody::Empty),
+    }
+}
+
+async fn get_play_queue_by_index(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+) -> Result<SR, SR> {
+    let Some(queue) = visible_play_queue(&state, &user).await else {
+        return Ok(SR::ok(ResponseBody::Empty));
+    };
+    let current_index = queue
+        .play_queue
+        .current
+        .filter(|index| (*index as usize) < queue.songs.len());
+    let queue = play_queue(queue, &user);
+    let fields = song_fields(&state.db, &queue.entry).await;
+    let mut queue = play_queue_by_index(queue, current_index)?;
+    merge(&mut queue, json!({ "entry": fields }));
+    Ok(SR::ok(ResponseBody::Empty).with(json!({ "playQueueByIndex": queue })))
+}
+
+async fn create_bookmark(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    Params(query): Params<CreateBookmark>,
+) -> Result<SR, SR> {
+    let song = match state.db.get_song(&query.id).await {
+        Some(song) if song_visible(&state, &user, &song).await => song,
+        _ => return Err(SR::not_found(&query.id)),
+    };
+    let position = query.position.to_duration().as_millis();
+    state
+        .db
+        .set_bookmark(
+            user.id,
+            song.id,
+            position.try_into().unwrap_or(i64::MAX),
+            query.comment,
+            false,
+        )
+        .await?;
+    Ok(SR::ok(ResponseBody::Empty))
+}
+
+async fn get_bookmarks(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+) -> SR {
+    let bookmark: Vec<_> = state
+        .db
+        .get_bookmarks(user.id)
+        .await
+        .into_iter()
+        .map(|(bookmark, song)| Bookmark {
+            position: bookmark.position.max(0) as u64,
+            username: user.username.clone(),
+            comment: bookmark.comment,
+            created: timestamp(bookmark.created),
+            changed: timestamp(bookmark.changed),
+            entry: vec![song.into()],
+        })
+        .collect();
+    let fields = song_fields(
+        &state.db,
+        bookmark.iter().flat_map(|bookmark| &bookmark.entry),
+    )
+    .await;
+    let entries: Vec<_> = fields
+        .into_iter()
+        .map(|fields| json!({ "entry": [fields] }))
+        .collect();
+    SR::ok(ResponseBody::Bookmarks(Bookmarks { bookmark }))
+        .with(json!({ "bookmarks": { "bookmark": entries } }))
+}
+
+async fn delete_bookmark(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    Params(query): Params<DeleteBookmark>,
+) -> Result<SR, SR> {
+    let Ok(song_id) = query.id.parse() else {
+        return Err(SR::not_found(&query.id));
+    };
+    state.db.delete_bookmark(user.id, song_id).await?;
+    Ok(SR::ok(ResponseBody::Empty))
+}
+
+/// bookmarks `song` at its start when it's long enough to be worth resuming (see
+/// `[bookmarks]` in the config), unless it already is bookmarked
+pub(super) async fn auto_bookmark(state: &AppState, user: &user::Model, song: &song::Model) {
+    let Some(minutes) = state.bookmarks.auto_minutes else {
+        return;
+    };
+    let long = song
+        .duration
+        .is_some_and(|duration| u64::from(duration) > u64::from(minutes) * 60);
+    if !long {
+        return;
+    }
+    if let Err(e) = state.db.set_bookmark(user.id, song.id, 0, None, true).await {
+        error!("auto bookmark {}: {e:?}", song.id);
+    }
+}
+
+pub(super) fn router() -> Router<AppState> {
+    Router::new()
+        .route("/savePlayQueue.view", get(save_play_queue))
+        .route("/getPlayQueue.view", get(get_play_queue))
+        .route("/savePlayQueueByIndex.view", get(save_play_queue_by_index))
+        .route("/getPlayQueueByIndex.view", get(get_play_queue_by_index))
+        .route("/createBookmark.view", get(create_bookmark))
+        .route("/getBookmarks.view", get(get_bookmarks))
+        .route("/deleteBookmark.view", get(delete_bookmark))
+}
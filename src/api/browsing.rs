@@ -0,0 +1,321 @@
+use std::collections::{BTreeMap, HashMap};
+
+use axum::{extract::State, routing::get, Extension, Router};
+use camino::Utf8Path;
+use serde_json::json;
+use subsonic_types::{
+    common::Milliseconds,
+    request::browsing::{GetAlbum, GetArtist, GetIndexes, GetMusicDirectory},
+    response::{
+        AlbumWithSongsID3, Artist as IndexArtist, ArtistWithAlbumsID3, Child, Directory, Genres,
+        Index, Indexes, MusicFolder, MusicFolders, ResponseBody,
+    },
+};
+
+use super::{open_subsonic::song_fields, AppState, Params, SR};
+use crate::entity::{album, artist, cover_art, directory, music_folder, song, user};
+
+// not taken into account when sorting directories into `getIndexes` buckets
+const IGNORED_ARTICLES: &str = "The El La Los Las Le Les";
+
+fn sort_name(name: &str) -> &str {
+    IGNORED_ARTICLES
+        .split(' ')
+        .find_map(|article| {
+            let prefix = name.get(..article.len() + 1)?;
+            (prefix.eq_ignore_ascii_case(&format!("{article} "))).then(|| &name[prefix.len()..])
+        })
+        .unwrap_or(name)
+}
+
+fn index_name(name: &str) -> String {
+    match sort_name(name).chars().next() {
+        Some(c) if c.is_alphabetic() => c.to_uppercase().collect(),
+        _ => "#".to_string(),
+    }
+}
+
+/// the music folders `user` may see, all of them unless they are restricted
+pub(super) async fn visible_folders(
+    state: &AppState,
+    user: &user::Model,
+) -> Vec<music_folder::Model> {
+    let restricted = state.db.get_user_folders(user.id).await;
+    state
+        .indexer
+        .music_folders()
+        .iter()
+        .filter(|folder| restricted.is_empty() || restricted.contains(&folder.id))
+        .cloned()
+        .collect()
+}
+
+/// the IDs of the folders to look in: those `user` may see, or only `id` of
+/// them, if given. Unknown and forbidden ones are as good as empty
+pub(super) async fn music_folders(
+    state: &AppState,
+    user: &user::Model,
+    id: Option<&str>,
+) -> Vec<i32> {
+    visible_folders(state, user)
+        .await
+        .into_iter()
+        .map(|folder| folder.id)
+        .filter(|folder| id.is_none_or(|id| *id == folder.to_string()))
+        .collect()
+}
+
+// whether `path` is in one of `folders`
+fn in_folders(path: &str, folders: &[music_folder::Model]) -> bool {
+    folders
+        .iter()
+        .any(|folder| Utf8Path::new(path).starts_with(&folder.path))
+}
+
+/// whether `song` is in a folder `user` may see
+pub(super) async fn song_visible(state: &AppState, user: &user::Model, song: &song::Model) -> bool {
+    let folders = music_folders(state, user, None).await;
+    song.folder.is_some_and(|folder| folders.contains(&folder))
+}
+
+/// drops the songs of `songs` that `user` may not see
+pub(super) async fn retain_visible(
+    state: &AppState,
+    user: &user::Model,
+    songs: &mut Vec<song::Model>,
+) {
+    let folders = music_folders(state, user, None).await;
+    songs.retain(|song| song.folder.is_some_and(|folder| folders.contains(&folder)));
+}
+
+/// whether any song of album `id` is
+pub(super) async fn album_visible(state: &AppState, user: &user::Model, id: i32) -> bool {
+    let folders = music_folders(state, user, None).await;
+    state
+        .db
+        .get_album_songs(id)
+        .await
+        .iter()
+        .any(|song| song.folder.is_some_and(|folder| folders.contains(&folder)))
+}
+
+/// whether `dir` is one of the folders `user` may see, or below one
+pub(super) async fn directory_visible(
+    state: &AppState,
+    user: &user::Model,
+    dir: &directory::Model,
+) -> bool {
+    in_folders(&dir.path, &visible_folders(state, user).await)
+}
+
+/// whether the song `cover_art` is embedded in, or the image file it was
+/// copied from, is
+pub(super) async fn cover_art_visible(
+    state: &AppState,
+    user: &user::Model,
+    cover_art: &cover_art::Model,
+) -> bool {
+    match (cover_art.song, &cover_art.path) {
+        (Some(song), _) => match state.db.get_song(song.to_string()).await {
+            Some(song) => song_visible(state, user, &song).await,
+            None => false,
+        },
+        (None, Some(path)) => in_folders(path, &visible_folders(state, user).await),
+        (None, None) => false,
+    }
+}
+
+pub(super) fn directory_child(dir: directory::Model, cover_art: &HashMap<String, i32>) -> Child {
+    Child {
+        id: directory::format_id(dir.id),
+        parent: dir.parent.map(directory::format_id),
+        is_dir: true,
+        cover_art: cover_art.get(&dir.path).map(|id| format!("{id}")),
+        title: dir.name,
+        ..Default::default()
+    }
+}
+
+async fn get_album(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    query: Params<GetAlbum>,
+) -> Result<SR, SR> {
+    let Some(id) = album::parse_id(&query.id) else {
+        return Err(SR::not_found(&query.id));
+    };
+    if !album_visible(&state, &user, id).await {
+        return Err(SR::not_found(&query.id));
+    }
+    let Some(album) = state.db.get_album(id).await else {
+        return Err(SR::not_found(&query.id));
+    };
+    let song: Vec<Child> = state
+        .db
+        .get_album_songs(id)
+        .await
+        .into_iter()
+        .map(|song| song.into())
+        .collect();
+    let fields = song_fields(&state.db, &song).await;
+
+    Ok(SR::ok(ResponseBody::Album(AlbumWithSongsID3 {
+        album: album.into(),
+        song,
+    }))
+    .with(json!({ "album": { "song": fields } })))
+}
+
+async fn get_artist(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    query: Params<GetArtist>,
+) -> Result<SR, SR> {
+    let Some(id) = artist::parse_id(&query.id) else {
+        return Err(SR::not_found(&query.id));
+    };
+    let folders = music_folders(&state, &user, None).await;
+    let Some(artist) = state.db.get_artist(id, &folders).await else {
+        return Err(SR::not_found(&query.id));
+    };
+    let albums = state.db.get_artist_albums(id, &folders).await;
+
+    Ok(SR::ok(ResponseBody::Artist(ArtistWithAlbumsID3 {
+        artist: artist.into(),
+        album: albums.into_iter().map(|album| album.into()).collect(),
+    })))
+}
+
+async fn get_music_folders(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+) -> SR {
+    let music_folder = visible_folders(&state, &user)
+        .await
+        .into_iter()
+        .map(|folder| MusicFolder {
+            id: folder.id as u32,
+            name: Some(folder.name),
+        })
+        .collect();
+    SR::ok(ResponseBody::MusicFolders(MusicFolders { music_folder }))
+}
+
+// the top level directories of the visible media paths, bucketed by first letter
+async fn get_indexes(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    query: Params<GetIndexes>,
+) -> Result<SR, SR> {
+    let id = query.music_folder_id.as_deref();
+    let folders: Vec<_> = visible_folders(&state, &user)
+        .await
+        .into_iter()
+        .filter(|folder| id.is_none_or(|id| id == folder.id.to_string()))
+        .map(|folder| folder.path)
+        .collect();
+    if let (Some(id), true) = (id, folders.is_empty()) {
+        return Err(SR::not_found(id));
+    }
+
+    let modified = state.db.directories_modified().await;
+    let mut indexes = Indexes {
+        last_modified: Milliseconds::new(modified as _),
+        ignored_articles: IGNORED_ARTICLES.to_string(),
+        ..Default::default()
+    };
+    if let Some(since) = &query.if_modified_since {
+        if since.to_duration().as_millis() >= modified as u128 {
+            return Ok(SR::ok(ResponseBody::Indexes(indexes)));
+        }
+    }
+
+    let roots: Vec<_> = state
+        .db
+        .get_root_directories()
+        .await
+        .into_iter()
+        .filter(|root| folders.contains(&root.path))
+        .map(|root| root.id)
+        .collect();
+    let mut dirs = state.db.get_subdirectories(&roots).await;
+    dirs.sort_by_cached_key(|dir| sort_name(&dir.name).to_lowercase());
+
+    let mut buckets = BTreeMap::<String, Vec<IndexArtist>>::new();
+    for dir in dirs {
+        buckets
+            .entry(index_name(&dir.name))
+            .or_default()
+            .push(IndexArtist {
+                id: directory::format_id(dir.id),
+                name: dir.name,
+                ..Default::default()
+            });
+    }
+    indexes.index = buckets
+        .into_iter()
+        .map(|(name, artist)| Index { name, artist })
+        .collect();
+    indexes.child = state
+        .db
+        .get_directory_songs(&roots)
+        .await
+        .into_iter()
+        .map(|song| song.into())
+        .collect();
+    let fields = song_fields(&state.db, &indexes.child).await;
+
+    Ok(SR::ok(ResponseBody::Indexes(indexes)).with(json!({ "indexes": { "child": fields } })))
+}
+
+async fn get_music_directory(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    query: Params<GetMusicDirectory>,
+) -> Result<SR, SR> {
+    let Some(id) = directory::parse_id(&query.id) else {
+        return Err(SR::not_found(&query.id));
+    };
+    let dir = match state.db.get_directory(id).await {
+        Some(dir) if directory_visible(&state, &user, &dir).await => dir,
+        _ => return Err(SR::not_found(&query.id)),
+    };
+
+    let subdirs = state.db.get_subdirectories(&[id]).await;
+    let cover_art = state.db.get_directory_cover_art(&subdirs).await;
+    let songs = state.db.get_directory_songs(&[id]).await;
+
+    let child: Vec<_> = subdirs
+        .into_iter()
+        .map(|subdir| directory_child(subdir, &cover_art))
+        .chain(songs.into_iter().map(|song| song.into()))
+        .collect();
+    let fields = song_fields(&state.db, &child).await;
+
+    Ok(SR::ok(ResponseBody::Directory(Directory {
+        id: directory::format_id(dir.id),
+        parent: dir.parent.map(directory::format_id),
+        name: dir.name,
+        child,
+        ..Default::default()
+    }))
+    .with(json!({ "directory": { "child": fields } })))
+}
+
+async fn get_genres(State(state): State<AppState>, Extension(user): Extension<user::Model>) -> SR {
+    let folders = music_folders(&state, &user, None).await;
+    let genre = state.db.get_genres(&folders).await;
+    SR::ok(ResponseBody::Genres(Genres {
+        genre: genre.into_iter().map(|genre| genre.into()).collect(),
+    }))
+}
+
+pub(super) fn router() -> Router<AppState> {
+    Router::new()
+        .route("/getAlbum.view", get(get_album))
+        .route("/getArtist.view", get(get_artist))
+        .route("/getGenres.view", get(get_genres))
+        .route("/getIndexes.view", get(get_indexes))
+        .route("/getMusicFolders.view", get(get_music_folders))
+        .route("/getMusicDirectory.view", get(get_music_directory))
+}
@@ -0,0 +1,301 @@
+use std::io;
+
+use axum::{
+    body::Body,
+    extract::Query,
+    http::{header::CONTENT_TYPE, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use quick_xml::{
+    events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event},
+    Writer,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use subsonic_types::response::{ErrorCode, Response as SubsonicResponse};
+use tracing::error;
+
+use super::{open_subsonic, Extra, SR};
+
+const XMLNS: &str = "http://subsonic.org/restapi";
+
+#[derive(Debug, Deserialize)]
+pub(super) struct FormatParams {
+    f: Option<String>,
+    callback: Option<String>,
+}
+
+enum Format {
+    Xml,
+    Json,
+    Jsonp(String),
+}
+
+impl Format {
+    fn from_params(params: FormatParams) -> Result<Self, SR> {
+        match params.f.as_deref() {
+            None | Some("xml") => Ok(Format::Xml),
+            Some("json") => Ok(Format::Json),
+            Some("jsonp") => match params.callback {
+                // the callback ends up verbatim in a script, so keep it to identifiers
+                Some(cb)
+                    if !cb.is_empty()
+                        && cb
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || "_.$".contains(c)) =>
+                {
+                    Ok(Format::Jsonp(cb))
+                }
+                Some(_) => Err(SR::error(ErrorCode::Generic, "invalid callback")),
+                None => Err(SR::error(
+                    ErrorCode::RequiredParameterMissing,
+                    "required parameter is missing: callback",
+                )),
+            },
+            Some(other) => Err(SR::error(
+                ErrorCode::Generic,
+                format!("unsupported format: {other}"),
+            )),
+        }
+    }
+
+    fn respond(&self, response: &SubsonicResponse, extra: Option<Extra>) -> Response {
+        match to_value(response, extra) {
+            Ok(value) => self.render(&value),
+            Err(e) => {
+                error!("serializing response: {e:?}");
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+
+    fn render(&self, value: &Value) -> Response {
+        match self {
+            Format::Json => {
+                ([(CONTENT_TYPE, "application/json")], value.to_string()).into_response()
+            }
+            Format::Jsonp(callback) => (
+                [(CONTENT_TYPE, "application/javascript")],
+                format!("{callback}({value});"),
+            )
+                .into_response(),
+            Format::Xml => match to_xml(value) {
+                Ok(xml) => ([(CONTENT_TYPE, "text/xml; charset=utf-8")], xml).into_response(),
+                Err(e) => {
+                    error!("converting response to XML: {e:?}");
+                    StatusCode::INTERNAL_SERVER_ERROR.into_response()
+                }
+            },
+        }
+    }
+}
+
+// the response as JSON, with everything `subsonic_types` has no fields for added
+fn to_value(response: &SubsonicResponse, extra: Option<Extra>) -> io::Result<Value> {
+    let json = response.to_json().map_err(io::Error::other)?;
+    let mut value: Value = serde_json::from_str(&json)?;
+    if let Some(body) = value.get_mut("subsonic-response") {
+        merge(body, open_subsonic::server_info());
+        if let Some(Extra(extra)) = extra {
+            merge(body, extra);
+        }
+    }
+    Ok(value)
+}
+
+// objects are merged key by key, arrays item by item, anything else is replaced
+pub(super) fn merge(target: &mut Value, extra: Value) {
+    match (target, extra) {
+        (Value::Object(target), Value::Object(extra)) => {
+            for (key, value) in extra {
+                match target.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(target), Value::Array(extra)) => {
+            for (existing, value) in target.iter_mut().zip(extra) {
+                merge(existing, value);
+            }
+        }
+        (target, extra) => *target = extra,
+    }
+}
+
+// Subsonic JSON is a direct mapping of the XML schema: scalars are attributes,
+// objects and arrays are (repeated) child elements, `value` is text content.
+fn write_element(
+    writer: &mut Writer<Vec<u8>>,
+    name: &str,
+    value: &Value,
+    extra_attributes: &[(&str, &str)],
+) -> io::Result<()> {
+    let map = match value {
+        Value::Object(map) => map,
+        Value::Null => return Ok(()),
+        scalar => {
+            writer.write_event(Event::Start(BytesStart::new(name)))?;
+            writer.write_event(Event::Text(BytesText::new(&scalar_to_string(scalar))))?;
+            return writer.write_event(Event::End(BytesEnd::new(name)));
+        }
+    };
+
+    let mut start = BytesStart::new(name);
+    start.extend_attributes(extra_attributes.iter().copied());
+    let mut text = None;
+    let mut children = vec![];
+    for (key, val) in map {
+        match val {
+            Value::Null => {}
+            Value::Object(_) => children.push((key, val)),
+            Value::Array(items) => children.extend(items.iter().map(|item| (key, item))),
+            scalar if key == "value" => text = Some(scalar_to_string(scalar)),
+            scalar => start.push_attribute((key.as_str(), scalar_to_string(scalar).as_str())),
+        }
+    }
+
+    if text.is_none() && children.is_empty() {
+        return writer.write_event(Event::Empty(start));
+    }
+
+    writer.write_event(Event::Start(start))?;
+    if let Some(text) = text {
+        writer.write_event(Event::Text(BytesText::new(&text)))?;
+    }
+    for (key, child) in children {
+        write_element(writer, key, child, &[])?;
+    }
+    writer.write_event(Event::End(BytesEnd::new(name)))
+}
+
+fn scalar_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn to_xml(response: &Value) -> io::Result<String> {
+    let mut writer = Writer::new(Vec::new());
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    if let Value::Object(root) = response {
+        for (name, body) in root {
+            write_element(&mut writer, name, body, &[("xmlns", XMLNS)])?;
+        }
+    }
+    String::from_utf8(writer.into_inner())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// renders `SR` responses according to the `f` (and `callback`) request parameter
+pub(super) async fn format_middleware(
+    Query(params): Query<FormatParams>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let format = match Format::from_params(params) {
+        Ok(format) => format,
+        // can't honor the requested format, so fall back to the default
+        Err(SR(e, _)) => return Format::Xml.respond(&e, None),
+    };
+
+    let mut response = next.run(request).await;
+    let extra = response.extensions_mut().remove::<Extra>();
+    match response.extensions_mut().remove::<Box<SubsonicResponse>>() {
+        Some(subsonic_response) => format.respond(&subsonic_response, extra),
+        // not a Subsonic response (media, cover art, ...)
+        None => response,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn params(f: &str, callback: Option<&str>) -> FormatParams {
+        FormatParams {
+            f: Some(f.to_string()),
+            callback: callback.map(String::from),
+        }
+    }
+
+    #[test]
+    fn xml_attributes_and_elements() {
+        let response = json!({
+            "subsonic-response": {
+                "status": "ok",
+                "version": "1.16.1",
+                "missing": null,
+                "album": {
+                    "id": "al-1",
+                    "name": "Tom & \"Jerry\" <3",
+                    "isDir": false,
+                    "songCount": 2,
+                    "song": [{ "id": "1" }, { "id": "2" }],
+                    "genre": ["Rock", "Pop"],
+                },
+                "lyrics": { "artist": "Someone", "value": "la & <la>" },
+            }
+        });
+        assert_eq!(
+            to_xml(&response).unwrap(),
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                r#"<subsonic-response xmlns="http://subsonic.org/restapi" status="ok" version="1.16.1">"#,
+                r#"<album id="al-1" name="Tom &amp; &quot;Jerry&quot; &lt;3" isDir="false" songCount="2">"#,
+                r#"<song id="1"/><song id="2"/><genre>Rock</genre><genre>Pop</genre>"#,
+                r#"</album>"#,
+                r#"<lyrics artist="Someone">la &amp; &lt;la&gt;</lyrics>"#,
+                r#"</subsonic-response>"#,
+            )
+        );
+    }
+
+    #[test]
+    fn merging() {
+        let mut target = json!({
+            "status": "ok",
+            "song": [{ "id": "1" }, { "id": "2" }],
+        });
+        merge(
+            &mut target,
+            json!({
+                "status": "failed",
+                "song": [{ "played": 1 }, { "played": 2 }, { "played": 3 }],
+                "openSubsonic": true,
+            }),
+        );
+        // items beyond those there already are dropped
+        assert_eq!(
+            target,
+            json!({
+                "status": "failed",
+                "song": [{ "id": "1", "played": 1 }, { "id": "2", "played": 2 }],
+                "openSubsonic": true,
+            })
+        );
+    }
+
+    #[test]
+    fn callbacks() {
+        assert!(matches!(
+            Format::from_params(params("jsonp", Some("jQuery_1.cb$"))),
+            Ok(Format::Jsonp(callback)) if callback == "jQuery_1.cb$"
+        ));
+        for invalid in ["", "alert(1)", "cb;alert(1)", "a b", "</script>"] {
+            assert!(Format::from_params(params("jsonp", Some(invalid))).is_err());
+        }
+        assert!(Format::from_params(params("jsonp", None)).is_err());
+        assert!(Format::from_params(params("yaml", None)).is_err());
+        assert!(matches!(
+            Format::from_params(params("json", Some("ignored"))),
+            Ok(Format::Json)
+        ));
+    }
+}
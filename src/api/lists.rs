@@ -0,0 +1,252 @@
+use axum::{extract::State, routing::get, Extension, Router};
+use serde_json::json;
+use subsonic_types::{
+    request::lists::{
+        GetAlbumList, GetAlbumList2, GetRandomSongs, GetSongsByGenre, GetStarred, GetStarred2,
+        ListType,
+    },
+    response::{
+        AlbumList, AlbumList2, Artist as IndexArtist, Child, ErrorCode, NowPlaying,
+        NowPlayingEntry, ResponseBody, Songs, Starred, Starred2,
+    },
+};
+
+use super::{
+    browsing::{directory_child, music_folders, visible_folders},
+    open_subsonic::song_fields,
+    AppState, Params, SR,
+};
+use crate::{
+    entity::{artist, directory, song, user},
+    indexer::types::Album,
+};
+
+const DEFAULT_SIZE: u32 = 10;
+const MAX_SIZE: u32 = 500;
+
+async fn album_list(
+    state: &AppState,
+    user: &user::Model,
+    mut list: GetAlbumList2,
+) -> Result<Vec<Album>, SR> {
+    let missing = match list.list_type {
+        ListType::ByYear if list.from_year.is_none() => Some("fromYear"),
+        ListType::ByYear if list.to_year.is_none() => Some("toYear"),
+        ListType::ByGenre if list.genre.is_none() => Some("genre"),
+        _ => None,
+    };
+    if let Some(param) = missing {
+        return Err(SR::error(
+            ErrorCode::RequiredParameterMissing,
+            format!("required parameter is missing: {param}"),
+        ));
+    }
+
+    let folders = music_folders(state, user, list.music_folder_id.as_deref()).await;
+    list.size = Some(list.size.unwrap_or(DEFAULT_SIZE).min(MAX_SIZE));
+    Ok(state.db.get_album_list(user.id, &list, &folders).await?)
+}
+
+async fn get_album_list(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    Params(list): Params<GetAlbumList>,
+) -> Result<SR, SR> {
+    // same parameters, different response
+    let list = GetAlbumList2 {
+        list_type: list.list_type,
+        size: list.size,
+        offset: list.offset,
+        from_year: list.from_year,
+        to_year: list.to_year,
+        genre: list.genre,
+        music_folder_id: list.music_folder_id,
+    };
+    let albums = album_list(&state, &user, list).await?;
+    Ok(SR::ok(ResponseBody::AlbumList(AlbumList {
+        album: albums.into_iter().map(|album| album.into()).collect(),
+    })))
+}
+
+async fn get_album_list2(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    Params(list): Params<GetAlbumList2>,
+) -> Result<SR, SR> {
+    let albums = album_list(&state, &user, list).await?;
+    Ok(SR::ok(ResponseBody::AlbumList2(AlbumList2 {
+        album: albums.into_iter().map(|album| album.into()).collect(),
+    })))
+}
+
+async fn get_random_songs(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    Params(query): Params<GetRandomSongs>,
+) -> SR {
+    let folders = music_folders(&state, &user, query.music_folder_id.as_deref()).await;
+    let size = query.size.unwrap_or(DEFAULT_SIZE).min(MAX_SIZE);
+    let song = songs(state.db.get_random_songs(&query, size, &folders).await);
+    let fields = song_fields(&state.db, &song).await;
+    SR::ok(ResponseBody::RandomSongs(Songs { song }))
+        .with(json!({ "randomSongs": { "song": fields } }))
+}
+
+async fn get_songs_by_genre(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    Params(query): Params<GetSongsByGenre>,
+) -> SR {
+    let folders = music_folders(&state, &user, query.music_folder_id.as_deref()).await;
+    let count = query.count.unwrap_or(DEFAULT_SIZE).min(MAX_SIZE);
+    let song = songs(
+        state
+            .db
+            .get_songs_by_genre(
+                &query.genre,
+                query.offset.unwrap_or_default(),
+                count,
+                &folders,
+            )
+            .await,
+    );
+    let fields = song_fields(&state.db, &song).await;
+    SR::ok(ResponseBody::SongsByGenre(Songs { song }))
+        .with(json!({ "songsByGenre": { "song": fields } }))
+}
+
+// what others play is only shown if `user` may see it, too
+async fn get_now_playing(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+) -> SR {
+    let folders = music_folders(&state, &user, None).await;
+    let entry: Vec<_> = state
+        .now_playing
+        .entries()
+        .into_iter()
+        .filter(|playing| {
+            playing
+                .song
+                .folder
+                .is_some_and(|folder| folders.contains(&folder))
+        })
+        .map(|playing| NowPlayingEntry {
+            child: playing.song.into(),
+            username: playing.username,
+            minutes_ago: (playing.since.elapsed().as_secs() / 60) as u32,
+            player_id: playing.player_id,
+            player_name: playing.player_name,
+        })
+        .collect();
+    let fields = song_fields(&state.db, entry.iter().map(|entry| &entry.child)).await;
+    SR::ok(ResponseBody::NowPlaying(NowPlaying { entry }))
+        .with(json!({ "nowPlaying": { "entry": fields } }))
+}
+
+// starred directories are artists if they are top level, and albums otherwise
+async fn get_starred(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    query: Params<GetStarred>,
+) -> SR {
+    let folders = music_folders(&state, &user, query.music_folder_id.as_deref()).await;
+    // directories are in a folder if they are below its path
+    let paths: Vec<_> = visible_folders(&state, &user)
+        .await
+        .into_iter()
+        .filter(|folder| folders.contains(&folder.id))
+        .map(|folder| folder.path + "/")
+        .collect();
+
+    let roots: Vec<_> = state
+        .db
+        .get_root_directories()
+        .await
+        .into_iter()
+        .map(|root| Some(root.id))
+        .collect();
+    let (top_level, directories): (Vec<_>, Vec<_>) = state
+        .db
+        .get_starred_directories(user.id)
+        .await
+        .into_iter()
+        .filter(|dir| paths.iter().any(|path| dir.path.starts_with(path)))
+        .partition(|dir| roots.contains(&dir.parent));
+    let cover_art = state.db.get_directory_cover_art(&directories).await;
+
+    let artist = top_level
+        .into_iter()
+        .map(|dir| IndexArtist {
+            id: directory::format_id(dir.id),
+            name: dir.name,
+            ..Default::default()
+        })
+        .chain(
+            state
+                .db
+                .get_starred_artists(user.id, &folders)
+                .await
+                .into_iter()
+                .map(|artist| IndexArtist {
+                    id: artist::format_id(artist.id),
+                    name: artist.name,
+                    ..Default::default()
+                }),
+        )
+        .collect();
+    let album = directories
+        .into_iter()
+        .map(|dir| directory_child(dir, &cover_art))
+        .chain(
+            state
+                .db
+                .get_starred_albums(user.id, &folders)
+                .await
+                .into_iter()
+                .map(Child::from),
+        )
+        .collect();
+    let song = songs(state.db.get_starred_songs(user.id, &folders).await);
+    let fields = song_fields(&state.db, &song).await;
+
+    SR::ok(ResponseBody::Starred(Starred {
+        song,
+        album,
+        artist,
+    }))
+    .with(json!({ "starred": { "song": fields } }))
+}
+
+async fn get_starred2(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    query: Params<GetStarred2>,
+) -> SR {
+    let folders = music_folders(&state, &user, query.music_folder_id.as_deref()).await;
+    let artist = state.db.get_starred_artists(user.id, &folders).await;
+    let album = state.db.get_starred_albums(user.id, &folders).await;
+    let song = songs(state.db.get_starred_songs(user.id, &folders).await);
+    let fields = song_fields(&state.db, &song).await;
+    SR::ok(ResponseBody::Starred2(Starred2 {
+        song,
+        album: album.into_iter().map(|album| album.into()).collect(),
+        artist: artist.into_iter().map(|artist| artist.into()).collect(),
+    }))
+    .with(json!({ "starred2": { "song": fields } }))
+}
+
+fn songs(songs: Vec<song::Model>) -> Vec<Child> {
+    songs.into_iter().map(|song| song.into()).collect()
+}
+
+pub(super) fn router() -> Router<AppState> {
+    Router::new()
+        .route("/getAlbumList.view", get(get_album_list))
+        .route("/getAlbumList2.view", get(get_album_list2))
+        .route("/getNowPlaying.view", get(get_now_playing))
+        .route("/getRandomSongs.view", get(get_random_songs))
+        .route("/getSongsByGenre.view", get(get_songs_by_genre))
+        .route("/getStarred.view", get(get_starred))
+        .route("/getStarred2.view", get(get_starred2))
+}
@@ -0,0 +1,120 @@
+use axum::{extract::State, routing::get, Extension, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use subsonic_types::{
+    request::retrieval::GetLyrics,
+    response::{Lyrics, ResponseBody},
+};
+
+use super::{
+    browsing::{music_folders, song_visible},
+    AppState, Params, SR,
+};
+use crate::{
+    entity::{lyrics, song, user},
+    indexer::lyrics::parse,
+};
+
+// OpenSubsonic `songLyrics`, which `subsonic_types` doesn't know about
+#[derive(Debug, Deserialize)]
+struct GetLyricsBySongId {
+    id: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StructuredLyrics {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display_artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display_title: Option<String>,
+    lang: String,
+    // offsets are applied while indexing
+    offset: i64,
+    synced: bool,
+    line: Vec<Line>,
+}
+
+#[derive(Debug, Serialize)]
+struct Line {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start: Option<u32>,
+    value: String,
+}
+
+fn structured(song: &song::Model, lyrics: lyrics::Model) -> StructuredLyrics {
+    let lrc = parse(&lyrics.text);
+    StructuredLyrics {
+        display_artist: lyrics.display_artist.or_else(|| song.artist.clone()),
+        display_title: lyrics.display_title.or_else(|| Some(song.title.clone())),
+        lang: lyrics.lang,
+        offset: 0,
+        synced: lyrics.synced,
+        line: lrc
+            .lines
+            .into_iter()
+            .map(|line| Line {
+                start: line.start,
+                value: line.value,
+            })
+            .collect(),
+    }
+}
+
+// the plain text of the first visible song that matches, without timestamps
+async fn get_lyrics(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    Params(query): Params<GetLyrics>,
+) -> SR {
+    if query.artist.is_none() && query.title.is_none() {
+        return SR::ok(ResponseBody::Lyrics(Default::default()));
+    }
+    let folders = music_folders(&state, &user, None).await;
+    let Some((song, lyrics)) = state
+        .db
+        .find_lyrics(query.artist.as_deref(), query.title.as_deref(), &folders)
+        .await
+    else {
+        return SR::ok(ResponseBody::Lyrics(Default::default()));
+    };
+
+    let text = parse(&lyrics.text)
+        .lines
+        .into_iter()
+        .map(|line| line.value)
+        .collect::<Vec<_>>()
+        .join("\n");
+    SR::ok(ResponseBody::Lyrics(Lyrics {
+        artist: song.artist,
+        title: Some(song.title),
+    }))
+    .with(json!({ "lyrics": { "value": text } }))
+}
+
+async fn get_lyrics_by_song_id(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    Params(query): Params<GetLyricsBySongId>,
+) -> Result<SR, SR> {
+    let song = match state.db.get_song(&query.id).await {
+        Some(song) if song_visible(&state, &user, &song).await => song,
+        _ => return Err(SR::not_found(&query.id)),
+    };
+    let structured_lyrics: Vec<_> = state
+        .db
+        .get_song_lyrics(song.id)
+        .await
+        .into_iter()
+        .map(|lyrics| structured(&song, lyrics))
+        .collect();
+    Ok(SR::ok(ResponseBody::Empty).with(json!({
+        "lyricsList": { "structuredLyrics": structured_lyrics }
+    })))
+}
+
+pub(super) fn router() -> Router<AppState> {
+    Router::new()
+        .route("/getLyrics.view", get(get_lyrics))
+        .route("/getLyricsBySongId.view", get(get_lyrics_by_song_id))
+}
@@ -0,0 +1,452 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::State,
+    http::{
+        header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_TYPE, ETAG},
+        StatusCode,
+    },
+    response::{IntoResponse, Response},
+    routing::get,
+    Extension, Router,
+};
+use axum_extra::{
+    body::AsyncReadBody,
+    headers::{ETag, IfNoneMatch, Range},
+    TypedHeader,
+};
+use axum_range::{KnownSize, Ranged};
+use camino::{Utf8Path, Utf8PathBuf};
+use mime_guess::MimeGuess;
+use subsonic_types::{
+    request::retrieval::{Download, GetCoverArt, Stream},
+    response::ErrorCode,
+};
+use tokio::io::AsyncReadExt;
+use tracing::{debug, error, warn};
+
+use super::{
+    browsing::{album_visible, cover_art_visible, directory_visible, music_folders, song_visible},
+    playlists::visible_playlist,
+    AppState, Params, SR,
+};
+use crate::{
+    artwork,
+    entity::{album, directory, playlist, song, user},
+    transcode::{Format, Transcode},
+    zip::ZipWriter,
+};
+
+// the highest bit rate we transcode to, in kbit/s
+const MAX_BIT_RATE: u32 = 320;
+
+// cover art IDs only ever point to the same picture, but revalidate once a day
+const COVER_ART_CACHE_CONTROL: &str = "private, max-age=86400";
+
+// recognizes the usual audio containers by their first bytes
+fn sniff(head: &[u8]) -> Option<&'static str> {
+    match head {
+        [b'I', b'D', b'3', ..] => Some("audio/mpeg"),
+        [0xff, b, ..] if b & 0xe0 == 0xe0 => Some("audio/mpeg"),
+        [b'f', b'L', b'a', b'C', ..] => Some("audio/flac"),
+        [b'O', b'g', b'g', b'S', ..] => Some("audio/ogg"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => Some("audio/wav"),
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => Some("audio/mp4"),
+        _ => None,
+    }
+}
+
+/// the MIME type found while indexing, or else whatever the file looks like
+async fn content_type(song: &song::Model) -> String {
+    if let Some(content_type) = &song.content_type {
+        return content_type.clone();
+    }
+
+    let mut head = [0; 12];
+    let mut len = 0;
+    if let Ok(mut file) = tokio::fs::File::open(&song.path).await {
+        while len < head.len() {
+            match file.read(&mut head[len..]).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => len += n,
+            }
+        }
+    }
+    match sniff(&head[..len]) {
+        Some(content_type) => content_type.to_string(),
+        None => MimeGuess::from_path(&song.path)
+            .first_or_octet_stream()
+            .to_string(),
+    }
+}
+
+// from size and duration, so it's only ever an estimate, in kbit/s
+fn bit_rate(song: &song::Model) -> Option<u32> {
+    let duration = song.duration.filter(|d| *d > 0)?;
+    Some((u64::from(song.size?) * 8 / 1000 / u64::from(duration)) as u32)
+}
+
+/// what to turn `song` into, `None` if it's to be served as is; starting at
+/// `offset` seconds takes a transcode, as files are only served whole
+fn transcoding(
+    song: &song::Model,
+    format: Option<&str>,
+    max_bit_rate: Option<u32>,
+    offset: u32,
+) -> Result<Option<Transcode>, SR> {
+    // 0 means no limit
+    let max_bit_rate = max_bit_rate.filter(|max| *max > 0);
+    let format = match (format, max_bit_rate) {
+        (Some("raw"), _) => return Ok(None),
+        (None, None) if offset == 0 => return Ok(None),
+        (Some(name), _) => Format::parse(name)
+            .ok_or_else(|| SR::error(ErrorCode::Generic, format!("unsupported format: {name}")))?,
+        // only the bit rate (or offset) is of concern, so keep the format if possible
+        (None, _) => Utf8Path::new(&song.path)
+            .extension()
+            .and_then(Format::parse)
+            .unwrap_or(Format::Mp3),
+    };
+
+    let same_format = Utf8Path::new(&song.path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(format.suffix()));
+    let fits = match (max_bit_rate, bit_rate(song)) {
+        (None, _) => true,
+        (Some(max), Some(actual)) => actual <= max,
+        (Some(_), None) => false,
+    };
+    if same_format && fits && offset == 0 {
+        return Ok(None);
+    }
+
+    let bit_rate = max_bit_rate
+        .unwrap_or(format.default_bit_rate())
+        .min(MAX_BIT_RATE);
+    Ok(Some(Transcode {
+        format,
+        bit_rate,
+        offset,
+    }))
+}
+
+async fn serve_file(
+    path: &Utf8Path,
+    content_type: String,
+    range: Option<TypedHeader<Range>>,
+) -> Result<Response, SR> {
+    let file = match tokio::fs::File::open(path).await {
+        Ok(file) => file,
+        Err(err) => {
+            error!("opening {path}: {err}");
+            return Err(SR::not_found(path));
+        }
+    };
+    let body = KnownSize::file(file).await.map_err(|e| {
+        error!("{e:?}");
+        SR::error(ErrorCode::Generic, "could not read file")
+    })?;
+    let range = range.map(|TypedHeader(range)| range);
+    let ranged = Ranged::new(range, body);
+    Ok(([(CONTENT_TYPE, content_type)], ranged).into_response())
+}
+
+async fn stream(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    range: Option<TypedHeader<Range>>,
+    query: Params<Stream>,
+) -> Result<Response, SR> {
+    let Some(song) = state.db.get_song(&query.id).await else {
+        error!("cannot find {}", query.id);
+        return Err(SR::not_found(&query.id));
+    };
+    if !song_visible(&state, &user, &song).await {
+        return Err(SR::not_found(&query.id));
+    }
+    stream_song(&state, &song, range, &query).await
+}
+
+/// `song` as `query` asks for it, transcoded or not
+pub(super) async fn stream_song(
+    state: &AppState,
+    song: &song::Model,
+    range: Option<TypedHeader<Range>>,
+    query: &Stream,
+) -> Result<Response, SR> {
+    let path = Utf8Path::new(&song.path);
+
+    // `transcodeOffset`: OpenSubsonic has it for audio, too
+    let offset = query
+        .time_offset
+        .map(|offset| offset.to_duration().as_secs().min(u32::MAX.into()) as u32)
+        .unwrap_or_default();
+    let Some(transcode) = transcoding(song, query.format.as_deref(), query.max_bit_rate, offset)?
+    else {
+        debug!("streaming {song:?}");
+        return serve_file(path, content_type(song).await, range).await;
+    };
+    let mime_type = transcode.format.mime_type().to_string();
+    if let Some(cached) = state.transcoder.cached(path, &transcode).await {
+        debug!("streaming cached {transcode:?} of {path}");
+        return serve_file(&cached, mime_type, range).await;
+    }
+
+    debug!("transcoding {path} to {transcode:?}");
+    let output = match state.transcoder.start(path, &transcode).await {
+        Ok(output) => output,
+        // most likely there is no ffmpeg, the file as it is beats nothing
+        Err(e) => {
+            warn!("transcoding {path}: {e}, streaming it as it is");
+            return serve_file(path, content_type(song).await, range).await;
+        }
+    };
+    let mut response = ([(CONTENT_TYPE, mime_type)], AsyncReadBody::new(output)).into_response();
+    if query.estimate_content_length == Some(true) {
+        if let Some(duration) = song.duration {
+            let duration = duration.saturating_sub(transcode.offset);
+            let estimate = u64::from(duration) * u64::from(transcode.bit_rate) * 1000 / 8;
+            response
+                .headers_mut()
+                .insert(CONTENT_LENGTH, estimate.into());
+        }
+    }
+    Ok(response)
+}
+
+// `attachment; filename=...`, with a plain ASCII fallback for old clients
+fn attachment(name: &str) -> String {
+    let fallback: String = name
+        .chars()
+        .map(|c| match c {
+            ' '..='~' if c != '"' && c != '\\' => c,
+            _ => '_',
+        })
+        .collect();
+    let encoded: String = name
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect();
+    format!("attachment; filename=\"{fallback}\"; filename*=UTF-8''{encoded}")
+}
+
+fn file_name(path: &str) -> &str {
+    Utf8Path::new(path).file_name().unwrap_or(path)
+}
+
+// the files of an album, playlist or directory, and their names in the archive,
+// as far as `user` may see them
+async fn archive_entries(
+    state: &AppState,
+    id: &str,
+    user: &user::Model,
+) -> Option<(String, Vec<(String, Utf8PathBuf)>)> {
+    if let Some(id) = album::parse_id(id) {
+        if !album_visible(state, user, id).await {
+            return None;
+        }
+        let album = state.db.get_album(id).await?;
+        let songs = state.db.get_album_songs(id).await;
+        let entries = songs
+            .into_iter()
+            .map(|song| (file_name(&song.path).to_string(), song.path.into()))
+            .collect();
+        return Some((album.name, entries));
+    }
+
+    if playlist::parse_id(id).is_some() {
+        let pl = visible_playlist(state, id, user).await?;
+        let folders = music_folders(state, user, None).await;
+        // numbered, to keep the playlist order
+        let entries = pl
+            .songs
+            .into_iter()
+            .filter(|song| song.folder.is_some_and(|folder| folders.contains(&folder)))
+            .enumerate()
+            .map(|(i, song)| {
+                let name = format!("{:02} - {}", i + 1, file_name(&song.path));
+                (name, song.path.into())
+            })
+            .collect();
+        return Some((pl.playlist.name, entries));
+    }
+
+    let dir = state.db.get_directory(directory::parse_id(id)?).await?;
+    if !directory_visible(state, user, &dir).await {
+        return None;
+    }
+    let root = Utf8PathBuf::from(&dir.path);
+    let mut entries = vec![];
+    let mut level = vec![dir.id];
+    while !level.is_empty() {
+        for song in state.db.get_directory_songs(&level).await {
+            let path = Utf8PathBuf::from(song.path);
+            let name = path.strip_prefix(&root).unwrap_or(&path).to_string();
+            entries.push((name, path));
+        }
+        level = state
+            .db
+            .get_subdirectories(&level)
+            .await
+            .into_iter()
+            .map(|dir| dir.id)
+            .collect();
+    }
+    Some((dir.name, entries))
+}
+
+// streams a ZIP archive without compression, as it's all compressed already
+async fn download_archive(state: &AppState, id: &str, user: &user::Model) -> Result<Response, SR> {
+    let Some((name, entries)) = archive_entries(state, id, user).await else {
+        return Err(SR::not_found(id));
+    };
+
+    let (reader, writer) = tokio::io::duplex(64 * 1024);
+    let archive = format!("{name}.zip");
+    debug!("archiving {} files as {archive}", entries.len());
+    tokio::spawn(async move {
+        let mut zip = ZipWriter::new(writer);
+        let mut names = HashSet::new();
+        for (name, path) in entries {
+            // two songs may well share a file name
+            let mut unique = name.clone();
+            let mut n = 1;
+            while !names.insert(unique.clone()) {
+                n += 1;
+                unique = match name.rsplit_once('.') {
+                    Some((stem, ext)) => format!("{stem} ({n}).{ext}"),
+                    None => format!("{name} ({n})"),
+                };
+            }
+            if let Err(e) = zip.add_file(&unique, &path).await {
+                // most likely the client went away
+                debug!("archiving {path}: {e}");
+                return;
+            }
+        }
+        if let Err(e) = zip.finish().await {
+            debug!("finishing archive: {e}");
+        }
+    });
+
+    Ok((
+        [
+            (CONTENT_TYPE, "application/zip".to_string()),
+            (CONTENT_DISPOSITION, attachment(&archive)),
+        ],
+        AsyncReadBody::new(reader),
+    )
+        .into_response())
+}
+
+/// a song as is, or a whole album, playlist or directory as a ZIP archive
+async fn download(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    range: Option<TypedHeader<Range>>,
+    query: Params<Download>,
+) -> Result<Response, SR> {
+    let Some(song) = state.db.get_song(&query.id).await else {
+        return download_archive(&state, &query.id, &user).await;
+    };
+    if !song_visible(&state, &user, &song).await {
+        return Err(SR::not_found(&query.id));
+    }
+
+    debug!("downloading {song:?}");
+    let mut response =
+        serve_file(Utf8Path::new(&song.path), content_type(&song).await, range).await?;
+    if let Ok(disposition) = attachment(file_name(&song.path)).parse() {
+        response
+            .headers_mut()
+            .insert(CONTENT_DISPOSITION, disposition);
+    }
+    Ok(response)
+}
+
+/// the embedded picture, scaled down if `size` asks for it
+async fn get_cover_art(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    if_none_match: Option<TypedHeader<IfNoneMatch>>,
+    query: Params<GetCoverArt>,
+) -> Result<Response, SR> {
+    let Some(cover_art) = state.db.get_cover_art(&query.id).await else {
+        error!("cannot find {}", query.id);
+        return Err(SR::not_found(&query.id));
+    };
+    if !cover_art_visible(&state, &user, &cover_art).await {
+        return Err(SR::not_found(&query.id));
+    }
+    let size = match query.size.as_deref() {
+        None => None,
+        Some(size) => match size.parse::<u32>() {
+            Ok(0) => None,
+            Ok(size) => artwork::thumbnail_size(size),
+            Err(_) => {
+                return Err(SR::error(
+                    ErrorCode::Generic,
+                    format!("invalid size: {size}"),
+                ))
+            }
+        },
+    };
+
+    let root = state.db.data_path();
+    // rows that still wait for the garbage collector to move their file are
+    // served as they are
+    let thumbnail = size.and_then(|size| Some((size, cover_art.thumbnail_path(root, size)?)));
+    // nothing stored under a hash ever changes
+    let etag = match (&cover_art.hash, &thumbnail) {
+        (Some(hash), Some((size, _))) => format!("\"{hash}-{size}\""),
+        (Some(hash), None) => format!("\"{hash}\""),
+        (None, _) => format!("\"{}\"", cover_art.id),
+    };
+    let headers = [
+        (ETAG, etag.clone()),
+        (CACHE_CONTROL, COVER_ART_CACHE_CONTROL.to_string()),
+    ];
+
+    if let (Some(TypedHeader(if_none_match)), Ok(etag)) = (if_none_match, etag.parse::<ETag>()) {
+        if !if_none_match.precondition_passes(&etag) {
+            return Ok((StatusCode::NOT_MODIFIED, headers).into_response());
+        }
+    }
+
+    let source = cover_art.path(root);
+    let (path, content_type) = match thumbnail {
+        None => (source, cover_art.mime_type),
+        Some((size, thumbnail)) => {
+            if let Err(e) = artwork::thumbnail(&source, &thumbnail, size).await {
+                error!("scaling {} to {size}: {e}", query.id);
+                return Err(SR::error(ErrorCode::Generic, "could not scale cover art"));
+            }
+            (thumbnail, "image/jpeg".to_string())
+        }
+    };
+    let file = match tokio::fs::File::open(&path).await {
+        Ok(file) => file,
+        Err(err) => {
+            error!("opening {path}: {err}");
+            return Err(SR::not_found(&query.id));
+        }
+    };
+    Ok((
+        headers,
+        [(CONTENT_TYPE, content_type)],
+        AsyncReadBody::new(file),
+    )
+        .into_response())
+}
+
+pub(super) fn router() -> Router<AppState> {
+    Router::new()
+        .route("/getCoverArt.view", get(get_cover_art))
+        .route("/download.view", get(download))
+        .route("/stream.view", get(stream))
+}
@@ -1,31 +1,33 @@
-use std::{sync::Arc, time::Duration};
+use std::{ops::Deref, sync::Arc, time::Duration};
 
 use axum::{
+    async_trait,
     body::Body,
-    extract::{Query, State},
-    http::{header::CONTENT_TYPE, Method, Request, StatusCode, Uri},
+    extract::{FromRequestParts, State},
+    http::{header::CONTENT_TYPE, request::Parts, Method, Request, StatusCode, Uri},
     middleware::Next,
     response::{IntoResponse, Redirect, Response},
     routing::get,
-    Router,
+    Extension, Router,
 };
-use axum_extra::{body::AsyncReadBody, headers::Range, TypedHeader};
-use axum_range::{KnownSize, Ranged};
+use axum_extra::{body::AsyncReadBody, extract::Query as ExtraQuery};
 use camino::{Utf8Path, Utf8PathBuf};
 use mime_guess::MimeGuess;
-use serde::{Deserialize, Serialize};
+use sea_orm::DbErr;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
 use subsonic_types::{
-    common::{Milliseconds, Seconds, Version},
+    common::{DateTime, Seconds, Version},
     request::{
-        browsing::GetSong,
-        retrieval::{GetCoverArt, Stream},
+        browsing::{GetArtists, GetSong},
         search::Search3,
     },
     response::{
-        AlbumID3, AlbumList2, ArtistID3, ArtistsID3, Child, IndexID3, MusicFolder, MusicFolders,
-        Playlist, Playlists, Response as SubsonicResponse, ResponseBody, SearchResult3,
+        ArtistID3, ArtistsID3, Child, Error as SubsonicError, ErrorCode, IndexID3,
+        Response as SubsonicResponse, ResponseBody, SearchResult3,
     },
 };
+use time::OffsetDateTime;
 use tower_http::{
     classify::ServerErrorsFailureClass,
     cors::{self, CorsLayer},
@@ -34,18 +36,128 @@ use tower_http::{
 use tracing::{debug, error, info, trace, warn, Span};
 
 use crate::{
-    config::Config,
-    entity::song,
-    indexer::{db::DB, types::QueryResult},
+    config::{Bookmarks, Config},
+    entity::{album, artist, song, user},
+    indexer::{db::DB, types::QueryResult, Indexer},
+    transcode::Transcoder,
     util::Pwn,
 };
 
+mod auth;
+
+mod annotation;
+
+mod bookmarks;
+
+mod format;
+
+mod browsing;
+
+mod lists;
+
+mod lyrics;
+
+mod media;
+
+mod open_subsonic;
+
+mod playlists;
+
+mod radio;
+
+mod scan;
+
+mod sharing;
+
+mod users;
+
 // wrapper to get around orphan rule, so we can impl IntoResponse
-struct SR(SubsonicResponse);
+// (boxed, since it's the error variant of most handler results)
+struct SR(Box<SubsonicResponse>, Option<Box<Extra>>);
+
+/// what `subsonic_types` has no fields for (OpenSubsonic extensions, ...), merged
+/// into the `subsonic-response` object when rendering
+#[derive(Debug, Clone)]
+struct Extra(Value);
+
+impl SR {
+    fn ok(body: ResponseBody) -> Self {
+        SR(Box::new(SubsonicResponse::ok(Version::LATEST, body)), None)
+    }
+
+    fn error(code: ErrorCode, message: impl Into<String>) -> Self {
+        SR(
+            Box::new(SubsonicResponse::failed(
+                Version::LATEST,
+                SubsonicError {
+                    code,
+                    message: Some(message.into()),
+                },
+            )),
+            None,
+        )
+    }
 
+    /// adds `extra` (a JSON object) to the response
+    fn with(mut self, extra: Value) -> Self {
+        self.1 = Some(Box::new(Extra(extra)));
+        self
+    }
+
+    fn not_found(what: impl std::fmt::Display) -> Self {
+        Self::error(ErrorCode::DataNotFound, format!("not found: {what}"))
+    }
+}
+
+/// like `Query`, but rejections are Subsonic errors, and repeated keys (`id=1&id=2`) are supported
+#[derive(Debug)]
+struct Params<T>(T);
+
+impl<T> Deref for Params<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for Params<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = SR;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        ExtraQuery::<T>::from_request_parts(parts, state)
+            .await
+            .map(|ExtraQuery(params)| Params(params))
+            .map_err(|e| SR::error(ErrorCode::RequiredParameterMissing, e.to_string()))
+    }
+}
+
+impl From<DbErr> for SR {
+    fn from(e: DbErr) -> Self {
+        match e {
+            DbErr::RecordNotFound(what) => SR::not_found(what),
+            e => {
+                error!("{e:?}");
+                SR::error(ErrorCode::Generic, "database error")
+            }
+        }
+    }
+}
+
+// the actual serialization happens in `format::format_middleware`, which knows the requested format
 impl IntoResponse for SR {
     fn into_response(self) -> Response {
-        self.0.to_json().expect("bug").into_response()
+        let mut response = ().into_response();
+        response.extensions_mut().insert(self.0);
+        if let Some(extra) = self.1 {
+            response.extensions_mut().insert(*extra);
+        }
+        response
     }
 }
 
@@ -53,39 +165,45 @@ impl IntoResponse for SR {
 
 struct AppState {
     db: Arc<DB>,
+    indexer: Arc<Indexer>,
     file_root: Utf8PathBuf,
     base_url: String,
+    now_playing: Arc<annotation::NowPlaying>,
+    transcoder: Arc<Transcoder>,
+    bookmarks: Bookmarks,
+    // only with `[radio] proxy`
+    radio_proxy: Option<reqwest::Client>,
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-struct Scrobble {
-    /// A string which uniquely identifies the file to scrobble.
-    #[serde(default)]
-    pub id: String, // fixed: this was Vec<String>
-    /// The time at which the song was listened to.
-    #[serde(default)]
-    pub time: Milliseconds, // fixed: this was Vec<Milliseconds>
-    /// Whether this is a "submission" or a "now playing" notification.
-    pub submission: Option<bool>,
+
+fn timestamp(unix: i64) -> DateTime {
+    OffsetDateTime::from_unix_timestamp(unix)
+        .unwrap_or(OffsetDateTime::UNIX_EPOCH)
+        .into()
 }
 
 impl From<song::Model> for Child {
     fn from(song: song::Model) -> Self {
         let mut child = Child::default();
         child.id = format!("{}", song.id);
+        // clients go by it as much as by `content_type`
+        child.suffix = Utf8Path::new(&song.path).extension().map(str::to_lowercase);
         child.path = Some(song.path);
         child.parent = song.parent;
         child.title = song.title;
-        child.album_id = song.album.clone();
+        child.album_id = song.album_id.map(album::format_id);
         child.album = song.album;
-        child.artist = song.artist.clone();
-        child.artist_id = song.artist;
+        child.artist = song.artist;
+        child.artist_id = song.artist_id.map(artist::format_id);
         child.track = song.track;
+        child.disc_number = song.disc;
         child.duration = song.duration.map(|d| Seconds::new(d as _));
         child.year = song.year;
         child.genre = song.genre;
         child.cover_art = song.cover_art;
         child.size = song.size.map(|sz| sz.into());
         child.content_type = song.content_type;
+        child.bit_rate = song.bit_rate;
+        child.play_count = Some(song.play_count.into());
         child
     }
 }
@@ -147,7 +265,7 @@ async fn serve_frontend(state: State<AppState>, uri: Uri) -> Response {
     (headers, body).into_response()
 }
 
-pub async fn serve(db: Arc<DB>, config: &Config) {
+pub async fn serve(indexer: Arc<Indexer>, config: &Config) {
     let base_url = config
         .system
         .base_url
@@ -155,191 +273,128 @@ pub async fn serve(db: Arc<DB>, config: &Config) {
         .to_pwned()
         .unwrap_or_default();
     let state = AppState {
-        db,
+        db: indexer.db(),
+        indexer,
         file_root: Utf8Path::new(&config.system.data_path).join("public"),
         base_url: base_url.clone(),
+        now_playing: Default::default(),
+        transcoder: Arc::new(
+            Transcoder::new(&config.system.data_path, config.system.cache_mb).await,
+        ),
+        bookmarks: config.bookmarks.clone(),
+        radio_proxy: config.radio.proxy.then(radio::proxy_client),
     };
 
     let api = Router::new()
-        .route(
-            "/scrobble.view",
-            get(|query: Query<Scrobble>| async move {
-                debug!("TODO scrobble {query:?}");
-                SR(SubsonicResponse::ok(Version::LATEST, ResponseBody::Empty))
-            }),
-        )
-        .route(
-            "/getCoverArt.view",
-            get(
-                |State(state): State<AppState>, query: Query<GetCoverArt>| async move {
-                    let Some(cover_art) = state.db.get_cover_art(&query.id).await else {
-                        error!("cannot find {}", query.id);
-                        return Err((StatusCode::NOT_FOUND, "404".to_string()));
-                    };
-
-                    let file = match tokio::fs::File::open(cover_art.path(state.db.data_path()))
-                        .await
-                    {
-                        Ok(file) => file,
-                        Err(err) => {
-                            return Err((StatusCode::NOT_FOUND, format!("File not found: {}", err)))
-                        }
-                    };
-                    let headers = [(CONTENT_TYPE, cover_art.mime_type)];
-                    let body = AsyncReadBody::new(file);
-                    Ok((headers, body))
-                },
-            ),
-        )
-        .route(
-            "/stream.view",
-            get(
-                |State(state): State<AppState>,
-                 range: Option<TypedHeader<Range>>,
-                 query: Query<Stream>| async move {
-                    let Some(song) = state.db.get_song(&query.id).await else {
-                        error!("cannot find {}", query.id);
-                        return Err((StatusCode::NOT_FOUND, "404".to_string()));
-                    };
-                    debug!("streaming {song:?}");
-                    let file = match tokio::fs::File::open(song.path).await {
-                        Ok(file) => file,
-                        Err(err) => {
-                            return Err((StatusCode::NOT_FOUND, format!("File not found: {}", err)))
-                        }
-                    };
-                    let body = KnownSize::file(file).await.unwrap();
-                    let range = range.map(|TypedHeader(range)| range);
-                    let ranged = Ranged::new(range, body);
-                    let headers = [(CONTENT_TYPE, "audio/mpeg")];
-                    Ok((headers, ranged))
-                },
-            ),
-        )
         .route(
             "/getSong.view",
             get(
-                |State(state): State<AppState>, query: Query<GetSong>| async move {
+                |State(state): State<AppState>,
+                 Extension(user): Extension<user::Model>,
+                 query: Params<GetSong>| async move {
                     let Some(song) = state.db.get_song(&query.id).await else {
                         error!("cannot find {}", query.id);
-                        return Err((StatusCode::NOT_FOUND, "404".to_string()));
+                        return Err(SR::not_found(&query.id));
                     };
+                    if !browsing::song_visible(&state, &user, &song).await {
+                        return Err(SR::not_found(&query.id));
+                    }
 
-                    Ok(SR(SubsonicResponse::ok(
-                        Version::V1_13_0,
-                        ResponseBody::Song(song.into()),
-                    )))
+                    let song: Child = song.into();
+                    let fields = open_subsonic::song_fields(&state.db, [&song]).await;
+                    Ok(SR::ok(ResponseBody::Song(song)).with(json!({ "song": fields[0] })))
                 },
             ),
         )
         .route(
             "/search3.view",
             get(
-                |State(state): State<AppState>, query: Query<Search3>| async move {
+                |State(state): State<AppState>,
+                 Extension(user): Extension<user::Model>,
+                 query: Params<Search3>| async move {
+                    let folders =
+                        browsing::music_folders(&state, &user, query.music_folder_id.as_deref())
+                            .await;
                     let QueryResult {
                         albums,
                         artists,
                         songs,
-                    } = state.db.query(&query).await;
+                    } = state.db.query(&query, &folders).await;
 
                     let albums = albums.into_iter().map(|m| m.into()).collect();
                     let artists = artists.into_iter().map(|m| m.into()).collect();
-                    let songs = songs.into_iter().map(|m| m.into()).collect();
-                    SR(SubsonicResponse::ok(
-                        Version::V1_13_0,
-                        ResponseBody::SearchResult3(SearchResult3 {
-                            artist: artists,
-                            album: albums,
-                            song: songs,
-                        }),
-                    ))
+                    let songs: Vec<Child> = songs.into_iter().map(|m| m.into()).collect();
+                    let fields = open_subsonic::song_fields(&state.db, &songs).await;
+                    SR::ok(ResponseBody::SearchResult3(SearchResult3 {
+                        artist: artists,
+                        album: albums,
+                        song: songs,
+                    }))
+                    .with(json!({ "searchResult3": { "song": fields } }))
                 },
             ),
         )
-        .route(
-            "/ping.view",
-            get(|| async { SR(SubsonicResponse::ok(Version::V1_13_0, ResponseBody::Empty)) }),
-        )
-        .route(
-            "/getPlaylists.view",
-            get(|| async {
-                let mut pl = Playlist::default();
-                pl.name = "EGG!!".into();
-                pl.id = "1".into();
-                pl.song_count = 1;
-                let pls = Playlists { playlist: vec![pl] };
-                SR(SubsonicResponse::ok(
-                    Version::V1_13_0,
-                    ResponseBody::Playlists(pls),
-                ))
-            }),
-        )
-        .route(
-            "/getMusicFolders.view",
-            get(|| async {
-                let folders = MusicFolders {
-                    music_folder: vec![MusicFolder {
-                        id: 1,
-                        name: Some("music".into()),
-                    }],
-                };
-                SR(SubsonicResponse::ok(
-                    Version::V1_13_0,
-                    ResponseBody::MusicFolders(folders),
-                ))
-            }),
-        )
+        .route("/ping.view", get(|| async { SR::ok(ResponseBody::Empty) }))
         .route(
             "/getArtists.view",
-            get(|State(state): State<AppState>| async move {
-                // TODO (everywhere): do we gain anything from using Option<String> for user_query instead?
-                let ars = state
-                    .db
-                    .get_artists("", None, None)
-                    .await
-                    .unwrap_or_default()
-                    .into_iter()
-                    .map(ArtistID3::from)
-                    .inspect(|a| {
-                        debug!("{}", a.name);
-                    })
-                    .collect();
-                let artists = ArtistsID3 {
-                    index: vec![IndexID3 {
-                        name: "idx".into(),
-                        artist: ars,
-                    }],
-                    ignored_articles: "".into(),
-                };
-                SR(SubsonicResponse::ok(
-                    Version::V1_13_0,
-                    ResponseBody::Artists(artists),
-                ))
-            }),
-        )
-        .route(
-            "/getAlbumList2.view",
-            get(|State(state): State<AppState>| async move {
-                let albums = state
-                    .db
-                    .get_albums("", None, None)
-                    .await
-                    .unwrap_or_default()
-                    .into_iter()
-                    .map(AlbumID3::from)
-                    .inspect(|a| {
-                        debug!("{}", a.name);
-                    })
-                    .collect();
-
-                let albums = AlbumList2 { album: albums };
-                SR(SubsonicResponse::ok(
-                    Version::V1_13_0,
-                    ResponseBody::AlbumList2(albums),
-                ))
-            }),
+            get(
+                |State(state): State<AppState>,
+                 Extension(user): Extension<user::Model>,
+                 query: Params<GetArtists>| async move {
+                    let id = query.music_folder_id.map(|id| id.to_string());
+                    let folders = browsing::music_folders(&state, &user, id.as_deref()).await;
+                    // TODO (everywhere): do we gain anything from using Option<String> for user_query instead?
+                    let ars = state
+                        .db
+                        .get_artists("", None, None, &folders)
+                        .await
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(ArtistID3::from)
+                        .inspect(|a| {
+                            debug!("{}", a.name);
+                        })
+                        .collect();
+                    let artists = ArtistsID3 {
+                        index: vec![IndexID3 {
+                            name: "idx".into(),
+                            artist: ars,
+                        }],
+                        ignored_articles: "".into(),
+                    };
+                    SR::ok(ResponseBody::Artists(artists))
+                },
+            ),
         )
+        .merge(annotation::router())
+        .merge(bookmarks::router())
+        .merge(browsing::router())
+        .merge(lists::router())
+        .merge(lyrics::router())
+        .merge(media::router())
+        .merge(playlists::router())
+        .merge(radio::router())
+        .merge(scan::router())
+        .merge(sharing::router())
+        .merge(users::router())
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            annotation::annotation_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth::auth_middleware,
+        ))
+        .merge(open_subsonic::router())
+        .layer(axum::middleware::from_fn(format::format_middleware))
         .with_state(state.clone());
+    // forms have to become queries before routing, which only a layer around
+    // the whole router gets to see
+    let api = Router::new()
+        .fallback_service(api)
+        .layer(axum::middleware::from_fn(
+            open_subsonic::form_post_middleware,
+        ));
     let index_url = base_url.clone() + "/index.html";
     let mut app = Router::new()
         .fallback(serve_frontend)
@@ -353,6 +408,7 @@ pub async fn serve(db: Arc<DB>, config: &Config) {
                 .await
             }),
         )
+        .merge(sharing::public_router(&base_url))
         .with_state(state)
         .layer(axum::middleware::from_fn(uri_middleware))
         .layer(
@@ -373,7 +429,7 @@ pub async fn serve(db: Arc<DB>, config: &Config) {
                     },
                 ),
         )
-        .nest(&(base_url.clone() + "/rest"), api);
+        .nest_service(&(base_url.clone() + "/rest"), api);
 
     if config.system.dev {
         warn!("CORS: allowing any request");
@@ -0,0 +1,184 @@
+//! OpenSubsonic: https://opensubsonic.netlify.app
+
+use std::collections::HashMap;
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header::CONTENT_TYPE, Method, Request, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use subsonic_types::response::{Child, ResponseBody};
+use tracing::warn;
+
+use super::{AppState, SR};
+use crate::{
+    entity::artist,
+    indexer::{db::DB, types::SongDetails},
+};
+
+// query strings get long, but not that long
+const FORM_LIMIT: usize = 2 * 1024 * 1024;
+
+#[derive(Debug, Serialize)]
+struct Extension {
+    name: &'static str,
+    versions: &'static [u32],
+}
+
+const EXTENSIONS: &[Extension] = &[
+    // parameters as `application/x-www-form-urlencoded` POST body
+    Extension {
+        name: "formPost",
+        versions: &[1],
+    },
+    // `savePlayQueueByIndex` and `getPlayQueueByIndex`
+    Extension {
+        name: "indexBasedQueue",
+        versions: &[1],
+    },
+    // `getLyricsBySongId`
+    Extension {
+        name: "songLyrics",
+        versions: &[1],
+    },
+    // `timeOffset` for `stream`, for audio
+    Extension {
+        name: "transcodeOffset",
+        versions: &[1],
+    },
+];
+
+/// what every response carries
+pub(super) fn server_info() -> Value {
+    json!({
+        "openSubsonic": true,
+        "type": "udrome",
+        "serverVersion": env!("CARGO_PKG_VERSION"),
+    })
+}
+
+// the fields `Child` has none for
+fn extended_fields(details: &SongDetails) -> Value {
+    let song = &details.song;
+    let mut object = Map::new();
+    let mut insert = |key: &str, value: Value| {
+        if !value.is_null() {
+            object.insert(key.to_string(), value);
+        }
+    };
+    insert("samplingRate", json!(song.sampling_rate));
+    insert("channelCount", json!(song.channel_count));
+    insert("musicBrainzId", json!(song.music_brainz_id));
+    insert("sortName", json!(song.sort_name));
+    insert("displayArtist", json!(song.artist));
+
+    let mut replay_gain = Map::new();
+    for (key, value) in [
+        ("trackGain", song.track_gain),
+        ("albumGain", song.album_gain),
+        ("trackPeak", song.track_peak),
+        ("albumPeak", song.album_peak),
+    ] {
+        if let Some(value) = value {
+            replay_gain.insert(key.to_string(), json!(value));
+        }
+    }
+    if !replay_gain.is_empty() {
+        insert("replayGain", Value::Object(replay_gain));
+    }
+
+    let artists: Vec<_> = details
+        .artists
+        .iter()
+        .map(|a| json!({ "id": artist::format_id(a.id), "name": a.name }))
+        .collect();
+    insert("artists", Value::Array(artists));
+    let genres: Vec<_> = details
+        .genres
+        .iter()
+        .map(|name| json!({ "name": name }))
+        .collect();
+    insert("genres", Value::Array(genres));
+    Value::Object(object)
+}
+
+/// the OpenSubsonic fields of each of `children`, in order, for `SR::with` to
+/// merge into the list they came from. Directories get none
+pub(super) async fn song_fields<'a>(
+    db: &DB,
+    children: impl IntoIterator<Item = &'a Child>,
+) -> Vec<Value> {
+    let ids: Vec<Option<i32>> = children
+        .into_iter()
+        .map(|child| child.id.parse().ok().filter(|_| !child.is_dir))
+        .collect();
+    let songs: Vec<_> = ids.iter().flatten().copied().collect();
+    let songs = if songs.is_empty() {
+        HashMap::new()
+    } else {
+        db.get_song_details(&songs).await
+    };
+    ids.into_iter()
+        .map(|id| match id.and_then(|id| songs.get(&id)) {
+            Some(details) => extended_fields(details),
+            None => json!({}),
+        })
+        .collect()
+}
+
+/// `formPost`: the parameters of a POSTed form are appended to the query, and
+/// the request goes on as a GET
+pub(super) async fn form_post_middleware(request: Request<Body>, next: Next) -> Response {
+    let is_form = request.method() == Method::POST
+        && request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/x-www-form-urlencoded"));
+    if !is_form {
+        return next.run(request).await;
+    }
+
+    let (mut parts, body) = request.into_parts();
+    let form = match to_bytes(body, FORM_LIMIT).await {
+        Ok(form) => form,
+        Err(e) => {
+            warn!("reading form: {e}");
+            return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+        }
+    };
+    let Ok(form) = std::str::from_utf8(&form) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+
+    let query = match parts.uri.query() {
+        Some(query) if !query.is_empty() => format!("{query}&{}", form.trim()),
+        _ => form.trim().to_string(),
+    };
+    parts.uri = match format!("{}?{query}", parts.uri.path()).parse::<Uri>() {
+        Ok(uri) => uri,
+        Err(e) => {
+            warn!("form as query: {e}");
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+    };
+    parts.method = Method::GET;
+    next.run(Request::from_parts(parts, Body::empty())).await
+}
+
+async fn get_open_subsonic_extensions() -> SR {
+    SR::ok(ResponseBody::Empty).with(json!({ "openSubsonicExtensions": EXTENSIONS }))
+}
+
+/// needs no authentication, so clients can ask before they log in
+pub(super) fn router() -> Router<AppState> {
+    Router::new().route(
+        "/getOpenSubsonicExtensions.view",
+        get(get_open_subsonic_extensions),
+    )
+}
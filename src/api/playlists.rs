@@ -0,0 +1,233 @@
+use axum::{extract::State, routing::get, Extension, Router};
+use serde::Deserialize;
+use serde_json::json;
+use subsonic_types::{
+    common::Seconds,
+    request::playlists::{DeletePlaylist, GetPlaylist, GetPlaylists, UpdatePlaylist},
+    response::{ErrorCode, Playlist, PlaylistWithSongs, Playlists, ResponseBody},
+};
+
+use super::{
+    browsing::{music_folders, retain_visible, song_visible},
+    open_subsonic::song_fields,
+    timestamp, AppState, Params, SR,
+};
+use crate::{
+    entity::{playlist, user},
+    indexer::types::{PlaylistUpdate, PlaylistWithEntries},
+};
+
+// `subsonic_types::request::playlists::CreatePlaylist` misspells `playlistId`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreatePlaylist {
+    playlist_id: Option<String>,
+    name: Option<String>,
+    #[serde(default)]
+    song_id: Vec<String>,
+}
+
+fn playlist(pl: &PlaylistWithEntries) -> Playlist {
+    Playlist {
+        id: playlist::format_id(pl.playlist.id),
+        name: pl.playlist.name.clone(),
+        comment: pl.playlist.comment.clone(),
+        owner: pl.owner.as_ref().map(|owner| owner.username.clone()),
+        public: Some(pl.playlist.public),
+        song_count: pl.songs.len() as u32,
+        duration: Seconds::new(
+            pl.songs
+                .iter()
+                .filter_map(|song| song.duration)
+                .map(u64::from)
+                .sum::<u64>() as _,
+        ),
+        created: timestamp(pl.playlist.created),
+        changed: timestamp(pl.playlist.changed),
+        cover_art: pl.songs.first().and_then(|song| song.cover_art.clone()),
+        allowed_user: vec![],
+    }
+}
+
+async fn playlist_with_songs(state: &AppState, pl: PlaylistWithEntries) -> SR {
+    let playlist = playlist(&pl);
+    let entry: Vec<_> = pl.songs.into_iter().map(|song| song.into()).collect();
+    let fields = song_fields(&state.db, &entry).await;
+    SR::ok(ResponseBody::Playlist(PlaylistWithSongs {
+        playlist,
+        entry,
+    }))
+    .with(json!({ "playlist": { "entry": fields } }))
+}
+
+/// songs `user` can't see are as good as missing
+pub(super) async fn song_ids(
+    state: &AppState,
+    user: &user::Model,
+    ids: &[String],
+) -> Result<Vec<i32>, SR> {
+    let mut songs = Vec::with_capacity(ids.len());
+    for id in ids {
+        match state.db.get_song(id).await {
+            Some(song) if song_visible(state, user, &song).await => songs.push(song.id),
+            _ => return Err(SR::not_found(id)),
+        }
+    }
+    Ok(songs)
+}
+
+/// public playlists, and those of `user`, without the songs they may not see
+pub(super) async fn visible_playlist(
+    state: &AppState,
+    id: &str,
+    user: &user::Model,
+) -> Option<PlaylistWithEntries> {
+    let mut pl = state.db.get_playlist(playlist::parse_id(id)?).await?;
+    if !(pl.playlist.public || pl.playlist.owner == user.id) {
+        return None;
+    }
+    retain_visible(state, user, &mut pl.songs).await;
+    Some(pl)
+}
+
+// only the owner gets to modify a playlist
+async fn owned_playlist(
+    state: &AppState,
+    id: &str,
+    user: &user::Model,
+) -> Result<PlaylistWithEntries, SR> {
+    let Some(pl) = visible_playlist(state, id, user).await else {
+        return Err(SR::not_found(id));
+    };
+    if pl.playlist.owner != user.id {
+        return Err(SR::error(
+            ErrorCode::UserNotAuthorizedForTheGivenOperation,
+            "not the owner of this playlist",
+        ));
+    }
+    Ok(pl)
+}
+
+async fn get_playlists(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    query: Params<GetPlaylists>,
+) -> SR {
+    let mut playlists = state
+        .db
+        .get_playlists(&user, query.username.as_deref())
+        .await;
+    for pl in &mut playlists {
+        retain_visible(&state, &user, &mut pl.songs).await;
+    }
+    SR::ok(ResponseBody::Playlists(Playlists {
+        playlist: playlists.iter().map(playlist).collect(),
+    }))
+}
+
+async fn get_playlist(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    query: Params<GetPlaylist>,
+) -> Result<SR, SR> {
+    match visible_playlist(&state, &query.id, &user).await {
+        Some(pl) => Ok(playlist_with_songs(&state, pl).await),
+        None => Err(SR::not_found(&query.id)),
+    }
+}
+
+// with `playlistId`, replaces the songs of an existing playlist
+async fn create_playlist(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    query: Params<CreatePlaylist>,
+) -> Result<SR, SR> {
+    let songs = song_ids(&state, &user, &query.song_id).await?;
+    let id = match (&query.playlist_id, &query.name) {
+        (Some(id), _) => {
+            let id = owned_playlist(&state, id, &user).await?.playlist.id;
+            let update = PlaylistUpdate {
+                name: query.name.clone(),
+                songs: Some(songs),
+                ..Default::default()
+            };
+            state.db.update_playlist(id, update).await?;
+            id
+        }
+        (None, Some(name)) => state.db.create_playlist(&user, name.clone(), songs).await?,
+        (None, None) => {
+            return Err(SR::error(
+                ErrorCode::RequiredParameterMissing,
+                "required parameter is missing: playlistId or name",
+            ))
+        }
+    };
+
+    let pl = state
+        .db
+        .get_playlist(id)
+        .await
+        .ok_or_else(|| SR::not_found(id))?;
+    Ok(playlist_with_songs(&state, pl).await)
+}
+
+async fn update_playlist(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    Params(query): Params<UpdatePlaylist>,
+) -> Result<SR, SR> {
+    let id = owned_playlist(&state, &query.playlist_id, &user)
+        .await?
+        .playlist
+        .id;
+
+    // the indexes count the songs the owner can see, which may be fewer than
+    // the stored ones
+    let folders = music_folders(&state, &user, None).await;
+    let positions: Vec<_> = state
+        .db
+        .get_playlist(id)
+        .await
+        .ok_or_else(|| SR::not_found(id))?
+        .songs
+        .iter()
+        .enumerate()
+        .filter(|(_, song)| song.folder.is_some_and(|folder| folders.contains(&folder)))
+        .map(|(index, _)| index as u32)
+        .collect();
+    let remove = query
+        .song_index_to_remove
+        .iter()
+        .filter_map(|index| positions.get(*index as usize).copied())
+        .collect();
+
+    let update = PlaylistUpdate {
+        name: query.name,
+        comment: query.comment,
+        public: query.public,
+        songs: None,
+        remove,
+        add: song_ids(&state, &user, &query.song_id_to_add).await?,
+    };
+    state.db.update_playlist(id, update).await?;
+    Ok(SR::ok(ResponseBody::Empty))
+}
+
+async fn delete_playlist(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    query: Params<DeletePlaylist>,
+) -> Result<SR, SR> {
+    let id = owned_playlist(&state, &query.id, &user).await?.playlist.id;
+    state.db.delete_playlist(id).await?;
+    Ok(SR::ok(ResponseBody::Empty))
+}
+
+pub(super) fn router() -> Router<AppState> {
+    Router::new()
+        .route("/getPlaylists.view", get(get_playlists))
+        .route("/getPlaylist.view", get(get_playlist))
+        .route("/createPlaylist.view", get(create_playlist))
+        .route("/updatePlaylist.view", get(update_playlist))
+        .route("/deletePlaylist.view", get(delete_playlist))
+}
@@ -0,0 +1,165 @@
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{
+        header::{HeaderName, CONTENT_TYPE},
+        HeaderMap,
+    },
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use serde::Deserialize;
+use subsonic_types::{
+    request::radio::{
+        CreateInternetRadioStation, DeleteInternetRadioStation, UpdateInternetRadioStation,
+    },
+    response::{ErrorCode, InternetRadioStation, InternetRadioStations, ResponseBody},
+};
+use tracing::{debug, warn};
+
+use super::{AppState, Params, SR};
+use crate::entity::internet_radio_station;
+
+// stations rarely take long to answer, but once they do, they stream forever
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// what players show about a station
+const ICY_HEADERS: &[&str] = &[
+    "icy-name",
+    "icy-genre",
+    "icy-description",
+    "icy-br",
+    "icy-url",
+];
+
+#[derive(Debug, Deserialize)]
+struct RadioProxy {
+    id: String,
+}
+
+pub(super) fn proxy_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .build()
+        .unwrap_or_default()
+}
+
+fn station_id(id: &str) -> Result<i32, SR> {
+    internet_radio_station::parse_id(id).ok_or_else(|| SR::not_found(id))
+}
+
+async fn get_internet_radio_stations(State(state): State<AppState>) -> SR {
+    let stations = state
+        .db
+        .get_internet_radio_stations()
+        .await
+        .into_iter()
+        .map(|station| InternetRadioStation {
+            id: internet_radio_station::format_id(station.id),
+            name: station.name,
+            stream_url: station.stream_url,
+            home_page_url: station.homepage_url,
+        })
+        .collect();
+    SR::ok(ResponseBody::InternetRadioStations(InternetRadioStations {
+        internet_radio_station: stations,
+    }))
+}
+
+async fn create_internet_radio_station(
+    State(state): State<AppState>,
+    Params(query): Params<CreateInternetRadioStation>,
+) -> Result<SR, SR> {
+    state
+        .db
+        .create_internet_radio_station(query.name, query.stream_url, query.homepage_url)
+        .await?;
+    Ok(SR::ok(ResponseBody::Empty))
+}
+
+async fn update_internet_radio_station(
+    State(state): State<AppState>,
+    Params(query): Params<UpdateInternetRadioStation>,
+) -> Result<SR, SR> {
+    state
+        .db
+        .update_internet_radio_station(
+            station_id(&query.id)?,
+            query.name,
+            query.stream_url,
+            query.homepage_url,
+        )
+        .await?;
+    Ok(SR::ok(ResponseBody::Empty))
+}
+
+async fn delete_internet_radio_station(
+    State(state): State<AppState>,
+    Params(query): Params<DeleteInternetRadioStation>,
+) -> Result<SR, SR> {
+    state
+        .db
+        .delete_internet_radio_station(station_id(&query.id)?)
+        .await?;
+    Ok(SR::ok(ResponseBody::Empty))
+}
+
+// relays only the streams of known stations, udrome is no open proxy
+async fn radio_proxy(
+    State(state): State<AppState>,
+    Params(query): Params<RadioProxy>,
+) -> Result<Response, SR> {
+    let Some(client) = &state.radio_proxy else {
+        return Err(SR::error(ErrorCode::Generic, "the radio proxy is disabled"));
+    };
+    let Some(station) = state
+        .db
+        .get_internet_radio_station(station_id(&query.id)?)
+        .await
+    else {
+        return Err(SR::not_found(&query.id));
+    };
+
+    debug!("relaying {}", station.stream_url);
+    let upstream = client
+        .get(&station.stream_url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| {
+            warn!("radio proxy {}: {e}", station.stream_url);
+            SR::error(ErrorCode::Generic, "the station is not available")
+        })?;
+
+    let mut headers = HeaderMap::new();
+    for name in [CONTENT_TYPE.as_str()].iter().chain(ICY_HEADERS) {
+        if let Some(value) = upstream.headers().get(*name) {
+            headers.insert(HeaderName::from_static(name), value.clone());
+        }
+    }
+    Ok((headers, Body::from_stream(upstream.bytes_stream())).into_response())
+}
+
+pub(super) fn router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/getInternetRadioStations.view",
+            get(get_internet_radio_stations),
+        )
+        .route(
+            "/createInternetRadioStation.view",
+            get(create_internet_radio_station),
+        )
+        .route(
+            "/updateInternetRadioStation.view",
+            get(update_internet_radio_station),
+        )
+        .route(
+            "/deleteInternetRadioStation.view",
+            get(delete_internet_radio_station),
+        )
+        .route("/radioProxy", get(radio_proxy))
+}
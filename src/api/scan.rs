@@ -0,0 +1,47 @@
+use axum::{extract::State, routing::get, Router};
+use serde::Deserialize;
+use serde_json::json;
+use subsonic_types::response::{ErrorCode, ResponseBody, ScanStatus};
+
+use super::{AppState, Params, SR};
+
+// `fullScan` reads every file again, not only new and outdated ones
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StartScan {
+    #[serde(default)]
+    full_scan: bool,
+}
+
+// `folder` is ours, `subsonic_types` doesn't know it
+fn scan_status(state: &AppState) -> SR {
+    let progress = state.indexer.status();
+    let response = SR::ok(ResponseBody::ScanStatus(ScanStatus {
+        scanning: progress.scanning,
+        count: Some(progress.count.into()),
+    }));
+    match progress.folder {
+        Some(folder) => response.with(json!({ "scanStatus": { "folder": folder } })),
+        None => response,
+    }
+}
+
+async fn start_scan(
+    State(state): State<AppState>,
+    Params(query): Params<StartScan>,
+) -> Result<SR, SR> {
+    if !state.indexer.start_scan(query.full_scan) {
+        return Err(SR::error(ErrorCode::Generic, "a scan is running already"));
+    }
+    Ok(scan_status(&state))
+}
+
+async fn get_scan_status(State(state): State<AppState>) -> SR {
+    scan_status(&state)
+}
+
+pub(super) fn router() -> Router<AppState> {
+    Router::new()
+        .route("/startScan.view", get(start_scan))
+        .route("/getScanStatus.view", get(get_scan_status))
+}
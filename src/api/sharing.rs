@@ -0,0 +1,323 @@
+use axum::{
+    extract::{Host, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Extension, Router,
+};
+use axum_extra::{headers::Range, TypedHeader};
+use serde_json::json;
+use subsonic_types::{
+    common::Milliseconds,
+    request::{
+        retrieval::Stream,
+        sharing::{CreateShare, DeleteShare, UpdateShare},
+    },
+    response::{ErrorCode, ResponseBody, Share, Shares},
+};
+use tracing::debug;
+
+use super::{
+    browsing::music_folders, media::stream_song, open_subsonic::song_fields,
+    playlists::visible_playlist, timestamp, AppState, Params, SR,
+};
+use crate::{
+    entity::{album, directory, playlist, song, user},
+    indexer::{db::unix_now_ms, types::ShareWithEntries},
+};
+
+// what the public pages live under, below `base_url`
+const SHARE_PATH: &str = "/share";
+
+// unix seconds, 0 meaning never
+fn expiry(expires: Milliseconds) -> Option<i64> {
+    let seconds = expires.to_duration().as_secs();
+    (seconds > 0).then(|| seconds.try_into().unwrap_or(i64::MAX))
+}
+
+fn now() -> i64 {
+    unix_now_ms() / 1000
+}
+
+/// the public page of share `id`, as seen by whoever sent `headers`
+fn share_url(state: &AppState, host: &str, headers: &HeaderMap, id: &str) -> String {
+    // behind a reverse proxy, that's what terminates TLS
+    let scheme = headers
+        .get("x-forwarded-proto")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("http");
+    format!("{scheme}://{host}{}{SHARE_PATH}/{id}", state.base_url)
+}
+
+fn share(share: ShareWithEntries, url: String) -> Share {
+    let ShareWithEntries {
+        share,
+        owner,
+        songs,
+    } = share;
+    Share {
+        id: share.id,
+        url,
+        description: share.description,
+        username: owner.map(|owner| owner.username).unwrap_or_default(),
+        created: timestamp(share.created),
+        expires: share.expires.map(timestamp),
+        last_visited: share.last_visited.map(timestamp),
+        visit_count: share.visit_count.into(),
+        entry: songs.into_iter().map(|song| song.into()).collect(),
+    }
+}
+
+// with the OpenSubsonic fields of every entry, split up by share
+async fn shares(state: &AppState, share: Vec<Share>) -> SR {
+    let mut fields = song_fields(&state.db, share.iter().flat_map(|share| &share.entry))
+        .await
+        .into_iter();
+    let entries: Vec<_> = share
+        .iter()
+        .map(|share| {
+            let entry: Vec<_> = fields.by_ref().take(share.entry.len()).collect();
+            json!({ "entry": entry })
+        })
+        .collect();
+    SR::ok(ResponseBody::Shares(Shares { share })).with(json!({ "shares": { "share": entries } }))
+}
+
+// songs as they are, albums, playlists and directories with their songs, all
+// only as far as `user` may see them
+async fn share_songs(state: &AppState, user: &user::Model, ids: &[String]) -> Result<Vec<i32>, SR> {
+    if ids.is_empty() {
+        return Err(SR::error(
+            ErrorCode::RequiredParameterMissing,
+            "required parameter is missing: id",
+        ));
+    }
+
+    let folders = music_folders(state, user, None).await;
+    let visible = |song: &song::Model| song.folder.is_some_and(|folder| folders.contains(&folder));
+    let mut songs = vec![];
+    for id in ids {
+        let found = if id.parse::<i32>().is_ok() {
+            match state.db.get_song(id).await {
+                Some(song) if visible(&song) => vec![song],
+                _ => return Err(SR::not_found(id)),
+            }
+        } else if let Some(album) = album::parse_id(id) {
+            state.db.get_album_songs(album).await
+        } else if playlist::parse_id(id).is_some() {
+            let Some(pl) = visible_playlist(state, id, user).await else {
+                return Err(SR::not_found(id));
+            };
+            pl.songs
+        } else if let Some(dir) = directory::parse_id(id) {
+            state.db.get_directory_songs(&[dir]).await
+        } else {
+            return Err(SR::not_found(id));
+        };
+        songs.extend(found.iter().filter(|song| visible(song)).map(|s| s.id));
+    }
+    if songs.is_empty() {
+        return Err(SR::error(ErrorCode::DataNotFound, "nothing to share"));
+    }
+    Ok(songs)
+}
+
+async fn get_shares(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    Host(host): Host,
+    headers: HeaderMap,
+) -> SR {
+    let share = state
+        .db
+        .get_shares(user.id)
+        .await
+        .into_iter()
+        .map(|s| {
+            let url = share_url(&state, &host, &headers, &s.share.id);
+            share(s, url)
+        })
+        .collect();
+    shares(&state, share).await
+}
+
+async fn create_share(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    Host(host): Host,
+    headers: HeaderMap,
+    Params(query): Params<CreateShare>,
+) -> Result<SR, SR> {
+    let songs = share_songs(&state, &user, &query.id).await?;
+    let id = state
+        .db
+        .create_share(
+            user.id,
+            songs,
+            query.description,
+            query.expires.and_then(expiry),
+        )
+        .await?;
+    let Some(created) = state.db.get_share(&id).await else {
+        return Err(SR::not_found(&id));
+    };
+    let url = share_url(&state, &host, &headers, &id);
+    Ok(shares(&state, vec![share(created, url)]).await)
+}
+
+// without `expires`, the expiry stays as it is; 0 removes it
+async fn update_share(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    Params(query): Params<UpdateShare>,
+) -> Result<SR, SR> {
+    state
+        .db
+        .update_share(
+            user.id,
+            &query.id,
+            query.description,
+            query.expires.map(expiry),
+        )
+        .await?;
+    Ok(SR::ok(ResponseBody::Empty))
+}
+
+async fn delete_share(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Model>,
+    Params(query): Params<DeleteShare>,
+) -> Result<SR, SR> {
+    state.db.delete_share(user.id, &query.id).await?;
+    Ok(SR::ok(ResponseBody::Empty))
+}
+
+pub(super) fn router() -> Router<AppState> {
+    Router::new()
+        .route("/getShares.view", get(get_shares))
+        .route("/createShare.view", get(create_share))
+        .route("/updateShare.view", get(update_share))
+        .route("/deleteShare.view", get(delete_share))
+}
+
+// the share, unless it's gone or expired
+async fn live_share(state: &AppState, id: &str) -> Option<ShareWithEntries> {
+    let share = state.db.get_share(id).await?;
+    (!share.share.expired(now())).then_some(share)
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const GONE: &str =
+    "<!DOCTYPE html><title>udrome</title><p>This share does not exist or has expired.</p>";
+
+// each player starts the next one when it's done
+const PAGE_SCRIPT: &str = r#"const players = [...document.querySelectorAll("audio")];
+players.forEach((player, i) => player.addEventListener("ended", () => players[i + 1]?.play()));"#;
+
+fn page(share: &ShareWithEntries) -> String {
+    let owner = share
+        .owner
+        .as_ref()
+        .map(|owner| owner.username.as_str())
+        .unwrap_or("somebody");
+    let title = match &share.share.description {
+        Some(description) if !description.is_empty() => escape(description),
+        _ => format!("shared by {}", escape(owner)),
+    };
+    let songs: String = share
+        .songs
+        .iter()
+        .map(|song| {
+            let name = match &song.artist {
+                Some(artist) => format!("{} - {}", escape(artist), escape(&song.title)),
+                None => escape(&song.title),
+            };
+            // relative to the page, so `base_url` takes care of itself
+            format!(
+                "<li><p>{name}</p><audio controls preload=\"none\" src=\"{}/{}\"></audio></li>\n",
+                escape(&share.share.id),
+                song.id
+            )
+        })
+        .collect();
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">
+<title>{title}</title>
+<style>body {{ font-family: sans-serif; max-width: 40em; margin: auto; padding: 1em; }} audio {{ width: 100%; }}</style>
+</head>
+<body>
+<h1>{title}</h1>
+<ol>
+{songs}</ol>
+<script>{PAGE_SCRIPT}</script>
+</body>
+</html>
+"
+    )
+}
+
+async fn share_page(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    let Some(share) = live_share(&state, &id).await else {
+        return (StatusCode::NOT_FOUND, Html(GONE)).into_response();
+    };
+    state.db.visit_share(&id).await;
+    Html(page(&share)).into_response()
+}
+
+// only the songs of the share, as they are
+async fn share_stream(
+    State(state): State<AppState>,
+    Path((id, song_id)): Path<(String, i32)>,
+    range: Option<TypedHeader<Range>>,
+) -> Response {
+    let song = live_share(&state, &id)
+        .await
+        .and_then(|share| share.songs.into_iter().find(|song| song.id == song_id));
+    let Some(song) = song else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let query = Stream {
+        id: song.id.to_string(),
+        max_bit_rate: None,
+        format: None,
+        time_offset: None,
+        size: None,
+        estimate_content_length: None,
+        converted: None,
+    };
+    match stream_song(&state, &song, range, &query).await {
+        Ok(response) => response,
+        Err(_) => {
+            debug!("streaming {} of share {id} failed", song.id);
+            StatusCode::NOT_FOUND.into_response()
+        }
+    }
+}
+
+/// the pages for those without an account, outside of `/rest`
+pub(super) fn public_router(base_url: &str) -> Router<AppState> {
+    Router::new()
+        .route(&format!("{base_url}{SHARE_PATH}/:id"), get(share_page))
+        .route(
+            &format!("{base_url}{SHARE_PATH}/:id/:song"),
+            get(share_stream),
+        )
+}
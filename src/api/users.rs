@@ -0,0 +1,282 @@
+use axum::{extract::State, routing::get, Extension, Router};
+use rand::{distributions::Alphanumeric, Rng};
+use sea_orm::{ActiveValue as AV, IntoActiveModel};
+use serde::Deserialize;
+use serde_json::json;
+use subsonic_types::{
+    request::user::{ChangePassword, DeleteUser, GetUser},
+    response::{ErrorCode, ResponseBody, User, Users},
+};
+
+use super::{
+    auth::{decode_password, require},
+    browsing::visible_folders,
+    AppState, Params, SR,
+};
+use crate::entity::user::{self, Role};
+
+// `subsonic_types` calls it `covertArtRole`, and has no `playlistRole` for
+// updates. Without `musicFolderId`, or with all of them, users see every folder.
+// `newApiKey=true` issues a key for OpenSubsonic `apiKey` authentication,
+// replacing any earlier one, and returns it as `apiKey`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateUser {
+    username: String,
+    password: String,
+    email: Option<String>,
+    admin_role: Option<bool>,
+    settings_role: Option<bool>,
+    stream_role: Option<bool>,
+    jukebox_role: Option<bool>,
+    download_role: Option<bool>,
+    upload_role: Option<bool>,
+    playlist_role: Option<bool>,
+    cover_art_role: Option<bool>,
+    comment_role: Option<bool>,
+    share_role: Option<bool>,
+    #[serde(default)]
+    music_folder_id: Vec<String>,
+    new_api_key: Option<bool>,
+}
+
+// what's not given stays as it is
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateUser {
+    username: String,
+    password: Option<String>,
+    email: Option<String>,
+    admin_role: Option<bool>,
+    settings_role: Option<bool>,
+    stream_role: Option<bool>,
+    jukebox_role: Option<bool>,
+    download_role: Option<bool>,
+    upload_role: Option<bool>,
+    playlist_role: Option<bool>,
+    cover_art_role: Option<bool>,
+    comment_role: Option<bool>,
+    share_role: Option<bool>,
+    #[serde(default)]
+    music_folder_id: Vec<String>,
+    new_api_key: Option<bool>,
+}
+
+async fn user(state: &AppState, user: user::Model) -> User {
+    let folder = visible_folders(state, &user)
+        .await
+        .into_iter()
+        .map(|folder| folder.id as u32)
+        .collect();
+    User {
+        username: user.username,
+        email: user.email,
+        scrobbling_enabled: true,
+        admin_role: user.admin,
+        settings_role: user.settings,
+        download_role: user.download,
+        upload_role: user.upload,
+        playlist_role: user.playlist,
+        cover_art_role: user.cover_art,
+        comment_role: user.comment,
+        stream_role: user.stream,
+        jukebox_role: user.jukebox,
+        share_role: user.share,
+        folder,
+        ..Default::default()
+    }
+}
+
+fn api_key() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
+
+// the response to `createUser` and `updateUser`, with the key if one was issued
+fn issued(api_key: Option<String>) -> SR {
+    match api_key {
+        Some(api_key) => SR::ok(ResponseBody::Empty).with(json!({ "apiKey": api_key })),
+        None => SR::ok(ResponseBody::Empty),
+    }
+}
+
+fn password(password: &str) -> Result<String, SR> {
+    match decode_password(password) {
+        Some(password) if !password.is_empty() => Ok(password),
+        _ => Err(SR::error(ErrorCode::Generic, "invalid password")),
+    }
+}
+
+// the IDs of configured music folders, or else which one isn't. All of them
+// are no restriction at all, so folders configured later are included too
+fn folder_ids(state: &AppState, ids: &[String]) -> Result<Vec<i32>, SR> {
+    let folders = state.indexer.music_folders();
+    let ids = ids
+        .iter()
+        .map(|id| {
+            folders
+                .iter()
+                .find(|folder| *id == folder.id.to_string())
+                .map(|folder| folder.id)
+                .ok_or_else(|| SR::not_found(id))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if folders.iter().all(|folder| ids.contains(&folder.id)) {
+        return Ok(vec![]);
+    }
+    Ok(ids)
+}
+
+// `AV::Set` if given, for the roles of `UpdateUser`
+fn set<T: Into<sea_orm::Value>>(value: Option<T>) -> AV<T> {
+    value.map(AV::Set).unwrap_or(AV::NotSet)
+}
+
+// users see themselves, admins everyone
+async fn get_user(
+    State(state): State<AppState>,
+    Extension(current): Extension<user::Model>,
+    Params(query): Params<GetUser>,
+) -> Result<SR, SR> {
+    if query.username != current.username {
+        require(&current, Role::Admin)?;
+    }
+    let Some(found) = state.db.get_user(&query.username).await else {
+        return Err(SR::not_found(&query.username));
+    };
+    Ok(SR::ok(ResponseBody::User(user(&state, found).await)))
+}
+
+async fn get_users(State(state): State<AppState>) -> SR {
+    let mut users = vec![];
+    for found in state.db.get_users().await {
+        users.push(user(&state, found).await);
+    }
+    SR::ok(ResponseBody::Users(Users { user: users }))
+}
+
+// roles not given are as Subsonic has them by default
+async fn create_user(
+    State(state): State<AppState>,
+    Params(query): Params<CreateUser>,
+) -> Result<SR, SR> {
+    if state.db.get_user(&query.username).await.is_some() {
+        return Err(SR::error(
+            ErrorCode::Generic,
+            format!("user {} exists already", query.username),
+        ));
+    }
+    let folders = folder_ids(&state, &query.music_folder_id)?;
+    let api_key = query.new_api_key.unwrap_or(false).then(api_key);
+    let created = user::ActiveModel {
+        username: AV::Set(query.username),
+        password: AV::Set(Some(password(&query.password)?)),
+        email: AV::Set(query.email.filter(|email| !email.is_empty())),
+        admin: AV::Set(query.admin_role.unwrap_or(false)),
+        settings: AV::Set(query.settings_role.unwrap_or(true)),
+        download: AV::Set(query.download_role.unwrap_or(false)),
+        upload: AV::Set(query.upload_role.unwrap_or(false)),
+        playlist: AV::Set(query.playlist_role.unwrap_or(false)),
+        cover_art: AV::Set(query.cover_art_role.unwrap_or(false)),
+        comment: AV::Set(query.comment_role.unwrap_or(false)),
+        share: AV::Set(query.share_role.unwrap_or(false)),
+        jukebox: AV::Set(query.jukebox_role.unwrap_or(false)),
+        stream: AV::Set(query.stream_role.unwrap_or(true)),
+        api_key: AV::Set(api_key.clone()),
+        ..Default::default()
+    };
+    state.db.create_user(created, &folders).await?;
+    Ok(issued(api_key))
+}
+
+async fn update_user(
+    State(state): State<AppState>,
+    Extension(current): Extension<user::Model>,
+    Params(query): Params<UpdateUser>,
+) -> Result<SR, SR> {
+    // or nobody might be left to undo it
+    if query.username == current.username && query.admin_role == Some(false) {
+        return Err(SR::error(
+            ErrorCode::Generic,
+            "admins can't revoke their own admin role",
+        ));
+    }
+    let Some(found) = state.db.get_user(&query.username).await else {
+        return Err(SR::not_found(&query.username));
+    };
+    // the folders stay as they are unless given
+    let folders = (!query.music_folder_id.is_empty())
+        .then(|| folder_ids(&state, &query.music_folder_id))
+        .transpose()?;
+    let mut updated = found.into_active_model();
+    if let Some(new) = &query.password {
+        updated.password = AV::Set(Some(password(new)?));
+    }
+    if let Some(email) = query.email {
+        updated.email = AV::Set(Some(email).filter(|email| !email.is_empty()));
+    }
+    updated.admin = set(query.admin_role);
+    updated.settings = set(query.settings_role);
+    updated.download = set(query.download_role);
+    updated.upload = set(query.upload_role);
+    updated.playlist = set(query.playlist_role);
+    updated.cover_art = set(query.cover_art_role);
+    updated.comment = set(query.comment_role);
+    updated.share = set(query.share_role);
+    updated.jukebox = set(query.jukebox_role);
+    updated.stream = set(query.stream_role);
+    let api_key = query.new_api_key.unwrap_or(false).then(api_key);
+    if let Some(api_key) = &api_key {
+        updated.api_key = AV::Set(Some(api_key.clone()));
+    }
+    state.db.update_user(updated, folders.as_deref()).await?;
+    Ok(issued(api_key))
+}
+
+async fn delete_user(
+    State(state): State<AppState>,
+    Extension(current): Extension<user::Model>,
+    Params(query): Params<DeleteUser>,
+) -> Result<SR, SR> {
+    if query.username == current.username {
+        return Err(SR::error(
+            ErrorCode::Generic,
+            "users can't delete themselves",
+        ));
+    }
+    state.db.delete_user(&query.username).await?;
+    Ok(SR::ok(ResponseBody::Empty))
+}
+
+// their own with `settingsRole`, anybody's as admin
+async fn change_password(
+    State(state): State<AppState>,
+    Extension(current): Extension<user::Model>,
+    Params(query): Params<ChangePassword>,
+) -> Result<SR, SR> {
+    if query.username == current.username {
+        require(&current, Role::Settings)?;
+    } else {
+        require(&current, Role::Admin)?;
+    }
+    let Some(found) = state.db.get_user(&query.username).await else {
+        return Err(SR::not_found(&query.username));
+    };
+    let mut updated = found.into_active_model();
+    updated.password = AV::Set(Some(password(&query.password)?));
+    state.db.update_user(updated, None).await?;
+    Ok(SR::ok(ResponseBody::Empty))
+}
+
+pub(super) fn router() -> Router<AppState> {
+    Router::new()
+        .route("/getUser.view", get(get_user))
+        .route("/getUsers.view", get(get_users))
+        .route("/createUser.view", get(create_user))
+        .route("/updateUser.view", get(update_user))
+        .route("/deleteUser.view", get(delete_user))
+        .route("/changePassword.view", get(change_password))
+}
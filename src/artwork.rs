@@ -0,0 +1,54 @@
+//! derived images: cover art scaled down for list rows and grids
+
+use std::io;
+
+use camino::Utf8Path;
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage};
+use tracing::debug;
+
+/// the edge lengths thumbnails are rendered at; requests are rounded up to the
+/// next one, so a handful of files covers every client
+pub const THUMBNAIL_SIZES: [u32; 5] = [64, 128, 256, 512, 1024];
+
+const JPEG_QUALITY: u8 = 85;
+
+/// the thumbnail size to serve for `requested`, `None` if only the original will do
+pub fn thumbnail_size(requested: u32) -> Option<u32> {
+    THUMBNAIL_SIZES.into_iter().find(|size| *size >= requested)
+}
+
+// decodes, scales down to fit into `size`², and encodes as JPEG
+fn render(data: &[u8], size: u32) -> image::ImageResult<Vec<u8>> {
+    let mut image = image::load_from_memory(data)?;
+    if image.width() > size || image.height() > size {
+        image = image.resize(size, size, FilterType::Lanczos3);
+    }
+    // JPEG has no alpha channel
+    let image = DynamicImage::from(image.to_rgb8());
+    let mut out = vec![];
+    image.write_with_encoder(JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY))?;
+    Ok(out)
+}
+
+/// makes sure `target` holds a thumbnail of `source`; as artwork is stored by
+/// content, one that exists already is up to date
+pub async fn thumbnail(source: &Utf8Path, target: &Utf8Path, size: u32) -> io::Result<()> {
+    if tokio::fs::try_exists(target).await? {
+        return Ok(());
+    }
+
+    debug!("rendering {size}px thumbnail of {source}");
+    let data = tokio::fs::read(source).await?;
+    let thumbnail = tokio::task::spawn_blocking(move || render(&data, size))
+        .await
+        .map_err(io::Error::other)?
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    if let Some(dir) = target.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    // concurrent requests for the same thumbnail each write their own file
+    let part = target.with_extension(format!("{:08x}.part", rand::random::<u32>()));
+    tokio::fs::write(&part, thumbnail).await?;
+    tokio::fs::rename(&part, target).await
+}
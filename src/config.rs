@@ -1,5 +1,6 @@
 use std::{fs::File, io::Read};
 
+use camino::{Utf8Path, Utf8PathBuf};
 use serde::Deserialize;
 use thiserror::Error;
 use tracing::info;
@@ -9,6 +10,10 @@ pub struct Config {
     pub system: System,
     pub media: Media,
     pub indexer: Indexer,
+    #[serde(default)]
+    pub bookmarks: Bookmarks,
+    #[serde(default)]
+    pub radio: Radio,
 }
 
 #[derive(Deserialize)]
@@ -18,20 +23,104 @@ pub struct System {
     pub bind_addr: String,
     pub base_url: Option<String>,
     pub dev: bool,
+    /// for the `admin` user created on first start, a generated one is logged otherwise
+    pub admin_password: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct Media {
-    pub paths: Vec<camino::Utf8PathBuf>,
+    /// each one is a music folder
+    pub paths: Vec<MediaPath>,
 }
 
-#[derive(Deserialize, Clone)]
+/// `"/media/mp3z"`, or `{ path = "/media/mp3z", name = "mp3z" }` to name its
+/// music folder
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum MediaPath {
+    Path(Utf8PathBuf),
+    Named { path: Utf8PathBuf, name: String },
+}
+
+impl MediaPath {
+    pub fn path(&self) -> &Utf8Path {
+        match self {
+            MediaPath::Path(path) | MediaPath::Named { path, .. } => path,
+        }
+    }
+
+    /// as configured, or else the last component of the path
+    pub fn name(&self) -> &str {
+        match self {
+            MediaPath::Path(path) => path.file_name().unwrap_or(path.as_str()),
+            MediaPath::Named { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
 pub struct Indexer {
     pub enable: bool,
     pub exclude: Exclude,
+    #[serde(default)]
+    pub artwork: Artwork,
+    /// split genre tags holding several genres ("Rock; Pop") on these
+    #[serde(default = "default_genre_separators")]
+    pub genre_separators: Vec<String>,
+}
+
+fn default_genre_separators() -> Vec<String> {
+    [";", "/", ","].map(String::from).into()
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Artwork {
+    /// where album art comes from, most preferred first; unlisted sources are not used
+    pub precedence: Vec<ArtworkSource>,
+    /// file names (any image extension, case insensitive) looked for next to songs
+    pub folder: Vec<String>,
+    /// the same, in the artist directory
+    pub artist: Vec<String>,
+}
+
+impl Default for Artwork {
+    fn default() -> Self {
+        Artwork {
+            precedence: vec![ArtworkSource::Embedded, ArtworkSource::Folder],
+            folder: ["cover", "folder", "front", "album"]
+                .map(String::from)
+                .into(),
+            artist: vec!["artist".to_string()],
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtworkSource {
+    /// pictures in the song's tags
+    Embedded,
+    /// image files next to the songs
+    Folder,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Bookmarks {
+    /// songs longer than this many minutes are bookmarked once they start playing
+    pub auto_minutes: Option<u32>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Radio {
+    /// relay station streams via `radioProxy`, for pages served over HTTPS that
+    /// can't play plain HTTP streams
+    pub proxy: bool,
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Clone, Debug)]
 pub struct Exclude {
     pub files: Vec<String>,
     pub dirs: Vec<String>,
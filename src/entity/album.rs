@@ -0,0 +1,78 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{artist, song};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "album")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    pub name: String,
+    // the album artist (falling back to the track artist)
+    pub artist_id: Option<i32>,
+    pub artist: Option<String>,
+    // albums are told apart by (artist, name, directory)
+    pub directory: String,
+    pub year: Option<u32>,
+    pub genre: Option<String>,
+    // id of a `cover_art` row, taken from the first song that comes with a picture
+    pub cover_art: Option<i32>,
+    // sum of the play counts of its songs
+    pub play_count: u32,
+    // unix milliseconds of the most recent play of any of its songs
+    pub played: Option<i64>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "artist::Entity",
+        from = "Column::ArtistId",
+        to = "artist::Column::Id"
+    )]
+    Artist,
+    #[sea_orm(has_many = "song::Entity")]
+    Song,
+}
+
+impl Related<artist::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Artist.def()
+    }
+}
+
+impl Related<song::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Song.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// Subsonic IDs share one namespace across songs, albums and artists
+const ID_PREFIX: &str = "al-";
+
+pub(crate) fn format_id(id: i32) -> String {
+    format!("{ID_PREFIX}{id}")
+}
+
+pub(crate) fn parse_id(id: &str) -> Option<i32> {
+    id.strip_prefix(ID_PREFIX)?.parse().ok()
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum Album {
+    Table,
+    Id,
+    Name,
+    ArtistId,
+    Artist,
+    Directory,
+    Year,
+    Genre,
+    CoverArt,
+    PlayCount,
+    Played,
+}
@@ -0,0 +1,171 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{album, artist, directory, song, user};
+
+/// a user's star and rating of exactly one of song, album, artist or directory
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "annotation")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    pub user: i32,
+    pub song: Option<i32>,
+    pub album: Option<i32>,
+    pub artist: Option<i32>,
+    pub directory: Option<i32>,
+    // unix timestamp
+    pub starred: Option<i64>,
+    // 1 to 5
+    pub rating: Option<u32>,
+}
+
+impl Model {
+    /// a blank annotation
+    pub(crate) fn new(user: i32, item: Item) -> Self {
+        let mut model = Model {
+            id: 0,
+            user,
+            song: None,
+            album: None,
+            artist: None,
+            directory: None,
+            starred: None,
+            rating: None,
+        };
+        match item {
+            Item::Song(id) => model.song = Some(id),
+            Item::Album(id) => model.album = Some(id),
+            Item::Artist(id) => model.artist = Some(id),
+            Item::Directory(id) => model.directory = Some(id),
+        }
+        model
+    }
+
+    pub(crate) fn item(&self) -> Option<Item> {
+        self.song
+            .map(Item::Song)
+            .or(self.album.map(Item::Album))
+            .or(self.artist.map(Item::Artist))
+            .or(self.directory.map(Item::Directory))
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "user::Entity",
+        from = "Column::User",
+        to = "user::Column::Id"
+    )]
+    User,
+    #[sea_orm(
+        belongs_to = "song::Entity",
+        from = "Column::Song",
+        to = "song::Column::Id"
+    )]
+    Song,
+    #[sea_orm(
+        belongs_to = "album::Entity",
+        from = "Column::Album",
+        to = "album::Column::Id"
+    )]
+    Album,
+    #[sea_orm(
+        belongs_to = "artist::Entity",
+        from = "Column::Artist",
+        to = "artist::Column::Id"
+    )]
+    Artist,
+    #[sea_orm(
+        belongs_to = "directory::Entity",
+        from = "Column::Directory",
+        to = "directory::Column::Id"
+    )]
+    Directory,
+}
+
+impl Related<user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<song::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Song.def()
+    }
+}
+
+impl Related<album::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Album.def()
+    }
+}
+
+impl Related<artist::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Artist.def()
+    }
+}
+
+impl Related<directory::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Directory.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// anything that can be starred or rated, told apart by the format of its Subsonic ID
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Item {
+    Song(i32),
+    Album(i32),
+    Artist(i32),
+    Directory(i32),
+}
+
+impl Item {
+    pub(crate) fn parse(id: &str) -> Option<Self> {
+        album::parse_id(id)
+            .map(Item::Album)
+            .or_else(|| artist::parse_id(id).map(Item::Artist))
+            .or_else(|| directory::parse_id(id).map(Item::Directory))
+            .or_else(|| id.parse().ok().map(Item::Song))
+    }
+
+    pub(crate) fn column(self) -> (Column, i32) {
+        match self {
+            Item::Song(id) => (Column::Song, id),
+            Item::Album(id) => (Column::Album, id),
+            Item::Artist(id) => (Column::Artist, id),
+            Item::Directory(id) => (Column::Directory, id),
+        }
+    }
+}
+
+impl std::fmt::Display for Item {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Item::Song(id) => write!(f, "{id}"),
+            Item::Album(id) => f.write_str(&album::format_id(id)),
+            Item::Artist(id) => f.write_str(&artist::format_id(id)),
+            Item::Directory(id) => f.write_str(&directory::format_id(id)),
+        }
+    }
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum Annotation {
+    Table,
+    Id,
+    User,
+    Song,
+    Album,
+    Artist,
+    Directory,
+    Starred,
+    Rating,
+}
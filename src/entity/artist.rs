@@ -0,0 +1,57 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{album, song};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "artist")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    #[sea_orm(unique)]
+    pub name: String,
+    // id of a `cover_art` row, from an image in the artist directory
+    pub cover_art: Option<i32>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "album::Entity")]
+    Album,
+    #[sea_orm(has_many = "song::Entity")]
+    Song,
+}
+
+impl Related<album::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Album.def()
+    }
+}
+
+impl Related<song::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Song.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// prefixed so artist IDs can't be mistaken for song or album IDs
+const ID_PREFIX: &str = "ar-";
+
+pub(crate) fn format_id(id: i32) -> String {
+    format!("{ID_PREFIX}{id}")
+}
+
+pub(crate) fn parse_id(id: &str) -> Option<i32> {
+    id.strip_prefix(ID_PREFIX)?.parse().ok()
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum Artist {
+    Table,
+    Id,
+    Name,
+    CoverArt,
+}
@@ -0,0 +1,63 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{song, user};
+
+/// where a user left off in a song, at most one per user and song
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "bookmark")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    pub user: i32,
+    pub song: i32,
+    // in milliseconds
+    pub position: i64,
+    pub comment: Option<String>,
+    // unix timestamps
+    pub created: i64,
+    pub changed: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "user::Entity",
+        from = "Column::User",
+        to = "user::Column::Id"
+    )]
+    User,
+    #[sea_orm(
+        belongs_to = "song::Entity",
+        from = "Column::Song",
+        to = "song::Column::Id"
+    )]
+    Song,
+}
+
+impl Related<user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<song::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Song.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(DeriveIden)]
+pub(crate) enum Bookmark {
+    Table,
+    Id,
+    User,
+    Song,
+    Position,
+    Comment,
+    Created,
+    Changed,
+}
@@ -10,32 +10,70 @@ pub struct Model {
     #[sea_orm(primary_key)]
     #[serde(skip_deserializing)]
     pub id: i32,
+    // the first byte of `hash`, or a random one of 512 for rows that predate it
     pub shard: i32,
     pub mime_type: String,
-    pub song: i32,
+    // the song it is embedded in, `None` for images found in folders
+    pub song: Option<i32>,
+    // the image file it was copied from, `None` for embedded pictures
+    #[sea_orm(unique)]
+    pub path: Option<String>,
+    // hex MD5 of the image, which is stored once no matter how many rows share it;
+    // `None` until the garbage collector moved a file from the old per-row layout
+    pub hash: Option<String>,
+}
+
+fn artwork_dir(root: &Utf8Path) -> Utf8PathBuf {
+    root.join("data").join("artwork")
+}
+
+fn shard_of(hash: &str) -> i32 {
+    hash.get(..2)
+        .and_then(|byte| i32::from_str_radix(byte, 16).ok())
+        .unwrap_or_default()
 }
 
 impl Model {
-    fn path_inner(id: i32, shard: i32, root: &Utf8Path) -> Utf8PathBuf {
-        let mut path = root.join("data");
-        path.push("artwork");
-        path.push(format!("{shard}"));
-        path.push(format!("{id}"));
+    pub fn path(&self, root: &Utf8Path) -> Utf8PathBuf {
+        let mut path = artwork_dir(root);
+        path.push(format!("{}", self.shard));
+        match &self.hash {
+            Some(hash) => path.push(hash),
+            None => path.push(format!("{}", self.id)),
+        }
         path
     }
 
-    pub fn path(&self, root: &Utf8Path) -> Utf8PathBuf {
-        Self::path_inner(self.id, self.shard, root)
+    /// where the JPEG scaled down to `size` goes, sharded like the original;
+    /// `None` for rows still waiting for their file to be moved
+    pub fn thumbnail_path(&self, root: &Utf8Path, size: u32) -> Option<Utf8PathBuf> {
+        let hash = self.hash.as_ref()?;
+        let mut path = root.join("data");
+        path.push("thumbnails");
+        path.push(format!("{}", self.shard));
+        path.push(format!("{hash}-{size}.jpg"));
+        Some(path)
     }
 
-    pub async fn write(data: &[u8], id: i32, shard: i32, root: &Utf8Path) -> std::io::Result<()> {
-        let path = Self::path_inner(id, shard, root);
-        let containing_dir = path.parent().expect("could not create containing path");
-        tokio::fs::create_dir_all(containing_dir).await?;
+    /// stores `data` by content unless it is there already; returns shard and hash
+    pub async fn write(data: &[u8], root: &Utf8Path) -> std::io::Result<(i32, String)> {
+        let hash = format!("{:x}", md5::compute(data));
+        let shard = shard_of(&hash);
+        let dir = artwork_dir(root).join(format!("{shard}"));
+        let path = dir.join(&hash);
+        if tokio::fs::try_exists(&path).await? {
+            return Ok((shard, hash));
+        }
+
+        tokio::fs::create_dir_all(&dir).await?;
         trace!("write {path}");
-        let mut file = File::create(path).await?;
+        // a file under its final name is always complete
+        let part = dir.join(format!("{hash}.part"));
+        let mut file = File::create(&part).await?;
         file.write_all(data).await?;
-        Ok(())
+        file.flush().await?;
+        tokio::fs::rename(part, path).await?;
+        Ok((shard, hash))
     }
 }
 
@@ -56,3 +94,14 @@ impl Related<super::song::Entity> for Entity {
 }
 
 impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(DeriveIden)]
+pub(crate) enum CoverArt {
+    Table,
+    Id,
+    Shard,
+    MimeType,
+    Song,
+    Path,
+    Hash,
+}
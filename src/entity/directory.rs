@@ -0,0 +1,45 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "directory")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    #[sea_orm(unique)]
+    pub path: String,
+    pub name: String,
+    // `None` for media path roots
+    pub parent: Option<i32>,
+    // unix timestamp in milliseconds, bumped whenever a song is indexed in here
+    pub modified: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(belongs_to = "Entity", from = "Column::Parent", to = "Column::Id")]
+    Parent,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+const ID_PREFIX: &str = "dir-";
+
+pub(crate) fn format_id(id: i32) -> String {
+    format!("{ID_PREFIX}{id}")
+}
+
+pub(crate) fn parse_id(id: &str) -> Option<i32> {
+    id.strip_prefix(ID_PREFIX)?.parse().ok()
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum Directory {
+    Table,
+    Id,
+    Path,
+    Name,
+    Parent,
+    Modified,
+}
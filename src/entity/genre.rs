@@ -0,0 +1,36 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::song_genre;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "genre")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    // unique regardless of case, the first spelling seen wins
+    #[sea_orm(unique)]
+    pub name: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "song_genre::Entity")]
+    SongGenre,
+}
+
+impl Related<song_genre::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SongGenre.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(DeriveIden)]
+pub(crate) enum Genre {
+    Table,
+    Id,
+    Name,
+}
@@ -0,0 +1,37 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "internet_radio_station")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    pub name: String,
+    pub stream_url: String,
+    pub homepage_url: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+const ID_PREFIX: &str = "ir-";
+
+pub(crate) fn format_id(id: i32) -> String {
+    format!("{ID_PREFIX}{id}")
+}
+
+pub(crate) fn parse_id(id: &str) -> Option<i32> {
+    id.strip_prefix(ID_PREFIX)?.parse().ok()
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum InternetRadioStation {
+    Table,
+    Id,
+    Name,
+    StreamUrl,
+    HomepageUrl,
+}
@@ -0,0 +1,53 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::song;
+
+/// ISO 639-2 for "no linguistic content", used when the language is unknown
+pub const UNKNOWN_LANGUAGE: &str = "xxx";
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "lyrics")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    pub song: i32,
+    // ISO 639-2, one row per language
+    pub lang: String,
+    // synced lyrics are kept as LRC (`[mm:ss.xxx]line`), so the timestamps survive
+    pub synced: bool,
+    pub text: String,
+    pub display_artist: Option<String>,
+    pub display_title: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "song::Entity",
+        from = "Column::Song",
+        to = "song::Column::Id"
+    )]
+    Song,
+}
+
+impl Related<song::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Song.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(DeriveIden)]
+pub(crate) enum Lyrics {
+    Table,
+    Id,
+    Song,
+    Lang,
+    Synced,
+    Text,
+    DisplayArtist,
+    DisplayTitle,
+}
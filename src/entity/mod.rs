@@ -1,4 +1,22 @@
 pub mod album;
+pub mod annotation;
 pub mod artist;
+pub mod bookmark;
 pub mod cover_art;
+pub mod directory;
+pub mod genre;
+pub mod internet_radio_station;
+pub mod lyrics;
+pub mod music_folder;
+pub mod play_history;
+pub mod play_queue;
+pub mod play_queue_entry;
+pub mod playlist;
+pub mod playlist_entry;
+pub mod share;
+pub mod share_entry;
 pub mod song;
+pub mod song_artist;
+pub mod song_genre;
+pub mod user;
+pub mod user_music_folder;
@@ -0,0 +1,56 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{song, user};
+
+/// one row per scrobble submission
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "play_history")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    pub user: i32,
+    pub song: i32,
+    // unix milliseconds, as given by the client
+    pub time: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "user::Entity",
+        from = "Column::User",
+        to = "user::Column::Id"
+    )]
+    User,
+    #[sea_orm(
+        belongs_to = "song::Entity",
+        from = "Column::Song",
+        to = "song::Column::Id"
+    )]
+    Song,
+}
+
+impl Related<user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<song::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Song.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(DeriveIden)]
+pub(crate) enum PlayHistory {
+    Table,
+    Id,
+    User,
+    Song,
+    Time,
+}
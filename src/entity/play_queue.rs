@@ -0,0 +1,60 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{play_queue_entry, user};
+
+/// where a user left off, one per user
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "play_queue")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    #[sea_orm(unique)]
+    pub user: i32,
+    // index into the entries, since a song can be queued more than once
+    pub current: Option<u32>,
+    // in milliseconds, within the current song
+    pub position: i64,
+    // unix timestamp
+    pub changed: i64,
+    // the client that saved it
+    pub changed_by: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "user::Entity",
+        from = "Column::User",
+        to = "user::Column::Id"
+    )]
+    User,
+    #[sea_orm(has_many = "play_queue_entry::Entity")]
+    Entry,
+}
+
+impl Related<user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<play_queue_entry::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Entry.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(DeriveIden)]
+pub(crate) enum PlayQueue {
+    Table,
+    Id,
+    User,
+    Current,
+    Position,
+    Changed,
+    ChangedBy,
+}
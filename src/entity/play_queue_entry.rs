@@ -0,0 +1,55 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{play_queue, song};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "play_queue_entry")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    pub play_queue: i32,
+    pub song: i32,
+    // 0-based, contiguous within a play queue
+    pub position: u32,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "play_queue::Entity",
+        from = "Column::PlayQueue",
+        to = "play_queue::Column::Id"
+    )]
+    PlayQueue,
+    #[sea_orm(
+        belongs_to = "song::Entity",
+        from = "Column::Song",
+        to = "song::Column::Id"
+    )]
+    Song,
+}
+
+impl Related<play_queue::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PlayQueue.def()
+    }
+}
+
+impl Related<song::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Song.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(DeriveIden)]
+pub(crate) enum PlayQueueEntry {
+    Table,
+    Id,
+    PlayQueue,
+    Song,
+    Position,
+}
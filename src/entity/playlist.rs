@@ -0,0 +1,67 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{playlist_entry, user};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "playlist")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    pub name: String,
+    pub comment: Option<String>,
+    pub owner: i32,
+    pub public: bool,
+    // unix timestamps
+    pub created: i64,
+    pub changed: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "user::Entity",
+        from = "Column::Owner",
+        to = "user::Column::Id"
+    )]
+    Owner,
+    #[sea_orm(has_many = "playlist_entry::Entity")]
+    Entry,
+}
+
+impl Related<user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Owner.def()
+    }
+}
+
+impl Related<playlist_entry::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Entry.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+const ID_PREFIX: &str = "pl-";
+
+pub(crate) fn format_id(id: i32) -> String {
+    format!("{ID_PREFIX}{id}")
+}
+
+pub(crate) fn parse_id(id: &str) -> Option<i32> {
+    id.strip_prefix(ID_PREFIX)?.parse().ok()
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum Playlist {
+    Table,
+    Id,
+    Name,
+    Comment,
+    Owner,
+    Public,
+    Created,
+    Changed,
+}
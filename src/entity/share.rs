@@ -0,0 +1,64 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{share_entry, user};
+
+/// songs anyone with the link may listen to, until it expires
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "share")]
+pub struct Model {
+    // random, as it's all that protects the songs
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: String,
+    pub user: i32,
+    pub description: Option<String>,
+    // unix timestamps
+    pub created: i64,
+    pub expires: Option<i64>,
+    pub last_visited: Option<i64>,
+    pub visit_count: u32,
+}
+
+impl Model {
+    pub(crate) fn expired(&self, now: i64) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "user::Entity",
+        from = "Column::User",
+        to = "user::Column::Id"
+    )]
+    User,
+    #[sea_orm(has_many = "share_entry::Entity")]
+    Entry,
+}
+
+impl Related<user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<share_entry::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Entry.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(DeriveIden)]
+pub(crate) enum Share {
+    Table,
+    Id,
+    User,
+    Description,
+    Created,
+    Expires,
+    LastVisited,
+    VisitCount,
+}
@@ -1,9 +1,9 @@
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use super::cover_art;
+use super::{album, artist, cover_art, lyrics, song_artist, song_genre};
 
-#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "song")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -22,12 +22,54 @@ pub struct Model {
     pub cover_art: Option<String>,
     pub size: Option<u32>,
     pub content_type: Option<String>,
+    pub album_artist: Option<String>,
+    pub disc: Option<u32>,
+    pub album_id: Option<i32>,
+    pub artist_id: Option<i32>,
+    pub play_count: u32,
+    // unix milliseconds of the most recent play
+    pub played: Option<i64>,
+    // what the indexer knew to read from tags at the time, see `indexer::TAG_VERSION`
+    pub tag_version: i32,
+    // in kbit/s, as encoded
+    pub bit_rate: Option<u32>,
+    // in Hz
+    pub sampling_rate: Option<u32>,
+    pub channel_count: Option<u32>,
+    // ReplayGain, in dB
+    pub track_gain: Option<f64>,
+    pub track_peak: Option<f64>,
+    pub album_gain: Option<f64>,
+    pub album_peak: Option<f64>,
+    // the MusicBrainz recording ID
+    pub music_brainz_id: Option<String>,
+    pub sort_name: Option<String>,
+    // the music folder whose media path the song is in
+    pub folder: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_one = "cover_art::Entity")]
     CoverArt,
+    #[sea_orm(
+        belongs_to = "album::Entity",
+        from = "Column::AlbumId",
+        to = "album::Column::Id"
+    )]
+    Album,
+    #[sea_orm(
+        belongs_to = "artist::Entity",
+        from = "Column::ArtistId",
+        to = "artist::Column::Id"
+    )]
+    Artist,
+    #[sea_orm(has_many = "song_genre::Entity")]
+    SongGenre,
+    #[sea_orm(has_many = "lyrics::Entity")]
+    Lyrics,
+    #[sea_orm(has_many = "song_artist::Entity")]
+    SongArtist,
 }
 
 impl Related<cover_art::Entity> for Entity {
@@ -36,6 +78,36 @@ impl Related<cover_art::Entity> for Entity {
     }
 }
 
+impl Related<album::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Album.def()
+    }
+}
+
+impl Related<artist::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Artist.def()
+    }
+}
+
+impl Related<song_genre::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SongGenre.def()
+    }
+}
+
+impl Related<lyrics::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Lyrics.def()
+    }
+}
+
+impl Related<song_artist::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SongArtist.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
 
 #[derive(DeriveIden)]
@@ -54,4 +126,21 @@ pub(crate) enum Song {
     CoverArt,
     Size,
     ContentType,
+    AlbumArtist,
+    Disc,
+    AlbumId,
+    ArtistId,
+    PlayCount,
+    Played,
+    TagVersion,
+    BitRate,
+    SamplingRate,
+    ChannelCount,
+    TrackGain,
+    TrackPeak,
+    AlbumGain,
+    AlbumPeak,
+    MusicBrainzId,
+    SortName,
+    Folder,
 }
@@ -0,0 +1,103 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "user")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    #[sea_orm(unique)]
+    pub username: String,
+    // stored in the clear: token auth needs `md5(password + salt)` on our side too.
+    // `None` means the user can only log in via API key
+    #[serde(skip_serializing)]
+    pub password: Option<String>,
+    #[sea_orm(unique)]
+    #[serde(skip_serializing)]
+    pub api_key: Option<String>,
+    pub email: Option<String>,
+    // roles, see `Role`
+    pub admin: bool,
+    pub settings: bool,
+    pub download: bool,
+    pub upload: bool,
+    pub playlist: bool,
+    pub cover_art: bool,
+    pub comment: bool,
+    pub share: bool,
+    pub jukebox: bool,
+    pub stream: bool,
+}
+
+/// what a user may do; admins may do anything. Nothing needs the upload,
+/// cover art or jukebox roles yet, they are only stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Role {
+    /// manage users, radio stations and scans
+    Admin,
+    /// change their own password
+    Settings,
+    Download,
+    /// create, change and delete playlists
+    Playlist,
+    /// ratings
+    Comment,
+    Share,
+    Stream,
+}
+
+impl Role {
+    /// as Subsonic calls it
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Role::Admin => "adminRole",
+            Role::Settings => "settingsRole",
+            Role::Download => "downloadRole",
+            Role::Playlist => "playlistRole",
+            Role::Comment => "commentRole",
+            Role::Share => "shareRole",
+            Role::Stream => "streamRole",
+        }
+    }
+}
+
+impl Model {
+    pub(crate) fn has(&self, role: Role) -> bool {
+        self.admin
+            || match role {
+                Role::Admin => false,
+                Role::Settings => self.settings,
+                Role::Download => self.download,
+                Role::Playlist => self.playlist,
+                Role::Comment => self.comment,
+                Role::Share => self.share,
+                Role::Stream => self.stream,
+            }
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(DeriveIden)]
+pub(crate) enum User {
+    Table,
+    Id,
+    Username,
+    Password,
+    ApiKey,
+    Email,
+    Admin,
+    Settings,
+    Download,
+    Upload,
+    Playlist,
+    CoverArt,
+    Comment,
+    Share,
+    Jukebox,
+    Stream,
+}
@@ -0,0 +1,53 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{music_folder, user};
+
+/// the music folders a user is restricted to; users without any see them all
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "user_music_folder")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    pub user: i32,
+    pub folder: i32,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "user::Entity",
+        from = "Column::User",
+        to = "user::Column::Id"
+    )]
+    User,
+    #[sea_orm(
+        belongs_to = "music_folder::Entity",
+        from = "Column::Folder",
+        to = "music_folder::Column::Id"
+    )]
+    MusicFolder,
+}
+
+impl Related<user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<music_folder::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MusicFolder.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(DeriveIden)]
+pub(crate) enum UserMusicFolder {
+    Table,
+    Id,
+    User,
+    Folder,
+}
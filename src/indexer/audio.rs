@@ -0,0 +1,157 @@
+//! technical properties of the audio stream (bit rate, sampling rate, channels)
+
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+};
+
+use camino::Utf8Path;
+
+// how far past the tags to look for the first frame
+const SCAN_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct Properties {
+    /// in kbit/s
+    pub(super) bit_rate: Option<u32>,
+    /// in Hz
+    pub(super) sampling_rate: Option<u32>,
+    pub(super) channel_count: Option<u32>,
+}
+
+// in kbit/s, by MPEG version (1 or 2/2.5), layer (I, II, III) and index
+#[rustfmt::skip]
+const BIT_RATES: [[[u32; 14]; 3]; 2] = [
+    [
+        [32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448],
+        [32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384],
+        [32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320],
+    ],
+    [
+        [32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256],
+        [8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+        [8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+    ],
+];
+
+const SAMPLING_RATES: [u32; 3] = [44100, 48000, 32000];
+
+#[derive(Debug)]
+struct FrameHeader {
+    mpeg1: bool,
+    // 1, 2 or 3
+    layer: usize,
+    bit_rate: u32,
+    sampling_rate: u32,
+    mono: bool,
+}
+
+impl FrameHeader {
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        let [0xff, b1, b2, b3, ..] = *bytes else {
+            return None;
+        };
+        if b1 & 0xe0 != 0xe0 {
+            return None;
+        }
+        // 0: 2.5, 1: reserved, 2: 2, 3: 1
+        let version = (b1 >> 3) & 3;
+        let layer = match (b1 >> 1) & 3 {
+            0 => return None,
+            bits => 4 - bits as usize,
+        };
+        let bit_rate_index = (b2 >> 4) as usize;
+        let sampling_rate_index = ((b2 >> 2) & 3) as usize;
+        // free format (0) isn't worth the trouble
+        if version == 1 || !(1..15).contains(&bit_rate_index) || sampling_rate_index == 3 {
+            return None;
+        }
+
+        let mpeg1 = version == 3;
+        let divisor = match version {
+            3 => 1,
+            2 => 2,
+            _ => 4,
+        };
+        Some(FrameHeader {
+            mpeg1,
+            layer,
+            bit_rate: BIT_RATES[usize::from(!mpeg1)][layer - 1][bit_rate_index - 1],
+            sampling_rate: SAMPLING_RATES[sampling_rate_index] / divisor,
+            mono: b3 >> 6 == 3,
+        })
+    }
+
+    fn samples(&self) -> u32 {
+        match (self.layer, self.mpeg1) {
+            (1, _) => 384,
+            (3, false) => 576,
+            _ => 1152,
+        }
+    }
+
+    // where a Xing header would be, after the side information
+    fn xing_offset(&self) -> usize {
+        4 + match (self.mpeg1, self.mono) {
+            (true, false) => 32,
+            (true, true) | (false, false) => 17,
+            (false, true) => 9,
+        }
+    }
+}
+
+// the size of an ID3v2 tag at the start of `header`, if there is one
+fn id3v2_size(header: &[u8; 10]) -> u64 {
+    if &header[..3] != b"ID3" {
+        return 0;
+    }
+    let size = header[6..]
+        .iter()
+        .fold(0u64, |size, byte| size << 7 | u64::from(byte & 0x7f));
+    // with a footer
+    let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+    10 + size + footer
+}
+
+// VBR files announce their frame and byte counts in a Xing header, which
+// makes for the average bit rate
+fn xing_bit_rate(frame: &[u8], header: &FrameHeader) -> Option<u32> {
+    let xing = frame.get(header.xing_offset()..)?;
+    if !xing.starts_with(b"Xing") {
+        return None;
+    }
+    let flags = u32::from_be_bytes(xing.get(4..8)?.try_into().ok()?);
+    // frames and bytes
+    if flags & 3 != 3 {
+        return None;
+    }
+    let frames = u32::from_be_bytes(xing.get(8..12)?.try_into().ok()?);
+    let bytes = u32::from_be_bytes(xing.get(12..16)?.try_into().ok()?);
+    let samples = u64::from(frames) * u64::from(header.samples());
+    if samples == 0 {
+        return None;
+    }
+    Some((u64::from(bytes) * 8 * u64::from(header.sampling_rate) / samples / 1000) as u32)
+}
+
+/// from the first MPEG audio frame header
+pub(super) fn mpeg(path: &Utf8Path) -> io::Result<Properties> {
+    let mut file = File::open(path)?;
+    let mut tag_header = [0; 10];
+    file.read_exact(&mut tag_header)?;
+    file.seek(SeekFrom::Start(id3v2_size(&tag_header)))?;
+
+    let mut buf = Vec::with_capacity(SCAN_SIZE);
+    file.take(SCAN_SIZE as u64).read_to_end(&mut buf)?;
+
+    let found = (0..buf.len())
+        .find_map(|start| FrameHeader::parse(&buf[start..]).map(|header| (start, header)));
+    let Some((start, header)) = found else {
+        return Ok(Properties::default());
+    };
+    Ok(Properties {
+        bit_rate: Some(xing_bit_rate(&buf[start..], &header).unwrap_or(header.bit_rate)),
+        sampling_rate: Some(header.sampling_rate),
+        channel_count: Some(if header.mono { 1 } else { 2 }),
+    })
+}
@@ -1,28 +1,52 @@
-use std::iter::zip;
+use std::{
+    collections::{HashMap, HashSet},
+    iter::zip,
+};
 
 use camino::{Utf8Path, Utf8PathBuf};
+use rand::{distributions::Alphanumeric, seq::SliceRandom, Rng};
 use sea_orm::{
-    ColumnTrait, Condition, ConnectOptions, Database, DatabaseConnection, DbErr, EntityTrait,
-    LoaderTrait, Order, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
+    sea_query::{Expr, Func, OnConflict, Query, SimpleExpr},
+    ActiveModelTrait, ActiveValue as AV, ColumnTrait, Condition, ConnectOptions, Database,
+    DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait, IntoActiveModel, JoinType,
+    LoaderTrait, ModelTrait, Order, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
+    RelationTrait, Select, TransactionTrait,
 };
 use sea_orm_migration::MigratorTrait;
-use subsonic_types::request::search::Search3;
+use subsonic_types::request::{
+    lists::{GetAlbumList2, GetRandomSongs, ListType},
+    search::Search3,
+};
 use thiserror::Error;
-use tracing::{debug, error, trace, warn};
+use time::OffsetDateTime;
+use tracing::{debug, error, info, trace, warn};
 
-use super::types::QueryResult;
+use super::types::{
+    Genre, PlayQueueWithEntries, PlaylistUpdate, PlaylistWithEntries, QueryResult,
+    ShareWithEntries, SongDetails,
+};
 use crate::{
+    config::MediaPath,
     entity::{
-        cover_art,
+        album,
+        annotation::{self, Item},
+        artist, bookmark, cover_art, directory, genre, internet_radio_station, lyrics,
+        music_folder, play_history, play_queue, play_queue_entry, playlist, playlist_entry, share,
+        share_entry,
         song::{self},
+        song_artist, song_genre, user, user_music_folder,
     },
     indexer::{
         migration,
         types::{Album, Artist},
     },
+    util::Pwn,
 };
 pub type SongId = String;
 
+/// the artist of albums by more than one, when no album artist is tagged
+pub(crate) const VARIOUS_ARTISTS: &str = "Various Artists";
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Db")]
@@ -37,7 +61,11 @@ pub struct DB {
 }
 
 impl DB {
-    pub(super) async fn new(data_path: impl AsRef<Utf8Path>) -> Result<Self, Error> {
+    /// `admin_password` is for the first user, if there are none yet
+    pub(super) async fn new(
+        data_path: impl AsRef<Utf8Path>,
+        admin_password: Option<&str>,
+    ) -> Result<Self, Error> {
         let data_path = data_path.as_ref().to_path_buf();
         // TODO needed?
         // assert!(path.is_absolute());
@@ -57,10 +85,48 @@ impl DB {
             debug!("{res:?}");
         }
 
-        Ok(Self {
+        let db = Self {
             connection,
             data_path,
-        })
+        };
+        db.ensure_user(admin_password).await?;
+
+        Ok(db)
+    }
+
+    // a fresh install has nobody who could log in, so there's an admin with the
+    // configured password, or else a token that's only ever shown once
+    async fn ensure_user(&self, password: Option<&str>) -> Result<(), DbErr> {
+        if user::Entity::find().count(self.connection()).await? > 0 {
+            return Ok(());
+        }
+
+        let (password, generated) = match password {
+            Some(password) => (password.to_string(), false),
+            None => (
+                rand::thread_rng()
+                    .sample_iter(&Alphanumeric)
+                    .take(16)
+                    .map(char::from)
+                    .collect(),
+                true,
+            ),
+        };
+        let admin = user::ActiveModel {
+            username: AV::Set("admin".to_string()),
+            password: AV::Set(Some(password.clone())),
+            admin: AV::Set(true),
+            ..Default::default()
+        };
+        user::Entity::insert(admin).exec(self.connection()).await?;
+        if generated {
+            info!(
+                "no users found, created `admin` with password `{password}` (shown only this once) - change it!"
+            );
+        } else {
+            info!("no users found, created `admin` with the configured password");
+        }
+        Ok(())
     }
 
     // TODO remove?
@@ -95,27 +161,65 @@ impl DB {
             > 0
     }
 
+    pub(crate) async fn get_song_id(&self, path: &str) -> Option<i32> {
+        song::Entity::find()
+            .select_only()
+            .column(song::Column::Id)
+            .filter(song::Column::Path.eq(path))
+            .into_tuple::<i32>()
+            .one(self.connection())
+            .await
+            .inspect_err(|e| error!("get song id {e:?}"))
+            .ok()
+            .flatten()
+    }
+
+    // artists with their album count, for `into_model::<Artist>()`
+    fn artists(&self) -> Select<artist::Entity> {
+        // the artist's own image, or else one of its album covers
+        let cover_art: SimpleExpr = Func::coalesce([
+            Expr::col((artist::Entity, artist::Column::CoverArt)).into(),
+            album::Column::CoverArt.max(),
+        ])
+        .into();
+        artist::Entity::find()
+            .select_only()
+            .columns([artist::Column::Id, artist::Column::Name])
+            .left_join(album::Entity)
+            .column_as(album::Column::Id.count(), "album_count")
+            .column_as(cover_art, "cover_art")
+            .group_by(artist::Column::Id)
+    }
+
+    // albums with song count and duration, for `into_model::<Album>()`
+    fn albums(&self) -> Select<album::Entity> {
+        album::Entity::find()
+            .left_join(song::Entity)
+            .column_as(song::Column::Id.count(), "song_count")
+            .column_as(song::Column::Duration.sum(), "duration")
+            .group_by(album::Column::Id)
+    }
+
+    /// only those with songs or albums in `folders`
     pub(crate) async fn get_artists(
         &self,
         filter: &str,
         limit: Option<u32>,
         offset: Option<u32>,
+        folders: &[i32],
     ) -> Result<Vec<Artist>, DbErr> {
-        // TODO `is_not_null` doesn't seem to do its job, we do get
-        // WARN Type("A null value was encountered while decoding \"artist\"")
-        let mut filter_cond = Condition::all().add(song::Column::Artist.is_not_null());
+        let mut filter_cond = Condition::all();
         for word in filter.split(" ") {
             if !word.is_empty() {
-                filter_cond = filter_cond.add(song::Column::Artist.contains(word));
+                filter_cond = filter_cond.add(artist::Column::Name.contains(word));
             }
         }
 
-        let mut query = song::Entity::find()
+        let mut query = self
+            .artists()
             .filter(filter_cond)
-            .select_only()
-            .column_as(song::Column::Artist, "name")
-            .order_by(song::Column::Artist, Order::Asc)
-            .distinct();
+            .filter(artists_in(folders))
+            .order_by(artist::Column::Name, Order::Asc);
 
         if limit.is_some() {
             query = query
@@ -130,28 +234,26 @@ impl DB {
             .inspect_err(|e| error!("{e:?}"))
     }
 
+    /// only those with songs in `folders`
     pub(crate) async fn get_albums(
         &self,
         filter: &str,
         limit: Option<u32>,
         offset: Option<u32>,
+        folders: &[i32],
     ) -> Result<Vec<Album>, DbErr> {
-        let mut filter_cond = Condition::all().add(song::Column::Album.is_not_null());
+        let mut filter_cond = Condition::all();
         for word in filter.split(" ") {
             if !word.is_empty() {
-                filter_cond = filter_cond.add(song::Column::Album.contains(word));
+                filter_cond = filter_cond.add(album::Column::Name.contains(word));
             }
         }
 
-        let mut query = song::Entity::find()
+        let mut query = self
+            .albums()
             .filter(filter_cond)
-            .select_only()
-            .column_as(song::Column::Album, "title")
-            .column_as(song::Column::Artist, "artist")
-            .group_by(song::Column::Artist)
-            // .order_by(song::Column::Artist, Order::Asc)
-            // .order_by(song::Column::Album, Order::Asc)
-            .distinct();
+            .filter(albums_in(folders))
+            .order_by(album::Column::Name, Order::Asc);
 
         if limit.is_some() {
             query = query
@@ -166,7 +268,507 @@ impl DB {
             .inspect_err(|e| warn!("{e:?}"))
     }
 
-    pub(crate) async fn query(&self, query: &Search3) -> QueryResult {
+    /// `starred` and `highest` are as seen by `user_id`, only albums with songs
+    /// in `folders`
+    pub(crate) async fn get_album_list(
+        &self,
+        user_id: i32,
+        list: &GetAlbumList2,
+        folders: &[i32],
+    ) -> Result<Vec<Album>, DbErr> {
+        let mut query = self.albums().filter(albums_in(folders));
+        query = match list.list_type {
+            ListType::Random => return self.random_albums(list.size, folders).await,
+            // ids only ever go up
+            ListType::Newest => query.order_by(album::Column::Id, Order::Desc),
+            ListType::AlphabeticalByName => query.order_by(album::Column::Name, Order::Asc),
+            ListType::AlphabeticalByArtist => query
+                .order_by(album::Column::Artist, Order::Asc)
+                .order_by(album::Column::Name, Order::Asc),
+            ListType::ByYear => {
+                let from = list.from_year.unwrap_or_default();
+                let to = list.to_year.unwrap_or(u32::MAX);
+                // from > to means newest first
+                let (order, low, high) = if from > to {
+                    (Order::Desc, to, from)
+                } else {
+                    (Order::Asc, from, to)
+                };
+                query
+                    .filter(album::Column::Year.between(low, high))
+                    .order_by(album::Column::Year, order)
+                    .order_by(album::Column::Name, Order::Asc)
+            }
+            // any of the album's songs will do
+            ListType::ByGenre => query
+                .filter(
+                    album::Column::Id.in_subquery(
+                        Query::select()
+                            .column(song::Column::AlbumId)
+                            .from(song::Entity)
+                            .inner_join(
+                                song_genre::Entity,
+                                Expr::col((song_genre::Entity, song_genre::Column::Song))
+                                    .equals((song::Entity, song::Column::Id)),
+                            )
+                            .inner_join(
+                                genre::Entity,
+                                Expr::col((genre::Entity, genre::Column::Id))
+                                    .equals((song_genre::Entity, song_genre::Column::Genre)),
+                            )
+                            .and_where(
+                                Expr::col((genre::Entity, genre::Column::Name))
+                                    .eq(list.genre.as_deref().unwrap_or_default()),
+                            )
+                            .to_owned(),
+                    ),
+                )
+                .order_by(album::Column::Name, Order::Asc),
+            ListType::Frequent => query
+                .filter(album::Column::PlayCount.gt(0))
+                .order_by(album::Column::PlayCount, Order::Desc)
+                .order_by(album::Column::Played, Order::Desc),
+            ListType::Recent => query
+                .filter(album::Column::Played.is_not_null())
+                .order_by(album::Column::Played, Order::Desc),
+            ListType::Starred => query
+                .join(JoinType::InnerJoin, annotation::Relation::Album.def().rev())
+                .filter(starred_by(user_id))
+                .order_by(annotation::Column::Starred, Order::Desc),
+            ListType::Highest => query
+                .join(JoinType::InnerJoin, annotation::Relation::Album.def().rev())
+                .filter(annotation::Column::User.eq(user_id))
+                .filter(annotation::Column::Rating.is_not_null())
+                .order_by(annotation::Column::Rating, Order::Desc)
+                .order_by(album::Column::Name, Order::Asc),
+        };
+
+        query
+            .limit(list.size.map(|size| size as u64))
+            .offset(list.offset.map(|offset| offset as u64))
+            .into_model::<Album>()
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get album list {e:?}"))
+    }
+
+    // like `get_random_songs`, picks among the IDs first; an offset makes no
+    // difference to random picks
+    async fn random_albums(&self, size: Option<u32>, folders: &[i32]) -> Result<Vec<Album>, DbErr> {
+        let candidates: Vec<i32> = album::Entity::find()
+            .select_only()
+            .column(album::Column::Id)
+            .filter(albums_in(folders))
+            .into_tuple()
+            .all(self.connection())
+            .await?;
+        let size = size.map_or(candidates.len(), |size| size as usize);
+        let picked: Vec<i32> = candidates
+            .choose_multiple(&mut rand::thread_rng(), size)
+            .copied()
+            .collect();
+        let mut albums = self
+            .albums()
+            .filter(album::Column::Id.is_in(picked.iter().copied()))
+            .into_model::<Album>()
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get random albums {e:?}"))?;
+        // in the order they were picked
+        albums.sort_by_key(|album| picked.iter().position(|id| *id == album.id));
+        Ok(albums)
+    }
+
+    pub(crate) async fn get_album(&self, id: i32) -> Option<Album> {
+        self.albums()
+            .filter(album::Column::Id.eq(id))
+            .into_model::<Album>()
+            .one(self.connection())
+            .await
+            .inspect_err(|e| error!("get album {e:?}"))
+            .ok()
+            .flatten()
+    }
+
+    /// in disc/track order
+    pub(crate) async fn get_album_songs(&self, album_id: i32) -> Vec<song::Model> {
+        let mut songs = song::Entity::find()
+            .filter(song::Column::AlbumId.eq(album_id))
+            .order_by(song::Column::Disc, Order::Asc)
+            .order_by(song::Column::Track, Order::Asc)
+            .order_by(song::Column::Title, Order::Asc)
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get album songs {e:?}"))
+            .unwrap_or_default();
+        self.fill_cover_art(&mut songs).await;
+        songs
+    }
+
+    /// only if it has songs or albums in `folders`
+    pub(crate) async fn get_artist(&self, id: i32, folders: &[i32]) -> Option<Artist> {
+        self.artists()
+            .filter(artist::Column::Id.eq(id))
+            .filter(artists_in(folders))
+            .into_model::<Artist>()
+            .one(self.connection())
+            .await
+            .inspect_err(|e| error!("get artist {e:?}"))
+            .ok()
+            .flatten()
+    }
+
+    /// oldest first, only those with songs in `folders`
+    pub(crate) async fn get_artist_albums(&self, artist_id: i32, folders: &[i32]) -> Vec<Album> {
+        self.albums()
+            .filter(album::Column::ArtistId.eq(artist_id))
+            .filter(albums_in(folders))
+            .order_by(album::Column::Year, Order::Asc)
+            .order_by(album::Column::Name, Order::Asc)
+            .into_model::<Album>()
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get artist albums {e:?}"))
+            .unwrap_or_default()
+    }
+
+    /// finds or creates `path` along with its ancestors up to `root`, returns the ID of `path`
+    pub(crate) async fn upsert_directory(
+        &self,
+        path: &Utf8Path,
+        root: &Utf8Path,
+    ) -> Result<i32, DbErr> {
+        // top down, so parents exist before their children
+        let mut chain: Vec<_> = path
+            .ancestors()
+            .take_while(|dir| dir.starts_with(root))
+            .collect();
+        chain.reverse();
+
+        let mut parent = None;
+        for dir in chain {
+            let existing = directory::Entity::find()
+                .filter(directory::Column::Path.eq(dir.as_str()))
+                .one(self.connection())
+                .await?;
+            let id = match existing {
+                Some(existing) => existing.id,
+                None => {
+                    let directory = directory::ActiveModel {
+                        path: AV::Set(dir.to_string()),
+                        name: AV::Set(dir.file_name().unwrap_or(dir.as_str()).to_string()),
+                        parent: AV::Set(parent),
+                        modified: AV::Set(unix_now_ms()),
+                        ..Default::default()
+                    };
+                    directory::Entity::insert(directory)
+                        .exec(self.connection())
+                        .await?
+                        .last_insert_id
+                }
+            };
+            parent = Some(id);
+        }
+
+        parent.ok_or_else(|| DbErr::Custom(format!("{path} is not inside {root}")))
+    }
+
+    pub(crate) async fn touch_directory(&self, id: i32) -> Result<(), DbErr> {
+        directory::Entity::update_many()
+            .col_expr(directory::Column::Modified, Expr::value(unix_now_ms()))
+            .filter(directory::Column::Id.eq(id))
+            .exec(self.connection())
+            .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn get_directory(&self, id: i32) -> Option<directory::Model> {
+        directory::Entity::find_by_id(id)
+            .one(self.connection())
+            .await
+            .inspect_err(|e| error!("get directory {e:?}"))
+            .ok()
+            .flatten()
+    }
+
+    /// the music folders of `paths`, in order, created as needed. Songs indexed
+    /// before folders were tracked get theirs
+    pub(crate) async fn sync_music_folders(
+        &self,
+        paths: &[MediaPath],
+    ) -> Result<Vec<music_folder::Model>, DbErr> {
+        let mut folders = Vec::with_capacity(paths.len());
+        for media_path in paths {
+            // without a trailing slash, like directory paths
+            let path: Utf8PathBuf = media_path.path().components().collect();
+            let path = path.as_str();
+            let name = media_path.name();
+            // looked up first, a conflicting insert would still use up an ID
+            let existing = music_folder::Entity::find()
+                .filter(music_folder::Column::Path.eq(path))
+                .one(self.connection())
+                .await?;
+            let folder = match existing {
+                Some(existing) if existing.name == name => existing,
+                Some(existing) => {
+                    let mut renamed = existing.into_active_model();
+                    renamed.name = AV::Set(name.to_string());
+                    renamed.update(self.connection()).await?
+                }
+                None => {
+                    let folder = music_folder::ActiveModel {
+                        path: AV::Set(path.to_string()),
+                        name: AV::Set(name.to_string()),
+                        ..Default::default()
+                    };
+                    folder.insert(self.connection()).await?
+                }
+            };
+
+            song::Entity::update_many()
+                .col_expr(song::Column::Folder, Expr::value(folder.id))
+                .filter(song::Column::Folder.is_null())
+                .filter(Expr::cust_with_values(
+                    "instr(\"path\", ?) = 1",
+                    [Utf8Path::new(path).join("").to_string()],
+                ))
+                .exec(self.connection())
+                .await?;
+            folders.push(folder);
+        }
+        Ok(folders)
+    }
+
+    /// the media paths
+    pub(crate) async fn get_root_directories(&self) -> Vec<directory::Model> {
+        directory::Entity::find()
+            .filter(directory::Column::Parent.is_null())
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get root directories {e:?}"))
+            .unwrap_or_default()
+    }
+
+    pub(crate) async fn get_subdirectories(&self, parents: &[i32]) -> Vec<directory::Model> {
+        directory::Entity::find()
+            .filter(directory::Column::Parent.is_in(parents.iter().copied()))
+            .order_by(directory::Column::Name, Order::Asc)
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get subdirectories {e:?}"))
+            .unwrap_or_default()
+    }
+
+    pub(crate) async fn get_directory_songs(&self, ids: &[i32]) -> Vec<song::Model> {
+        let mut songs = song::Entity::find()
+            .filter(song::Column::Parent.is_in(ids.iter().map(|id| directory::format_id(*id))))
+            .order_by(song::Column::Disc, Order::Asc)
+            .order_by(song::Column::Track, Order::Asc)
+            .order_by(song::Column::Path, Order::Asc)
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get directory songs {e:?}"))
+            .unwrap_or_default();
+        self.fill_cover_art(&mut songs).await;
+        songs
+    }
+
+    /// cover art of the albums found in `directories`, by directory path
+    pub(crate) async fn get_directory_cover_art(
+        &self,
+        directories: &[directory::Model],
+    ) -> HashMap<String, i32> {
+        album::Entity::find()
+            .filter(album::Column::Directory.is_in(directories.iter().map(|d| d.path.as_str())))
+            .filter(album::Column::CoverArt.is_not_null())
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get directory cover art {e:?}"))
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|album| Some((album.directory, album.cover_art?)))
+            .collect()
+    }
+
+    /// when a directory last had songs indexed, in unix milliseconds
+    pub(crate) async fn directories_modified(&self) -> i64 {
+        directory::Entity::find()
+            .select_only()
+            .column_as(directory::Column::Modified.max(), "modified")
+            .into_tuple::<Option<i64>>()
+            .one(self.connection())
+            .await
+            .inspect_err(|e| error!("directories modified {e:?}"))
+            .ok()
+            .flatten()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    pub(crate) async fn upsert_artist(&self, name: &str) -> Result<i32, DbErr> {
+        let existing = artist::Entity::find()
+            .filter(artist::Column::Name.eq(name))
+            .one(self.connection())
+            .await?;
+        if let Some(artist) = existing {
+            return Ok(artist.id);
+        }
+
+        let artist = artist::ActiveModel {
+            name: AV::Set(name.to_string()),
+            ..Default::default()
+        };
+        Ok(artist::Entity::insert(artist)
+            .exec(self.connection())
+            .await?
+            .last_insert_id)
+    }
+
+    /// finds or creates the album identified by `name`, `artist` and `directory`,
+    /// returns its ID and that of its artist. Albums without an album artist tag
+    /// (`by_directory`) are one per directory instead, whatever the artist: as
+    /// soon as there are songs by another one, they are by `VARIOUS_ARTISTS`
+    pub(crate) async fn upsert_album(
+        &self,
+        name: &str,
+        artist: Option<(i32, &str)>,
+        directory: &str,
+        year: Option<u32>,
+        genre: Option<&str>,
+        by_directory: bool,
+    ) -> Result<(i32, Option<i32>), DbErr> {
+        let artist_id = artist.map(|(id, _)| id);
+        let mut existing = album::Entity::find()
+            .filter(album::Column::Name.eq(name))
+            .filter(album::Column::Directory.eq(directory))
+            .order_by(album::Column::Id, Order::Asc);
+        if !by_directory {
+            existing = existing.filter(match artist_id {
+                Some(id) => album::Column::ArtistId.eq(id),
+                None => album::Column::ArtistId.is_null(),
+            });
+        }
+        if let Some(album) = existing.one(self.connection()).await? {
+            let mut update: album::ActiveModel = album.clone().into();
+            let mut album_artist_id = album.artist_id;
+            if by_directory
+                && album.artist_id != artist_id
+                && album.artist.as_deref() != Some(VARIOUS_ARTISTS)
+            {
+                album_artist_id = Some(self.upsert_artist(VARIOUS_ARTISTS).await?);
+                update.artist_id = AV::Set(album_artist_id);
+                update.artist = AV::Set(Some(VARIOUS_ARTISTS.to_string()));
+            }
+            // fill in what earlier songs of the album didn't have
+            if album.year.is_none() && year.is_some() {
+                update.year = AV::Set(year);
+            }
+            if album.genre.is_none() && genre.is_some() {
+                update.genre = AV::Set(genre.to_pwned());
+            }
+            if update.is_changed() {
+                update.update(self.connection()).await?;
+            }
+            return Ok((album.id, album_artist_id));
+        }
+
+        let album = album::ActiveModel {
+            name: AV::Set(name.to_string()),
+            artist_id: AV::Set(artist_id),
+            artist: AV::Set(artist.map(|(_, name)| name.to_string())),
+            directory: AV::Set(directory.to_string()),
+            year: AV::Set(year),
+            genre: AV::Set(genre.to_pwned()),
+            ..Default::default()
+        };
+        let id = album::Entity::insert(album)
+            .exec(self.connection())
+            .await?
+            .last_insert_id;
+        Ok((id, artist_id))
+    }
+
+    /// removes albums without any songs, as regrouping leaves them behind;
+    /// returns how many there were
+    pub(crate) async fn delete_empty_albums(&self) -> Result<u64, DbErr> {
+        let res = album::Entity::delete_many()
+            .filter(
+                album::Column::Id.not_in_subquery(
+                    Query::select()
+                        .column(song::Column::AlbumId)
+                        .from(song::Entity)
+                        .and_where(song::Column::AlbumId.is_not_null())
+                        .to_owned(),
+                ),
+            )
+            .exec(self.connection())
+            .await?;
+        Ok(res.rows_affected)
+    }
+
+    /// the `cover_art` row currently shown for the album
+    pub(crate) async fn get_album_cover_art(&self, album_id: i32) -> Option<cover_art::Model> {
+        cover_art::Entity::find()
+            .filter(
+                cover_art::Column::Id.in_subquery(
+                    Query::select()
+                        .column(album::Column::CoverArt)
+                        .from(album::Entity)
+                        .and_where(album::Column::Id.eq(album_id))
+                        .to_owned(),
+                ),
+            )
+            .one(self.connection())
+            .await
+            .inspect_err(|e| error!("get album cover art {e:?}"))
+            .ok()
+            .flatten()
+    }
+
+    pub(crate) async fn set_album_cover_art(
+        &self,
+        album_id: i32,
+        cover_art_id: i32,
+    ) -> Result<(), DbErr> {
+        album::Entity::update_many()
+            .col_expr(album::Column::CoverArt, Expr::value(cover_art_id))
+            .filter(album::Column::Id.eq(album_id))
+            .exec(self.connection())
+            .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn set_artist_cover_art(
+        &self,
+        artist_id: i32,
+        cover_art_id: i32,
+    ) -> Result<(), DbErr> {
+        artist::Entity::update_many()
+            .col_expr(artist::Column::CoverArt, Expr::value(cover_art_id))
+            .filter(artist::Column::Id.eq(artist_id))
+            .exec(self.connection())
+            .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn set_song_cover_art(
+        &self,
+        song_id: i32,
+        cover_art_id: i32,
+    ) -> Result<(), DbErr> {
+        song::Entity::update_many()
+            .col_expr(
+                song::Column::CoverArt,
+                Expr::value(format!("{cover_art_id}")),
+            )
+            .filter(song::Column::Id.eq(song_id))
+            .exec(self.connection())
+            .await?;
+        Ok(())
+    }
+
+    /// only what's in `folders`
+    pub(crate) async fn query(&self, query: &Search3, folders: &[i32]) -> QueryResult {
         debug!("{query:?}");
 
         // what the user was actually searching for
@@ -176,7 +778,7 @@ impl DB {
 
         // get albums
         let albums = self
-            .get_albums(&user_query, query.album_count, query.album_offset)
+            .get_albums(&user_query, query.album_count, query.album_offset, folders)
             .await
             .unwrap_or_default()
             .into_iter()
@@ -184,7 +786,12 @@ impl DB {
 
         // get artists
         let artists = self
-            .get_artists(&user_query, query.artist_count, query.artist_offset)
+            .get_artists(
+                &user_query,
+                query.artist_count,
+                query.artist_offset,
+                folders,
+            )
             .await
             .unwrap_or_default()
             .into_iter()
@@ -204,7 +811,7 @@ impl DB {
             }
         }
 
-        let mut op = song::Entity::find();
+        let mut op = song::Entity::find().filter(songs_in(folders));
 
         if do_filter {
             let filter = Condition::any()
@@ -223,12 +830,27 @@ impl DB {
             .inspect_err(|e| error!("{e:?}"))
             .unwrap_or_default();
 
-        if let Ok(covers) = songs
+        self.fill_cover_art(&mut songs).await;
+
+        QueryResult {
+            artists,
+            albums,
+            songs,
+        }
+    }
+
+    // songs indexed before `song.cover_art` was populated by the indexer fall
+    // back to their embedded picture
+    async fn fill_cover_art(&self, songs: &mut [song::Model]) {
+        if let Ok(covers) = (&*songs)
             .load_one(cover_art::Entity, self.connection())
             .await
             .inspect_err(|e| error!("{e:?}"))
         {
-            for (song, cover) in zip(&mut songs, covers) {
+            for (song, cover) in zip(songs, covers) {
+                if song.cover_art.is_some() {
+                    continue;
+                }
                 if let Some(cover) = cover {
                     // TODO hacky, ewww
                     let cover_id = format!("{}", cover.id);
@@ -236,12 +858,6 @@ impl DB {
                 }
             }
         }
-
-        QueryResult {
-            artists,
-            albums,
-            songs,
-        }
     }
 
     pub async fn get_cover_art(&self, id: impl AsRef<str>) -> Option<cover_art::Model> {
@@ -256,41 +872,1423 @@ impl DB {
             .flatten()
     }
 
-    pub async fn get_cover_art_for_song(&self, song_id: i32) -> Option<cover_art::Model> {
+    /// the image file `path` was registered as
+    pub(crate) async fn get_folder_cover_art(&self, path: &str) -> Option<cover_art::Model> {
         cover_art::Entity::find()
-            .filter(cover_art::Column::Song.eq(song_id))
+            .filter(cover_art::Column::Path.eq(path))
             .one(self.connection())
             .await
-            .inspect_err(|e| error!("get cover art for song {e:?}"))
+            .inspect_err(|e| error!("get folder cover art {e:?}"))
             .ok()
             .flatten()
     }
-    pub async fn get_song(&self, id: impl AsRef<str>) -> Option<song::Model> {
-        let Ok(id) = id.as_ref().parse::<i32>() else {
-            return None;
-        };
-        let mut song = song::Entity::find_by_id(id)
-            .one(self.connection())
+
+    pub(crate) async fn all_cover_art(&self) -> Vec<cover_art::Model> {
+        cover_art::Entity::find()
+            .all(self.connection())
             .await
-            .inspect_err(|e| error!("get song {e:?}"))
-            .ok()
-            .flatten();
+            .inspect_err(|e| error!("all cover art {e:?}"))
+            .unwrap_or_default()
+    }
 
-        // TODO some kind of join would be nice?
-        if let Some(song) = song.as_mut() {
-            song.cover_art = self
-                .get_cover_art_for_song(song.id)
-                .await
-                .map(|ca| format!("{}", ca.id));
+    /// records where a row's file went when it was moved into the content store
+    pub(crate) async fn set_cover_art_hash(
+        &self,
+        id: i32,
+        shard: i32,
+        hash: String,
+    ) -> Result<(), DbErr> {
+        cover_art::ActiveModel {
+            id: AV::Unchanged(id),
+            shard: AV::Set(shard),
+            hash: AV::Set(Some(hash)),
+            ..Default::default()
         }
-        song
+        .update(self.connection())
+        .await?;
+        Ok(())
     }
 
-    pub fn connection(&self) -> &DatabaseConnection {
-        &self.connection
+    /// removes `cover_art` rows, and whatever shows them
+    pub(crate) async fn delete_cover_art(&self, ids: &[i32]) -> Result<(), DbErr> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let txn = self.connection().begin().await?;
+        album::Entity::update_many()
+            .col_expr(album::Column::CoverArt, Expr::value(Option::<i32>::None))
+            .filter(album::Column::CoverArt.is_in(ids.iter().copied()))
+            .exec(&txn)
+            .await?;
+        artist::Entity::update_many()
+            .col_expr(artist::Column::CoverArt, Expr::value(Option::<i32>::None))
+            .filter(artist::Column::CoverArt.is_in(ids.iter().copied()))
+            .exec(&txn)
+            .await?;
+        song::Entity::update_many()
+            .col_expr(song::Column::CoverArt, Expr::value(Option::<String>::None))
+            .filter(song::Column::CoverArt.is_in(ids.iter().map(|id| format!("{id}"))))
+            .exec(&txn)
+            .await?;
+        cover_art::Entity::delete_many()
+            .filter(cover_art::Column::Id.is_in(ids.iter().copied()))
+            .exec(&txn)
+            .await?;
+        txn.commit().await
     }
 
-    pub fn data_path(&self) -> &Utf8Path {
-        self.data_path.as_path()
+    /// replaces the artists of song `song_id`, keeping their order
+    pub(crate) async fn set_song_artists(
+        &self,
+        song_id: i32,
+        artist_ids: &[i32],
+    ) -> Result<(), DbErr> {
+        let txn = self.connection().begin().await?;
+        song_artist::Entity::delete_many()
+            .filter(song_artist::Column::Song.eq(song_id))
+            .exec(&txn)
+            .await?;
+        song_artist::Entity::insert_many(artist_ids.iter().map(|artist_id| {
+            song_artist::ActiveModel {
+                song: AV::Set(song_id),
+                artist: AV::Set(*artist_id),
+                ..Default::default()
+            }
+        }))
+        .on_conflict(
+            OnConflict::columns([song_artist::Column::Song, song_artist::Column::Artist])
+                .do_nothing()
+                .to_owned(),
+        )
+        .on_empty_do_nothing()
+        .exec(&txn)
+        .await?;
+        txn.commit().await
+    }
+
+    /// songs `ids` with all their artists and genres, for the OpenSubsonic fields
+    pub(crate) async fn get_song_details(&self, ids: &[i32]) -> HashMap<i32, SongDetails> {
+        let songs = song::Entity::find()
+            .filter(song::Column::Id.is_in(ids.iter().copied()))
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get song details {e:?}"))
+            .unwrap_or_default();
+        let mut details: HashMap<_, _> = songs
+            .into_iter()
+            .map(|song| {
+                (
+                    song.id,
+                    SongDetails {
+                        song,
+                        artists: vec![],
+                        genres: vec![],
+                    },
+                )
+            })
+            .collect();
+
+        let artists = song_artist::Entity::find()
+            .filter(song_artist::Column::Song.is_in(ids.iter().copied()))
+            .find_also_related(artist::Entity)
+            .order_by(song_artist::Column::Id, Order::Asc)
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get song artists {e:?}"))
+            .unwrap_or_default();
+        for (link, artist) in artists {
+            if let (Some(song), Some(artist)) = (details.get_mut(&link.song), artist) {
+                song.artists.push(artist);
+            }
+        }
+
+        let genres = song_genre::Entity::find()
+            .filter(song_genre::Column::Song.is_in(ids.iter().copied()))
+            .find_also_related(genre::Entity)
+            .order_by(song_genre::Column::Id, Order::Asc)
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get song genres {e:?}"))
+            .unwrap_or_default();
+        for (link, genre) in genres {
+            if let (Some(song), Some(genre)) = (details.get_mut(&link.song), genre) {
+                song.genres.push(genre.name);
+            }
+        }
+        details
+    }
+
+    /// replaces the genres of song `song_id`
+    pub(crate) async fn set_song_genres(
+        &self,
+        song_id: i32,
+        genres: &[String],
+    ) -> Result<(), DbErr> {
+        let txn = self.connection().begin().await?;
+        song_genre::Entity::delete_many()
+            .filter(song_genre::Column::Song.eq(song_id))
+            .exec(&txn)
+            .await?;
+        for name in genres {
+            genre::Entity::insert(genre::ActiveModel {
+                name: AV::Set(name.clone()),
+                ..Default::default()
+            })
+            .on_conflict(
+                OnConflict::column(genre::Column::Name)
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .do_nothing()
+            .exec(&txn)
+            .await?;
+            // case insensitive, so this finds "rock" when "Rock" was there first
+            let Some(genre) = genre::Entity::find()
+                .filter(genre::Column::Name.eq(name))
+                .one(&txn)
+                .await?
+            else {
+                continue;
+            };
+            song_genre::Entity::insert(song_genre::ActiveModel {
+                song: AV::Set(song_id),
+                genre: AV::Set(genre.id),
+                ..Default::default()
+            })
+            .on_conflict(
+                OnConflict::columns([song_genre::Column::Song, song_genre::Column::Genre])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .do_nothing()
+            .exec(&txn)
+            .await?;
+        }
+        txn.commit().await
+    }
+
+    /// replaces the lyrics of song `song_id`
+    pub(crate) async fn set_song_lyrics(
+        &self,
+        song_id: i32,
+        lyrics: Vec<lyrics::ActiveModel>,
+    ) -> Result<(), DbErr> {
+        let txn = self.connection().begin().await?;
+        lyrics::Entity::delete_many()
+            .filter(lyrics::Column::Song.eq(song_id))
+            .exec(&txn)
+            .await?;
+        let lyrics = lyrics.into_iter().map(|mut lyrics| {
+            lyrics.song = AV::Set(song_id);
+            lyrics
+        });
+        lyrics::Entity::insert_many(lyrics)
+            .on_empty_do_nothing()
+            .exec(&txn)
+            .await?;
+        txn.commit().await
+    }
+
+    /// all lyrics of song `song_id`, synced first
+    pub(crate) async fn get_song_lyrics(&self, song_id: i32) -> Vec<lyrics::Model> {
+        lyrics::Entity::find()
+            .filter(lyrics::Column::Song.eq(song_id))
+            .order_by(lyrics::Column::Synced, Order::Desc)
+            .order_by(lyrics::Column::Lang, Order::Asc)
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get song lyrics {e:?}"))
+            .unwrap_or_default()
     }
+
+    /// the first song by `artist` called `title` that has lyrics (both case
+    /// insensitive, either may be left out) in `folders`, along with them
+    pub(crate) async fn find_lyrics(
+        &self,
+        artist: Option<&str>,
+        title: Option<&str>,
+        folders: &[i32],
+    ) -> Option<(song::Model, lyrics::Model)> {
+        let mut query = song::Entity::find()
+            .find_also_related(lyrics::Entity)
+            .filter(songs_in(folders));
+        if let Some(artist) = artist {
+            query = query.filter(
+                Expr::expr(Func::lower(Expr::col((song::Entity, song::Column::Artist))))
+                    .eq(artist.to_lowercase()),
+            );
+        }
+        if let Some(title) = title {
+            query = query.filter(
+                Expr::expr(Func::lower(Expr::col((song::Entity, song::Column::Title))))
+                    .eq(title.to_lowercase()),
+            );
+        }
+        query
+            .filter(lyrics::Column::Id.is_not_null())
+            .order_by(song::Column::Id, Order::Asc)
+            .order_by(lyrics::Column::Synced, Order::Desc)
+            .one(self.connection())
+            .await
+            .inspect_err(|e| error!("find lyrics {e:?}"))
+            .ok()
+            .flatten()
+            .and_then(|(song, lyrics)| Some((song, lyrics?)))
+    }
+
+    /// IDs of the songs that have their genres linked
+    pub(crate) async fn songs_with_genres(&self) -> HashSet<i32> {
+        song_genre::Entity::find()
+            .select_only()
+            .column(song_genre::Column::Song)
+            .distinct()
+            .into_tuple::<i32>()
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("songs with genres {e:?}"))
+            .unwrap_or_default()
+            .into_iter()
+            .collect()
+    }
+
+    /// genres that have songs, by name
+    /// counting only songs in `folders`
+    pub(crate) async fn get_genres(&self, folders: &[i32]) -> Vec<Genre> {
+        genre::Entity::find()
+            .select_only()
+            .column(genre::Column::Name)
+            .inner_join(song_genre::Entity)
+            .join(JoinType::InnerJoin, song_genre::Relation::Song.def())
+            .column_as(
+                Expr::col((song::Entity, song::Column::Id)).count_distinct(),
+                "song_count",
+            )
+            .column_as(
+                Expr::col((song::Entity, song::Column::AlbumId)).count_distinct(),
+                "album_count",
+            )
+            .filter(songs_in(folders))
+            .group_by(genre::Column::Id)
+            .order_by(genre::Column::Name, Order::Asc)
+            .into_model::<Genre>()
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get genres {e:?}"))
+            .unwrap_or_default()
+    }
+
+    /// up to `size` songs in `folders`, picked at random from those matching the filters
+    pub(crate) async fn get_random_songs(
+        &self,
+        query: &GetRandomSongs,
+        size: u32,
+        folders: &[i32],
+    ) -> Vec<song::Model> {
+        // only IDs at first: that's cheap, unlike `ORDER BY RANDOM()` on full rows
+        let mut candidates = song::Entity::find()
+            .select_only()
+            .column(song::Column::Id)
+            .filter(songs_in(folders));
+        if let Some(genre) = &query.genre {
+            candidates = candidates.filter(
+                song::Column::Id.in_subquery(
+                    Query::select()
+                        .column(song_genre::Column::Song)
+                        .from(song_genre::Entity)
+                        .inner_join(
+                            genre::Entity,
+                            Expr::col((genre::Entity, genre::Column::Id))
+                                .equals((song_genre::Entity, song_genre::Column::Genre)),
+                        )
+                        .and_where(Expr::col((genre::Entity, genre::Column::Name)).eq(genre))
+                        .to_owned(),
+                ),
+            );
+        }
+        if let Some(from) = query.from_year {
+            candidates = candidates.filter(song::Column::Year.gte(from));
+        }
+        if let Some(to) = query.to_year {
+            candidates = candidates.filter(song::Column::Year.lte(to));
+        }
+        let candidates: Vec<i32> = candidates
+            .into_tuple()
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("random song candidates {e:?}"))
+            .unwrap_or_default();
+
+        let picked: Vec<i32> = candidates
+            .choose_multiple(&mut rand::thread_rng(), size as usize)
+            .copied()
+            .collect();
+        let mut songs = song::Entity::find()
+            .filter(song::Column::Id.is_in(picked.iter().copied()))
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get random songs {e:?}"))
+            .unwrap_or_default();
+        // in the order they were picked
+        songs.sort_by_key(|song| picked.iter().position(|id| *id == song.id));
+        self.fill_cover_art(&mut songs).await;
+        songs
+    }
+
+    /// songs in genre `name` and `folders`, by artist, album and track
+    pub(crate) async fn get_songs_by_genre(
+        &self,
+        name: &str,
+        offset: u32,
+        count: u32,
+        folders: &[i32],
+    ) -> Vec<song::Model> {
+        let mut songs = song::Entity::find()
+            .inner_join(song_genre::Entity)
+            .join(JoinType::InnerJoin, song_genre::Relation::Genre.def())
+            .filter(genre::Column::Name.eq(name))
+            .filter(songs_in(folders))
+            .order_by(song::Column::Artist, Order::Asc)
+            .order_by(song::Column::Album, Order::Asc)
+            .order_by(song::Column::Disc, Order::Asc)
+            .order_by(song::Column::Track, Order::Asc)
+            .offset(offset as u64)
+            .limit(count as u64)
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get songs by genre {e:?}"))
+            .unwrap_or_default();
+        self.fill_cover_art(&mut songs).await;
+        songs
+    }
+
+    pub async fn get_cover_art_for_song(&self, song_id: i32) -> Option<cover_art::Model> {
+        cover_art::Entity::find()
+            .filter(cover_art::Column::Song.eq(song_id))
+            .one(self.connection())
+            .await
+            .inspect_err(|e| error!("get cover art for song {e:?}"))
+            .ok()
+            .flatten()
+    }
+    pub async fn get_song(&self, id: impl AsRef<str>) -> Option<song::Model> {
+        let Ok(id) = id.as_ref().parse::<i32>() else {
+            return None;
+        };
+        let mut song = song::Entity::find_by_id(id)
+            .one(self.connection())
+            .await
+            .inspect_err(|e| error!("get song {e:?}"))
+            .ok()
+            .flatten();
+
+        // TODO some kind of join would be nice?
+        if let Some(song) = song.as_mut().filter(|song| song.cover_art.is_none()) {
+            song.cover_art = self
+                .get_cover_art_for_song(song.id)
+                .await
+                .map(|ca| format!("{}", ca.id));
+        }
+        song
+    }
+
+    async fn playlist_with_entries(
+        &self,
+        playlist: playlist::Model,
+    ) -> Result<PlaylistWithEntries, DbErr> {
+        let owner = playlist
+            .find_related(user::Entity)
+            .one(self.connection())
+            .await?;
+        let mut songs: Vec<_> = playlist_entry::Entity::find()
+            .filter(playlist_entry::Column::Playlist.eq(playlist.id))
+            .order_by(playlist_entry::Column::Position, Order::Asc)
+            .find_also_related(song::Entity)
+            .all(self.connection())
+            .await?
+            .into_iter()
+            .filter_map(|(_, song)| song)
+            .collect();
+        self.fill_cover_art(&mut songs).await;
+
+        Ok(PlaylistWithEntries {
+            playlist,
+            owner,
+            songs,
+        })
+    }
+
+    /// playlists owned by `user`, plus everyone else's public ones.
+    /// `owner` restricts the result to playlists of that user
+    pub(crate) async fn get_playlists(
+        &self,
+        user: &user::Model,
+        owner: Option<&str>,
+    ) -> Vec<PlaylistWithEntries> {
+        let mut query = playlist::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(playlist::Column::Owner.eq(user.id))
+                    .add(playlist::Column::Public.eq(true)),
+            )
+            .order_by(playlist::Column::Name, Order::Asc);
+        if let Some(owner) = owner {
+            query = query
+                .inner_join(user::Entity)
+                .filter(user::Column::Username.eq(owner));
+        }
+
+        let playlists = query
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get playlists {e:?}"))
+            .unwrap_or_default();
+
+        let mut res = Vec::with_capacity(playlists.len());
+        for playlist in playlists {
+            match self.playlist_with_entries(playlist).await {
+                Ok(playlist) => res.push(playlist),
+                Err(e) => error!("get playlist entries {e:?}"),
+            }
+        }
+        res
+    }
+
+    pub(crate) async fn get_playlist(&self, id: i32) -> Option<PlaylistWithEntries> {
+        let playlist = playlist::Entity::find_by_id(id)
+            .one(self.connection())
+            .await
+            .inspect_err(|e| error!("get playlist {e:?}"))
+            .ok()
+            .flatten()?;
+        self.playlist_with_entries(playlist)
+            .await
+            .inspect_err(|e| error!("get playlist entries {e:?}"))
+            .ok()
+    }
+
+    pub(crate) async fn create_playlist(
+        &self,
+        owner: &user::Model,
+        name: String,
+        songs: Vec<i32>,
+    ) -> Result<i32, DbErr> {
+        let now = unix_now();
+        let playlist = playlist::ActiveModel {
+            name: AV::Set(name),
+            owner: AV::Set(owner.id),
+            public: AV::Set(false),
+            created: AV::Set(now),
+            changed: AV::Set(now),
+            ..Default::default()
+        };
+
+        let txn = self.connection().begin().await?;
+        let id = playlist::Entity::insert(playlist)
+            .exec(&txn)
+            .await?
+            .last_insert_id;
+        set_playlist_entries(&txn, id, songs).await?;
+        txn.commit().await?;
+        Ok(id)
+    }
+
+    /// fails with `DbErr::RecordNotFound` if the playlist or any of the added songs don't exist
+    pub(crate) async fn update_playlist(
+        &self,
+        id: i32,
+        update: PlaylistUpdate,
+    ) -> Result<(), DbErr> {
+        let txn = self.connection().begin().await?;
+        let Some(playlist) = playlist::Entity::find_by_id(id).one(&txn).await? else {
+            return Err(DbErr::RecordNotFound(format!("playlist {id}")));
+        };
+
+        let mut songs = match update.songs {
+            Some(songs) => songs,
+            None => playlist_entry::Entity::find()
+                .filter(playlist_entry::Column::Playlist.eq(id))
+                .order_by(playlist_entry::Column::Position, Order::Asc)
+                .all(&txn)
+                .await?
+                .into_iter()
+                .map(|entry| entry.song)
+                .collect(),
+        };
+        let remove: HashSet<_> = update.remove.into_iter().collect();
+        let mut index = 0;
+        songs.retain(|_| {
+            index += 1;
+            !remove.contains(&(index - 1))
+        });
+        songs.extend(update.add);
+        set_playlist_entries(&txn, id, songs).await?;
+
+        let mut playlist: playlist::ActiveModel = playlist.into();
+        if let Some(name) = update.name {
+            playlist.name = AV::Set(name);
+        }
+        if let Some(comment) = update.comment {
+            playlist.comment = AV::Set(Some(comment));
+        }
+        if let Some(public) = update.public {
+            playlist.public = AV::Set(public);
+        }
+        playlist.changed = AV::Set(unix_now());
+        playlist.update(&txn).await?;
+
+        txn.commit().await
+    }
+
+    pub(crate) async fn delete_playlist(&self, id: i32) -> Result<(), DbErr> {
+        // entries are removed via `ON DELETE CASCADE`
+        let res = playlist::Entity::delete_by_id(id)
+            .exec(self.connection())
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(DbErr::RecordNotFound(format!("playlist {id}")));
+        }
+        Ok(())
+    }
+
+    /// `user_id`'s play queue, with its songs in order
+    pub(crate) async fn get_play_queue(&self, user_id: i32) -> Option<PlayQueueWithEntries> {
+        let play_queue = play_queue::Entity::find()
+            .filter(play_queue::Column::User.eq(user_id))
+            .one(self.connection())
+            .await
+            .inspect_err(|e| error!("get play queue {e:?}"))
+            .ok()
+            .flatten()?;
+        let mut songs: Vec<_> = play_queue_entry::Entity::find()
+            .filter(play_queue_entry::Column::PlayQueue.eq(play_queue.id))
+            .order_by(play_queue_entry::Column::Position, Order::Asc)
+            .find_also_related(song::Entity)
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get play queue entries {e:?}"))
+            .ok()?
+            .into_iter()
+            .filter_map(|(_, song)| song)
+            .collect();
+        self.fill_cover_art(&mut songs).await;
+        Some(PlayQueueWithEntries { play_queue, songs })
+    }
+
+    /// replaces `user_id`'s play queue, an empty one is removed altogether;
+    /// fails with `DbErr::RecordNotFound` if any of the songs don't exist
+    pub(crate) async fn save_play_queue(
+        &self,
+        user_id: i32,
+        songs: Vec<i32>,
+        current: Option<u32>,
+        position: i64,
+        changed_by: String,
+    ) -> Result<(), DbErr> {
+        let txn = self.connection().begin().await?;
+        if songs.is_empty() {
+            // entries are removed via `ON DELETE CASCADE`
+            play_queue::Entity::delete_many()
+                .filter(play_queue::Column::User.eq(user_id))
+                .exec(&txn)
+                .await?;
+            return txn.commit().await;
+        }
+        check_songs(&txn, &songs).await?;
+
+        let play_queue = play_queue::ActiveModel {
+            user: AV::Set(user_id),
+            current: AV::Set(current),
+            position: AV::Set(position),
+            changed: AV::Set(unix_now()),
+            changed_by: AV::Set(changed_by),
+            ..Default::default()
+        };
+        play_queue::Entity::insert(play_queue)
+            .on_conflict(
+                OnConflict::column(play_queue::Column::User)
+                    .update_columns([
+                        play_queue::Column::Current,
+                        play_queue::Column::Position,
+                        play_queue::Column::Changed,
+                        play_queue::Column::ChangedBy,
+                    ])
+                    .to_owned(),
+            )
+            .exec_without_returning(&txn)
+            .await?;
+        // looked up, SQLite's last insert ID isn't set by the update
+        let id = play_queue::Entity::find()
+            .filter(play_queue::Column::User.eq(user_id))
+            .one(&txn)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound("play queue".to_string()))?
+            .id;
+
+        play_queue_entry::Entity::delete_many()
+            .filter(play_queue_entry::Column::PlayQueue.eq(id))
+            .exec(&txn)
+            .await?;
+        let entries =
+            songs
+                .into_iter()
+                .enumerate()
+                .map(|(position, song)| play_queue_entry::ActiveModel {
+                    play_queue: AV::Set(id),
+                    song: AV::Set(song),
+                    position: AV::Set(position as u32),
+                    ..Default::default()
+                });
+        play_queue_entry::Entity::insert_many(entries)
+            .exec(&txn)
+            .await?;
+        txn.commit().await
+    }
+
+    /// `user_id`'s bookmarks with their songs, the most recently changed first
+    pub(crate) async fn get_bookmarks(&self, user_id: i32) -> Vec<(bookmark::Model, song::Model)> {
+        let bookmarks: Vec<_> = bookmark::Entity::find()
+            .filter(bookmark::Column::User.eq(user_id))
+            .order_by(bookmark::Column::Changed, Order::Desc)
+            .find_also_related(song::Entity)
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get bookmarks {e:?}"))
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(bookmark, song)| Some((bookmark, song?)))
+            .collect();
+        let (bookmarks, mut songs): (Vec<_>, Vec<_>) = bookmarks.into_iter().unzip();
+        self.fill_cover_art(&mut songs).await;
+        bookmarks.into_iter().zip(songs).collect()
+    }
+
+    /// creates or replaces the bookmark of `user_id` on `song_id`, `position` in
+    /// milliseconds; with `keep`, an existing one is left alone
+    pub(crate) async fn set_bookmark(
+        &self,
+        user_id: i32,
+        song_id: i32,
+        position: i64,
+        comment: Option<String>,
+        keep: bool,
+    ) -> Result<(), DbErr> {
+        let now = unix_now();
+        let bookmark = bookmark::ActiveModel {
+            user: AV::Set(user_id),
+            song: AV::Set(song_id),
+            position: AV::Set(position),
+            comment: AV::Set(comment),
+            created: AV::Set(now),
+            changed: AV::Set(now),
+            ..Default::default()
+        };
+        let mut on_conflict = OnConflict::columns([bookmark::Column::User, bookmark::Column::Song]);
+        if keep {
+            on_conflict.do_nothing();
+        } else {
+            on_conflict.update_columns([
+                bookmark::Column::Position,
+                bookmark::Column::Comment,
+                bookmark::Column::Changed,
+            ]);
+        }
+        bookmark::Entity::insert(bookmark)
+            .on_conflict(on_conflict)
+            .exec_without_returning(self.connection())
+            .await?;
+        Ok(())
+    }
+
+    /// fails with `DbErr::RecordNotFound` if there is no such bookmark
+    pub(crate) async fn delete_bookmark(&self, user_id: i32, song_id: i32) -> Result<(), DbErr> {
+        let res = bookmark::Entity::delete_many()
+            .filter(bookmark::Column::User.eq(user_id))
+            .filter(bookmark::Column::Song.eq(song_id))
+            .exec(self.connection())
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(DbErr::RecordNotFound(format!("bookmark {song_id}")));
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn get_internet_radio_stations(&self) -> Vec<internet_radio_station::Model> {
+        internet_radio_station::Entity::find()
+            .order_by(internet_radio_station::Column::Name, Order::Asc)
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get internet radio stations {e:?}"))
+            .unwrap_or_default()
+    }
+
+    pub(crate) async fn get_internet_radio_station(
+        &self,
+        id: i32,
+    ) -> Option<internet_radio_station::Model> {
+        internet_radio_station::Entity::find_by_id(id)
+            .one(self.connection())
+            .await
+            .inspect_err(|e| error!("get internet radio station {e:?}"))
+            .ok()
+            .flatten()
+    }
+
+    pub(crate) async fn create_internet_radio_station(
+        &self,
+        name: String,
+        stream_url: String,
+        homepage_url: Option<String>,
+    ) -> Result<(), DbErr> {
+        let station = internet_radio_station::ActiveModel {
+            name: AV::Set(name),
+            stream_url: AV::Set(stream_url),
+            homepage_url: AV::Set(homepage_url),
+            ..Default::default()
+        };
+        internet_radio_station::Entity::insert(station)
+            .exec(self.connection())
+            .await?;
+        Ok(())
+    }
+
+    /// fails with `DbErr::RecordNotFound` if there is no such station
+    pub(crate) async fn update_internet_radio_station(
+        &self,
+        id: i32,
+        name: String,
+        stream_url: String,
+        homepage_url: Option<String>,
+    ) -> Result<(), DbErr> {
+        let res = internet_radio_station::Entity::update_many()
+            .col_expr(internet_radio_station::Column::Name, Expr::value(name))
+            .col_expr(
+                internet_radio_station::Column::StreamUrl,
+                Expr::value(stream_url),
+            )
+            .col_expr(
+                internet_radio_station::Column::HomepageUrl,
+                Expr::value(homepage_url),
+            )
+            .filter(internet_radio_station::Column::Id.eq(id))
+            .exec(self.connection())
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(DbErr::RecordNotFound(internet_radio_station::format_id(id)));
+        }
+        Ok(())
+    }
+
+    /// fails with `DbErr::RecordNotFound` if there is no such station
+    pub(crate) async fn delete_internet_radio_station(&self, id: i32) -> Result<(), DbErr> {
+        let res = internet_radio_station::Entity::delete_by_id(id)
+            .exec(self.connection())
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(DbErr::RecordNotFound(internet_radio_station::format_id(id)));
+        }
+        Ok(())
+    }
+
+    async fn share_with_entries(&self, share: share::Model) -> Result<ShareWithEntries, DbErr> {
+        let owner = share
+            .find_related(user::Entity)
+            .one(self.connection())
+            .await?;
+        let mut songs: Vec<_> = share_entry::Entity::find()
+            .filter(share_entry::Column::Share.eq(&share.id))
+            .order_by(share_entry::Column::Position, Order::Asc)
+            .find_also_related(song::Entity)
+            .all(self.connection())
+            .await?
+            .into_iter()
+            .filter_map(|(_, song)| song)
+            .collect();
+        self.fill_cover_art(&mut songs).await;
+        Ok(ShareWithEntries {
+            share,
+            owner,
+            songs,
+        })
+    }
+
+    /// `user_id`'s shares, expired ones included, the newest first
+    pub(crate) async fn get_shares(&self, user_id: i32) -> Vec<ShareWithEntries> {
+        let shares = share::Entity::find()
+            .filter(share::Column::User.eq(user_id))
+            .order_by(share::Column::Created, Order::Desc)
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get shares {e:?}"))
+            .unwrap_or_default();
+
+        let mut res = Vec::with_capacity(shares.len());
+        for share in shares {
+            match self.share_with_entries(share).await {
+                Ok(share) => res.push(share),
+                Err(e) => error!("get share entries {e:?}"),
+            }
+        }
+        res
+    }
+
+    pub(crate) async fn get_share(&self, id: &str) -> Option<ShareWithEntries> {
+        let share = share::Entity::find_by_id(id)
+            .one(self.connection())
+            .await
+            .inspect_err(|e| error!("get share {e:?}"))
+            .ok()
+            .flatten()?;
+        self.share_with_entries(share)
+            .await
+            .inspect_err(|e| error!("get share entries {e:?}"))
+            .ok()
+    }
+
+    /// shares `songs` in the given order, returns the new share's ID; fails with
+    /// `DbErr::RecordNotFound` if any of the songs don't exist
+    pub(crate) async fn create_share(
+        &self,
+        user_id: i32,
+        songs: Vec<i32>,
+        description: Option<String>,
+        expires: Option<i64>,
+    ) -> Result<String, DbErr> {
+        // long enough not to be guessed
+        let id: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(16)
+            .map(char::from)
+            .collect();
+        let share = share::ActiveModel {
+            id: AV::Set(id.clone()),
+            user: AV::Set(user_id),
+            description: AV::Set(description),
+            created: AV::Set(unix_now()),
+            expires: AV::Set(expires),
+            last_visited: AV::Set(None),
+            visit_count: AV::Set(0),
+        };
+
+        let txn = self.connection().begin().await?;
+        check_songs(&txn, &songs).await?;
+        share::Entity::insert(share)
+            .exec_without_returning(&txn)
+            .await?;
+        let entries =
+            songs
+                .into_iter()
+                .enumerate()
+                .map(|(position, song)| share_entry::ActiveModel {
+                    share: AV::Set(id.clone()),
+                    song: AV::Set(song),
+                    position: AV::Set(position as u32),
+                    ..Default::default()
+                });
+        share_entry::Entity::insert_many(entries).exec(&txn).await?;
+        txn.commit().await?;
+        Ok(id)
+    }
+
+    /// only what's `Some` changes, `Some(None)` removes the expiry; fails with
+    /// `DbErr::RecordNotFound` if `user_id` has no such share
+    pub(crate) async fn update_share(
+        &self,
+        user_id: i32,
+        id: &str,
+        description: Option<String>,
+        expires: Option<Option<i64>>,
+    ) -> Result<(), DbErr> {
+        let mut update = share::Entity::update_many()
+            .filter(share::Column::Id.eq(id))
+            .filter(share::Column::User.eq(user_id));
+        if let Some(description) = description {
+            update = update.col_expr(share::Column::Description, Expr::value(description));
+        }
+        if let Some(expires) = expires {
+            update = update.col_expr(share::Column::Expires, Expr::value(expires));
+        }
+        // something has to be set, and the row count tells whether the share exists
+        let res = update
+            .col_expr(share::Column::Id, Expr::col(share::Column::Id).into())
+            .exec(self.connection())
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(DbErr::RecordNotFound(format!("share {id}")));
+        }
+        Ok(())
+    }
+
+    /// fails with `DbErr::RecordNotFound` if `user_id` has no such share
+    pub(crate) async fn delete_share(&self, user_id: i32, id: &str) -> Result<(), DbErr> {
+        let res = share::Entity::delete_many()
+            .filter(share::Column::Id.eq(id))
+            .filter(share::Column::User.eq(user_id))
+            .exec(self.connection())
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(DbErr::RecordNotFound(format!("share {id}")));
+        }
+        Ok(())
+    }
+
+    /// counts a visit of the share's page
+    pub(crate) async fn visit_share(&self, id: &str) {
+        let res = share::Entity::update_many()
+            .col_expr(
+                share::Column::VisitCount,
+                Expr::col(share::Column::VisitCount).add(1),
+            )
+            .col_expr(share::Column::LastVisited, Expr::value(unix_now()))
+            .filter(share::Column::Id.eq(id))
+            .exec(self.connection())
+            .await;
+        if let Err(e) = res {
+            error!("visit share {e:?}");
+        }
+    }
+
+    /// records a play of `song_id` by `user_id` at `time` (unix milliseconds), and
+    /// bumps the play counts of the song and its album
+    pub(crate) async fn scrobble(
+        &self,
+        user_id: i32,
+        song_id: i32,
+        time: i64,
+    ) -> Result<(), DbErr> {
+        let txn = self.connection().begin().await?;
+        let Some(song) = song::Entity::find_by_id(song_id).one(&txn).await? else {
+            return Err(DbErr::RecordNotFound(format!("{song_id}")));
+        };
+
+        play_history::ActiveModel {
+            user: AV::Set(user_id),
+            song: AV::Set(song_id),
+            time: AV::Set(time),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        let album_id = song.album_id;
+        let play_count = song.play_count + 1;
+        // plays may be submitted out of order (e.g. from an offline cache)
+        let played = song.played.max(Some(time));
+        let mut song: song::ActiveModel = song.into();
+        song.play_count = AV::Set(play_count);
+        song.played = AV::Set(played);
+        song.update(&txn).await?;
+
+        if let Some(album) = match album_id {
+            Some(id) => album::Entity::find_by_id(id).one(&txn).await?,
+            None => None,
+        } {
+            let play_count = album.play_count + 1;
+            let played = album.played.max(Some(time));
+            let mut album: album::ActiveModel = album.into();
+            album.play_count = AV::Set(play_count);
+            album.played = AV::Set(played);
+            album.update(&txn).await?;
+        }
+
+        txn.commit().await
+    }
+
+    /// stars (or unstars) `items` for `user_id`, fails with `DbErr::RecordNotFound`
+    /// if any of them don't exist
+    pub(crate) async fn set_starred(
+        &self,
+        user_id: i32,
+        items: &[Item],
+        starred: bool,
+    ) -> Result<(), DbErr> {
+        let txn = self.connection().begin().await?;
+        for item in items {
+            annotate(&txn, user_id, *item, |annotation| {
+                // starring again keeps the original timestamp
+                annotation.starred = match starred {
+                    true => annotation.starred.or(Some(unix_now())),
+                    false => None,
+                };
+            })
+            .await?;
+        }
+        txn.commit().await
+    }
+
+    /// `None` removes the rating
+    pub(crate) async fn set_rating(
+        &self,
+        user_id: i32,
+        item: Item,
+        rating: Option<u32>,
+    ) -> Result<(), DbErr> {
+        let txn = self.connection().begin().await?;
+        annotate(&txn, user_id, item, |annotation| annotation.rating = rating).await?;
+        txn.commit().await
+    }
+
+    pub(crate) async fn get_annotations(
+        &self,
+        user_id: i32,
+        items: &[Item],
+    ) -> HashMap<Item, annotation::Model> {
+        if items.is_empty() {
+            return HashMap::new();
+        }
+        let (mut songs, mut albums, mut artists, mut directories) =
+            (vec![], vec![], vec![], vec![]);
+        for item in items {
+            match *item {
+                Item::Song(id) => songs.push(id),
+                Item::Album(id) => albums.push(id),
+                Item::Artist(id) => artists.push(id),
+                Item::Directory(id) => directories.push(id),
+            }
+        }
+
+        annotation::Entity::find()
+            .filter(annotation::Column::User.eq(user_id))
+            .filter(
+                Condition::any()
+                    .add(annotation::Column::Song.is_in(songs))
+                    .add(annotation::Column::Album.is_in(albums))
+                    .add(annotation::Column::Artist.is_in(artists))
+                    .add(annotation::Column::Directory.is_in(directories)),
+            )
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get annotations {e:?}"))
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|annotation| Some((annotation.item()?, annotation)))
+            .collect()
+    }
+
+    /// most recently starred first, in `folders`
+    pub(crate) async fn get_starred_songs(
+        &self,
+        user_id: i32,
+        folders: &[i32],
+    ) -> Vec<song::Model> {
+        let mut songs = song::Entity::find()
+            .join(JoinType::InnerJoin, annotation::Relation::Song.def().rev())
+            .filter(starred_by(user_id))
+            .filter(songs_in(folders))
+            .order_by(annotation::Column::Starred, Order::Desc)
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get starred songs {e:?}"))
+            .unwrap_or_default();
+        self.fill_cover_art(&mut songs).await;
+        songs
+    }
+
+    /// most recently starred first, with songs in `folders`
+    pub(crate) async fn get_starred_albums(&self, user_id: i32, folders: &[i32]) -> Vec<Album> {
+        self.albums()
+            .join(JoinType::InnerJoin, annotation::Relation::Album.def().rev())
+            .filter(starred_by(user_id))
+            .filter(albums_in(folders))
+            .order_by(annotation::Column::Starred, Order::Desc)
+            .into_model::<Album>()
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get starred albums {e:?}"))
+            .unwrap_or_default()
+    }
+
+    /// most recently starred first, with songs or albums in `folders`
+    pub(crate) async fn get_starred_artists(&self, user_id: i32, folders: &[i32]) -> Vec<Artist> {
+        self.artists()
+            .join(
+                JoinType::InnerJoin,
+                annotation::Relation::Artist.def().rev(),
+            )
+            .filter(starred_by(user_id))
+            .filter(artists_in(folders))
+            .order_by(annotation::Column::Starred, Order::Desc)
+            .into_model::<Artist>()
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get starred artists {e:?}"))
+            .unwrap_or_default()
+    }
+
+    /// most recently starred first
+    pub(crate) async fn get_starred_directories(&self, user_id: i32) -> Vec<directory::Model> {
+        directory::Entity::find()
+            .join(
+                JoinType::InnerJoin,
+                annotation::Relation::Directory.def().rev(),
+            )
+            .filter(starred_by(user_id))
+            .order_by(annotation::Column::Starred, Order::Desc)
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get starred directories {e:?}"))
+            .unwrap_or_default()
+    }
+
+    pub(crate) async fn get_user(&self, username: &str) -> Option<user::Model> {
+        user::Entity::find()
+            .filter(user::Column::Username.eq(username))
+            .one(self.connection())
+            .await
+            .inspect_err(|e| error!("get user {e:?}"))
+            .ok()
+            .flatten()
+    }
+
+    pub(crate) async fn get_users(&self) -> Vec<user::Model> {
+        user::Entity::find()
+            .order_by(user::Column::Username, Order::Asc)
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get users {e:?}"))
+            .unwrap_or_default()
+    }
+
+    /// restricted to the music `folders`, unless there are none
+    pub(crate) async fn create_user(
+        &self,
+        user: user::ActiveModel,
+        folders: &[i32],
+    ) -> Result<(), DbErr> {
+        let txn = self.connection().begin().await?;
+        let id = user::Entity::insert(user).exec(&txn).await?.last_insert_id;
+        set_user_folders(&txn, id, folders).await?;
+        txn.commit().await
+    }
+
+    /// what's set in `user` changes, and their music folders become `folders`
+    /// if given; fails with `DbErr::RecordNotFound` if there is no such user
+    pub(crate) async fn update_user(
+        &self,
+        user: user::ActiveModel,
+        folders: Option<&[i32]>,
+    ) -> Result<(), DbErr> {
+        let txn = self.connection().begin().await?;
+        let updated = match user.update(&txn).await {
+            Ok(updated) => updated,
+            Err(DbErr::RecordNotUpdated) => return Err(DbErr::RecordNotFound("user".to_string())),
+            Err(e) => return Err(e),
+        };
+        if let Some(folders) = folders {
+            set_user_folders(&txn, updated.id, folders).await?;
+        }
+        txn.commit().await
+    }
+
+    /// everything of theirs goes with them; fails with `DbErr::RecordNotFound` if
+    /// there is no such user
+    pub(crate) async fn delete_user(&self, username: &str) -> Result<(), DbErr> {
+        let res = user::Entity::delete_many()
+            .filter(user::Column::Username.eq(username))
+            .exec(self.connection())
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(DbErr::RecordNotFound(username.to_string()));
+        }
+        Ok(())
+    }
+
+    /// the music folders `user_id` is restricted to, none meaning all of them
+    pub(crate) async fn get_user_folders(&self, user_id: i32) -> Vec<i32> {
+        user_music_folder::Entity::find()
+            .select_only()
+            .column(user_music_folder::Column::Folder)
+            .filter(user_music_folder::Column::User.eq(user_id))
+            .into_tuple()
+            .all(self.connection())
+            .await
+            .inspect_err(|e| error!("get user folders {e:?}"))
+            .unwrap_or_default()
+    }
+
+    pub(crate) async fn get_user_by_api_key(&self, api_key: &str) -> Option<user::Model> {
+        user::Entity::find()
+            .filter(user::Column::ApiKey.eq(api_key))
+            .one(self.connection())
+            .await
+            .inspect_err(|e| error!("get user by api key {e:?}"))
+            .ok()
+            .flatten()
+    }
+
+    pub fn connection(&self) -> &DatabaseConnection {
+        &self.connection
+    }
+
+    pub fn data_path(&self) -> &Utf8Path {
+        self.data_path.as_path()
+    }
+}
+
+fn unix_now() -> i64 {
+    OffsetDateTime::now_utc().unix_timestamp()
+}
+
+pub(crate) fn unix_now_ms() -> i64 {
+    (OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000) as i64
+}
+
+// songs in any of `folders`
+fn songs_in(folders: &[i32]) -> SimpleExpr {
+    song::Column::Folder.is_in(folders.iter().copied())
+}
+
+// albums with any of their songs in `folders`
+fn albums_in(folders: &[i32]) -> SimpleExpr {
+    album::Column::Id.in_subquery(
+        Query::select()
+            .column(song::Column::AlbumId)
+            .from(song::Entity)
+            .and_where(songs_in(folders))
+            .to_owned(),
+    )
+}
+
+// artists of songs or albums in `folders`
+fn artists_in(folders: &[i32]) -> Condition {
+    Condition::any()
+        .add(
+            artist::Column::Id.in_subquery(
+                Query::select()
+                    .column(song::Column::ArtistId)
+                    .from(song::Entity)
+                    .and_where(songs_in(folders))
+                    .to_owned(),
+            ),
+        )
+        .add(
+            artist::Column::Id.in_subquery(
+                Query::select()
+                    .column(album::Column::ArtistId)
+                    .from(album::Entity)
+                    .and_where(albums_in(folders))
+                    .to_owned(),
+            ),
+        )
+}
+
+fn starred_by(user_id: i32) -> Condition {
+    Condition::all()
+        .add(annotation::Column::User.eq(user_id))
+        .add(annotation::Column::Starred.is_not_null())
+}
+
+// applies `update` to the annotation of `item` by `user_id`, which is created as
+// needed, and removed once there's nothing left in it
+async fn annotate(
+    txn: &DatabaseTransaction,
+    user_id: i32,
+    item: Item,
+    update: impl FnOnce(&mut annotation::Model),
+) -> Result<(), DbErr> {
+    let exists = match item {
+        Item::Song(id) => song::Entity::find_by_id(id).count(txn).await?,
+        Item::Album(id) => album::Entity::find_by_id(id).count(txn).await?,
+        Item::Artist(id) => artist::Entity::find_by_id(id).count(txn).await?,
+        Item::Directory(id) => directory::Entity::find_by_id(id).count(txn).await?,
+    } > 0;
+    if !exists {
+        return Err(DbErr::RecordNotFound(item.to_string()));
+    }
+
+    let (column, id) = item.column();
+    let existing = annotation::Entity::find()
+        .filter(annotation::Column::User.eq(user_id))
+        .filter(column.eq(id))
+        .one(txn)
+        .await?;
+    let mut annotation = existing
+        .clone()
+        .unwrap_or_else(|| annotation::Model::new(user_id, item));
+    update(&mut annotation);
+
+    let empty = annotation.starred.is_none() && annotation.rating.is_none();
+    match (existing, empty) {
+        (Some(existing), true) => {
+            existing.delete(txn).await?;
+        }
+        (Some(_), false) => {
+            annotation
+                .into_active_model()
+                .reset_all()
+                .update(txn)
+                .await?;
+        }
+        (None, true) => {}
+        (None, false) => {
+            let mut annotation = annotation.into_active_model().reset_all();
+            annotation.id = AV::NotSet;
+            annotation.insert(txn).await?;
+        }
+    }
+    Ok(())
+}
+
+// fails with `DbErr::RecordNotFound` unless all `songs` exist
+async fn check_songs(txn: &DatabaseTransaction, songs: &[i32]) -> Result<(), DbErr> {
+    let known = song::Entity::find()
+        .filter(song::Column::Id.is_in(songs.iter().copied()))
+        .count(txn)
+        .await?;
+    let distinct: HashSet<_> = songs.iter().collect();
+    if known != distinct.len() as u64 {
+        return Err(DbErr::RecordNotFound("song".to_string()));
+    }
+    Ok(())
+}
+
+// restricts `user_id` to `folders`, none lifting the restriction
+async fn set_user_folders(
+    txn: &DatabaseTransaction,
+    user_id: i32,
+    folders: &[i32],
+) -> Result<(), DbErr> {
+    user_music_folder::Entity::delete_many()
+        .filter(user_music_folder::Column::User.eq(user_id))
+        .exec(txn)
+        .await?;
+    if !folders.is_empty() {
+        let entries = folders.iter().map(|folder| user_music_folder::ActiveModel {
+            user: AV::Set(user_id),
+            folder: AV::Set(*folder),
+            ..Default::default()
+        });
+        user_music_folder::Entity::insert_many(entries)
+            .exec(txn)
+            .await?;
+    }
+    Ok(())
+}
+
+// replaces all entries of playlist `id` with `songs`, in that order
+async fn set_playlist_entries(
+    txn: &DatabaseTransaction,
+    id: i32,
+    songs: Vec<i32>,
+) -> Result<(), DbErr> {
+    check_songs(txn, &songs).await?;
+
+    playlist_entry::Entity::delete_many()
+        .filter(playlist_entry::Column::Playlist.eq(id))
+        .exec(txn)
+        .await?;
+    let entries =
+        songs
+            .into_iter()
+            .enumerate()
+            .map(|(position, song)| playlist_entry::ActiveModel {
+                playlist: AV::Set(id),
+                song: AV::Set(song),
+                position: AV::Set(position as u32),
+                ..Default::default()
+            });
+    playlist_entry::Entity::insert_many(entries)
+        .on_empty_do_nothing()
+        .exec(txn)
+        .await?;
+    Ok(())
 }
@@ -9,21 +9,50 @@ use serde_json::{Map, Value};
 use thiserror::Error;
 use tracing::warn;
 
+use super::audio::Properties;
+
 #[derive(Debug, Deserialize)]
 pub(crate) struct Metadata {
-    format: Tags,
+    format: Format,
+    #[serde(default)]
+    streams: Vec<Stream>,
 }
 
 impl Metadata {
+    /// of the first audio stream, with the overall bit rate as a fallback
+    pub(crate) fn properties(&self) -> Properties {
+        let stream = self.streams.iter().find(|s| s.sample_rate.is_some());
+        let bit_rate = stream
+            .and_then(|s| s.bit_rate.as_deref())
+            .or(self.format.bit_rate.as_deref());
+        Properties {
+            bit_rate: bit_rate.and_then(leading_number).map(|bits| bits / 1000),
+            sampling_rate: stream
+                .and_then(|s| s.sample_rate.as_deref())
+                .and_then(leading_number),
+            channel_count: stream.and_then(|s| s.channels),
+        }
+    }
+
     pub(crate) fn into_tag(self) -> Tag {
         self.format.tags
     }
 }
 
 #[derive(Debug, Deserialize)]
-pub(crate) struct Tags {
+pub(crate) struct Format {
     #[serde(deserialize_with = "case_insensitive")]
     tags: Tag,
+    // in bit/s
+    bit_rate: Option<String>,
+}
+
+// ffprobe has numbers as strings, mostly
+#[derive(Debug, Deserialize)]
+struct Stream {
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+    bit_rate: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -31,12 +60,32 @@ pub(crate) struct Tag {
     pub(crate) title: String,
     pub(crate) artist: Option<String>,
     pub(crate) album: Option<String>,
-    #[allow(unused)]
+    pub(crate) album_artist: Option<String>,
     pub(crate) genre: Option<String>,
     // hurray: it can be "01" or "2/14", so no uint here 🙄
-    // ... should we parse it?
-    #[allow(unused)]
     pub(crate) track: Option<String>,
+    // same as `track`
+    pub(crate) disc: Option<String>,
+    // "2001", "2001-04-01", ...
+    pub(crate) date: Option<String>,
+    // Vorbis comments; plain text or LRC
+    pub(crate) lyrics: Option<String>,
+    // the individual artists, where `artist` is for display
+    pub(crate) artists: Option<String>,
+    pub(crate) titlesort: Option<String>,
+    pub(crate) musicbrainz_trackid: Option<String>,
+    // "-6.50 dB", "0.988"
+    pub(crate) replaygain_track_gain: Option<String>,
+    pub(crate) replaygain_track_peak: Option<String>,
+    pub(crate) replaygain_album_gain: Option<String>,
+    pub(crate) replaygain_album_peak: Option<String>,
+}
+
+/// the leading number of "01", "2/14", "2001-04-01" etc.
+pub(crate) fn leading_number(s: &str) -> Option<u32> {
+    let s = s.trim_start();
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s[..end].parse().ok()
 }
 
 fn case_insensitive<'de, T, D>(deserializer: D) -> Result<T, D::Error>
@@ -66,7 +115,7 @@ pub(crate) fn metadata(f: impl AsRef<Utf8Path>) -> Result<Metadata, Error> {
             "-loglevel",
             "error",
             "-show_entries",
-            "stream_tags:format_tags",
+            "stream=sample_rate,channels,bit_rate:stream_tags:format=bit_rate:format_tags",
             "-of",
             "json",
             f.as_ref().as_str(),
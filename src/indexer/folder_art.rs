@@ -0,0 +1,166 @@
+//! cover art from image files next to the songs (`cover.jpg`, `folder.png`, ...)
+
+use std::collections::HashMap;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use sea_orm::{ActiveValue as AV, EntityTrait};
+use tracing::{debug, error, warn};
+
+use super::{db::DB, is_disc_dir};
+use crate::{
+    config::{Artwork, ArtworkSource},
+    entity::cover_art,
+};
+
+const IMAGE_EXTENSIONS: [&str; 5] = ["jpg", "jpeg", "png", "webp", "gif"];
+
+// the first of `names` found in `dir`
+fn find_image(dir: &Utf8Path, names: &[String]) -> Option<Utf8PathBuf> {
+    let mut images: Vec<_> = dir
+        .read_dir_utf8()
+        .inspect_err(|e| warn!("reading {dir}: {e}"))
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.into_path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        })
+        .collect();
+    // `cover.jpg` and `cover.png`? take the same one every time
+    images.sort();
+    names.iter().find_map(|name| {
+        images
+            .iter()
+            .find(|path| {
+                path.file_stem()
+                    .is_some_and(|stem| stem.eq_ignore_ascii_case(name))
+            })
+            .cloned()
+    })
+}
+
+// copies the image into the artwork store, once
+async fn register(db: &DB, path: &Utf8Path) -> Option<cover_art::Model> {
+    if let Some(existing) = db.get_folder_cover_art(path.as_str()).await {
+        return Some(existing);
+    }
+
+    let data = tokio::fs::read(path)
+        .await
+        .inspect_err(|e| error!("reading {path}: {e}"))
+        .ok()?;
+    let (shard, hash) = cover_art::Model::write(&data, db.data_path())
+        .await
+        .inspect_err(|e| error!("writing cover art: {e}"))
+        .ok()?;
+    let cover_art = cover_art::ActiveModel {
+        shard: AV::Set(shard),
+        mime_type: AV::Set(
+            mime_guess::from_path(path)
+                .first_or_octet_stream()
+                .to_string(),
+        ),
+        path: AV::Set(Some(path.to_string())),
+        hash: AV::Set(Some(hash)),
+        ..Default::default()
+    };
+    let id = cover_art::Entity::insert(cover_art)
+        .exec(db.connection())
+        .await
+        .inspect_err(|e| warn!("inserting folder cover art: {e}"))
+        .ok()?
+        .last_insert_id;
+    debug!("found folder cover art {path}");
+    db.get_cover_art(format!("{id}")).await
+}
+
+/// looks for images in song directories, remembering what it found for the
+/// rest of the run
+pub(super) struct FolderArt {
+    config: Artwork,
+    media_paths: Vec<Utf8PathBuf>,
+    albums: HashMap<Utf8PathBuf, Option<cover_art::Model>>,
+    artists: HashMap<Utf8PathBuf, Option<cover_art::Model>>,
+}
+
+impl FolderArt {
+    pub(super) fn new(config: Artwork, media_paths: Vec<Utf8PathBuf>) -> Self {
+        FolderArt {
+            config,
+            media_paths,
+            albums: HashMap::new(),
+            artists: HashMap::new(),
+        }
+    }
+
+    pub(super) fn uses(&self, source: ArtworkSource) -> bool {
+        self.config.precedence.contains(&source)
+    }
+
+    /// position in the configured precedence, lower is better
+    pub(super) fn rank(&self, cover_art: &cover_art::Model) -> usize {
+        let source = match cover_art.path {
+            Some(_) => ArtworkSource::Folder,
+            None => ArtworkSource::Embedded,
+        };
+        self.config
+            .precedence
+            .iter()
+            .position(|preferred| *preferred == source)
+            .unwrap_or(usize::MAX)
+    }
+
+    // the song's directory and, for disc directories, the album directory above
+    fn album_dirs<'a>(&self, song: &'a Utf8Path) -> Vec<&'a Utf8Path> {
+        let Some(dir) = song.parent() else {
+            return vec![];
+        };
+        match dir.parent() {
+            Some(parent) if dir.file_name().is_some_and(is_disc_dir) && self.contains(parent) => {
+                vec![dir, parent]
+            }
+            _ => vec![dir],
+        }
+    }
+
+    fn contains(&self, dir: &Utf8Path) -> bool {
+        self.media_paths
+            .iter()
+            .any(|root| dir.starts_with(root) && dir != root)
+    }
+
+    /// folder art for the song (and its album)
+    pub(super) async fn album(&mut self, db: &DB, song: &Utf8Path) -> Option<cover_art::Model> {
+        if !self.uses(ArtworkSource::Folder) {
+            return None;
+        }
+        for dir in self.album_dirs(song) {
+            if !self.albums.contains_key(dir) {
+                let found = match find_image(dir, &self.config.folder) {
+                    Some(path) => register(db, &path).await,
+                    None => None,
+                };
+                self.albums.insert(dir.to_owned(), found);
+            }
+            if let Some(Some(cover_art)) = self.albums.get(dir) {
+                return Some(cover_art.clone());
+            }
+        }
+        None
+    }
+
+    /// the image in the artist directory, one above the album
+    pub(super) async fn artist(&mut self, db: &DB, song: &Utf8Path) -> Option<cover_art::Model> {
+        let album_dir = *self.album_dirs(song).last()?;
+        let dir = album_dir.parent().filter(|dir| self.contains(dir))?;
+        if !self.artists.contains_key(dir) {
+            let found = match find_image(dir, &self.config.artist) {
+                Some(path) => register(db, &path).await,
+                None => None,
+            };
+            self.artists.insert(dir.to_owned(), found);
+        }
+        self.artists.get(dir).cloned().flatten()
+    }
+}
@@ -0,0 +1,116 @@
+//! keeps `data/artwork` and the `cover_art` table in step: files nothing
+//! refers to go, as do rows whose file has gone missing. Albums left without
+//! songs after a scan go, too
+
+use std::collections::HashSet;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use tracing::{debug, error, info, warn};
+
+use super::db::DB;
+use crate::entity::cover_art;
+
+// the files in each shard directory below `dir`
+async fn shard_files(dir: &Utf8Path) -> Vec<Utf8PathBuf> {
+    let mut files = vec![];
+    let Ok(mut shards) = tokio::fs::read_dir(dir).await else {
+        return files;
+    };
+    while let Ok(Some(shard)) = shards.next_entry().await {
+        let Ok(mut entries) = tokio::fs::read_dir(shard.path()).await else {
+            continue;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            match Utf8PathBuf::from_path_buf(entry.path()) {
+                Ok(path) => files.push(path),
+                Err(path) => warn!("skipping non UTF-8 path: {path:?}"),
+            }
+        }
+    }
+    files
+}
+
+// rows from before content addressing have their file hashed and moved
+async fn move_legacy(db: &DB, rows: &mut [cover_art::Model]) {
+    let root = db.data_path();
+    for row in rows.iter_mut().filter(|row| row.hash.is_none()) {
+        let legacy = row.path(root);
+        let Ok(data) = tokio::fs::read(&legacy).await else {
+            // dealt with as missing
+            continue;
+        };
+        let (shard, hash) = match cover_art::Model::write(&data, root).await {
+            Ok(stored) => stored,
+            Err(e) => {
+                error!("moving {legacy}: {e}");
+                continue;
+            }
+        };
+        if let Err(e) = db.set_cover_art_hash(row.id, shard, hash.clone()).await {
+            error!("moving {legacy}: {e}");
+            continue;
+        }
+        row.shard = shard;
+        row.hash = Some(hash);
+        if let Err(e) = tokio::fs::remove_file(&legacy).await {
+            warn!("removing {legacy}: {e}");
+        }
+    }
+}
+
+pub(super) async fn collect_garbage(db: &DB) {
+    let root = db.data_path();
+    let mut rows = db.all_cover_art().await;
+    move_legacy(db, &mut rows).await;
+
+    let mut missing = vec![];
+    let mut hashes = HashSet::new();
+    for row in rows {
+        match (tokio::fs::try_exists(row.path(root)).await, row.hash) {
+            (Ok(true), Some(hash)) => {
+                hashes.insert(hash);
+            }
+            _ => missing.push(row.id),
+        }
+    }
+    if !missing.is_empty() {
+        info!("dropping {} cover art rows without a file", missing.len());
+        if let Err(e) = db.delete_cover_art(&missing).await {
+            error!("dropping cover art rows: {e}");
+        }
+    }
+
+    // thumbnails are named `{hash}-{size}.jpg`
+    let artwork = shard_files(&root.join("data").join("artwork")).await;
+    let thumbnails = shard_files(&root.join("data").join("thumbnails")).await;
+    let unreferenced: Vec<_> = artwork
+        .into_iter()
+        .filter(|path| !path.file_name().is_some_and(|name| hashes.contains(name)))
+        .chain(thumbnails.into_iter().filter(|path| {
+            !path
+                .file_name()
+                .and_then(|name| name.split_once('-'))
+                .is_some_and(|(hash, _)| hashes.contains(hash))
+        }))
+        .collect();
+    for path in &unreferenced {
+        debug!("removing unreferenced {path}");
+        if let Err(e) = tokio::fs::remove_file(path).await {
+            warn!("removing {path}: {e}");
+        }
+    }
+    info!(
+        "artwork: {} images, removed {} files",
+        hashes.len(),
+        unreferenced.len()
+    );
+}
+
+// once songs are regrouped, the albums they were in before may be empty
+pub(super) async fn collect_albums(db: &DB) {
+    match db.delete_empty_albums().await {
+        Ok(0) => {}
+        Ok(dropped) => info!("dropped {dropped} albums without songs"),
+        Err(e) => error!("dropping albums without songs: {e}"),
+    }
+}
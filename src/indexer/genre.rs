@@ -0,0 +1,113 @@
+//! genre tags, which come in all shapes: `Rock`, `(17)`, `(17)(31)Trance`,
+//! `17`, `Rock; Pop`, ...
+
+// the ID3v1 (Winamp extended) genre at `index`
+fn v1_genre(index: u32) -> Option<String> {
+    let genre_id = u8::try_from(index).ok()?;
+    id3::v1::Tag {
+        genre_id,
+        ..id3::v1::Tag::new()
+    }
+    .genre()
+    .map(String::from)
+}
+
+/// resolves the ID3v1 references in a TCON value: `(17)(31)Eurodance` is
+/// `Rock`, `Trance` and `Eurodance`; a bare `17` is `Rock` as well
+pub(super) fn resolve_tcon(value: &str) -> Vec<String> {
+    let value = value.trim();
+    if let Ok(index) = value.parse() {
+        return v1_genre(index).into_iter().collect();
+    }
+
+    let mut genres = vec![];
+    let mut rest = value;
+    loop {
+        // "((" escapes a genre that starts with a parenthesis
+        if rest.starts_with("((") {
+            genres.push(rest[1..].to_string());
+            return genres;
+        }
+        let Some(inner) = rest.strip_prefix('(') else {
+            break;
+        };
+        let Some((reference, after)) = inner.split_once(')') else {
+            break;
+        };
+        let resolved = match reference {
+            "RX" => Some("Remix".to_string()),
+            "CR" => Some("Cover".to_string()),
+            _ => reference.parse().ok().and_then(v1_genre),
+        };
+        // not a reference after all, so keep it as it is
+        let Some(resolved) = resolved else {
+            break;
+        };
+        genres.push(resolved);
+        rest = after;
+    }
+    if !rest.trim().is_empty() {
+        genres.push(rest.to_string());
+    }
+    genres
+}
+
+/// splits `values` on `separators`, dropping empty and duplicate genres
+/// (regardless of case, the first spelling wins)
+pub(super) fn normalize<S: AsRef<str>>(values: &[S], separators: &[String]) -> Vec<String> {
+    let mut genres: Vec<String> = vec![];
+    for value in values {
+        let mut parts = vec![value.as_ref()];
+        for separator in separators.iter().filter(|s| !s.is_empty()) {
+            parts = parts
+                .into_iter()
+                .flat_map(|part| part.split(separator.as_str()))
+                .collect();
+        }
+        for part in parts.into_iter().map(str::trim).filter(|p| !p.is_empty()) {
+            if !genres
+                .iter()
+                .any(|g| g.to_lowercase() == part.to_lowercase())
+            {
+                genres.push(part.to_string());
+            }
+        }
+    }
+    genres
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn v1_numbers() {
+        assert_eq!(resolve_tcon("17"), ["Rock"]);
+        assert_eq!(resolve_tcon(" 0 "), ["Blues"]);
+        assert!(resolve_tcon("300").is_empty());
+    }
+
+    #[test]
+    fn references() {
+        assert_eq!(
+            resolve_tcon("(17)(31)Eurodance"),
+            ["Rock", "Trance", "Eurodance"]
+        );
+        assert_eq!(resolve_tcon("(17)"), ["Rock"]);
+        assert_eq!(resolve_tcon("(RX)(CR)"), ["Remix", "Cover"]);
+        assert_eq!(resolve_tcon("((Sic)"), ["(Sic)"]);
+        // not a reference, so left alone
+        assert_eq!(resolve_tcon("(Live) Rock"), ["(Live) Rock"]);
+        assert_eq!(resolve_tcon("Rock"), ["Rock"]);
+    }
+
+    #[test]
+    fn separators() {
+        let separators = [";".to_string(), "/".to_string(), String::new()];
+        assert_eq!(
+            normalize(&["Rock; Pop", "pop", "Jazz/Blues", " "], &separators),
+            ["Rock", "Pop", "Jazz", "Blues"]
+        );
+        assert_eq!(normalize(&["Rock; Pop"], &[]), ["Rock; Pop"]);
+    }
+}
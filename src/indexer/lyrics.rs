@@ -0,0 +1,313 @@
+//! lyrics from tags (ID3 USLT and SYLT, Vorbis LYRICS) and `.lrc` files
+
+use camino::Utf8Path;
+use id3::frame::{SynchronisedLyricsType, TimestampFormat};
+use sea_orm::ActiveValue as AV;
+use tracing::warn;
+
+use super::Tag;
+use crate::entity::lyrics::{self, UNKNOWN_LANGUAGE};
+
+/// a line of lyrics, with its start in milliseconds if synced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Line {
+    pub(crate) start: Option<u32>,
+    pub(crate) value: String,
+}
+
+/// parsed LRC: metadata and lines in order of appearance
+#[derive(Debug, Default)]
+pub(crate) struct Lrc {
+    pub(crate) artist: Option<String>,
+    pub(crate) title: Option<String>,
+    pub(crate) lines: Vec<Line>,
+}
+
+impl Lrc {
+    pub(crate) fn synced(&self) -> bool {
+        self.lines.iter().any(|line| line.start.is_some())
+    }
+}
+
+// `mm:ss`, `mm:ss.xx` or `mm:ss.xxx`
+fn parse_timestamp(stamp: &str) -> Option<u32> {
+    let (minutes, seconds) = stamp.split_once(':')?;
+    let (seconds, fraction) = seconds.split_once(['.', ':']).unwrap_or((seconds, "0"));
+    let minutes: u32 = minutes.trim().parse().ok()?;
+    let seconds: u32 = seconds.parse().ok()?;
+    let millis = match fraction.len() {
+        1 => fraction.parse::<u32>().ok()? * 100,
+        2 => fraction.parse::<u32>().ok()? * 10,
+        3 => fraction.parse::<u32>().ok()?,
+        _ => return None,
+    };
+    Some(minutes * 60_000 + seconds * 1000 + millis)
+}
+
+/// parses LRC; text without timestamps comes out as unsynced lines
+pub(crate) fn parse(text: &str) -> Lrc {
+    let mut lrc = Lrc::default();
+    let mut offset = 0i64;
+    let mut synced = vec![];
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        let mut rest = line;
+        let mut starts = vec![];
+        // a line can carry several timestamps when it repeats
+        while let Some((tag, after)) = rest
+            .strip_prefix('[')
+            .and_then(|inner| inner.split_once(']'))
+        {
+            if let Some(start) = parse_timestamp(tag) {
+                starts.push(start);
+            } else {
+                // anything else in brackets ("[Chorus]") is part of the lyrics
+                let Some((key, value)) = tag.split_once(':') else {
+                    break;
+                };
+                let value = value.trim().to_string();
+                match key.trim() {
+                    "ar" => lrc.artist = Some(value),
+                    "ti" => lrc.title = Some(value),
+                    "offset" => offset = value.parse().unwrap_or_default(),
+                    "al" | "au" | "by" | "length" | "re" | "ve" | "#" => {}
+                    _ => break,
+                }
+            }
+            rest = after;
+        }
+
+        if starts.is_empty() {
+            // lines holding nothing but metadata aren't lyrics
+            if rest.len() == line.len() {
+                lrc.lines.push(Line {
+                    start: None,
+                    value: rest.to_string(),
+                });
+            }
+            continue;
+        }
+        for start in starts {
+            synced.push(Line {
+                start: Some(start),
+                value: rest.trim().to_string(),
+            });
+        }
+    }
+
+    if !synced.is_empty() {
+        // a positive offset makes lyrics show up sooner
+        for line in &mut synced {
+            line.start = line
+                .start
+                .map(|start| (start as i64 - offset).max(0) as u32);
+        }
+        synced.sort_by_key(|line| line.start);
+        lrc.lines = synced;
+    } else {
+        // blank lines before and after them aren't lyrics either
+        while lrc
+            .lines
+            .last()
+            .is_some_and(|line| line.value.trim().is_empty())
+        {
+            lrc.lines.pop();
+        }
+        let leading = lrc
+            .lines
+            .iter()
+            .take_while(|line| line.value.trim().is_empty())
+            .count();
+        lrc.lines.drain(..leading);
+    }
+    lrc
+}
+
+/// the text stored for lyrics, LRC if they are synced
+fn format(lines: &[Line]) -> String {
+    lines
+        .iter()
+        .map(|line| match line.start {
+            Some(start) => format!(
+                "[{:02}:{:02}.{:03}]{}",
+                start / 60_000,
+                start / 1000 % 60,
+                start % 1000,
+                line.value
+            ),
+            None => line.value.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// ID3 uses three letters, with "XXX" (or garbage) for unknown
+fn language(lang: &str) -> String {
+    let lang = lang.trim().to_lowercase();
+    if lang.len() == 3 && lang.chars().all(|c| c.is_ascii_lowercase()) {
+        lang
+    } else {
+        UNKNOWN_LANGUAGE.to_string()
+    }
+}
+
+fn found(lang: String, text: &str) -> Option<lyrics::ActiveModel> {
+    let lrc = parse(text);
+    if lrc.lines.is_empty() {
+        return None;
+    }
+    let synced = lrc.synced();
+    Some(lyrics::ActiveModel {
+        lang: AV::Set(lang),
+        synced: AV::Set(synced),
+        text: AV::Set(format(&lrc.lines)),
+        display_artist: AV::Set(lrc.artist),
+        display_title: AV::Set(lrc.title),
+        ..Default::default()
+    })
+}
+
+/// everything there is for a song, at most one per language, synced preferred
+pub(super) fn extract(path: &Utf8Path, tag: Option<&Tag>) -> Vec<lyrics::ActiveModel> {
+    // most preferred first
+    let mut candidates = vec![];
+
+    let lrc = path.with_extension("lrc");
+    match std::fs::read(&lrc) {
+        Ok(data) => candidates.extend(found(
+            UNKNOWN_LANGUAGE.to_string(),
+            &String::from_utf8_lossy(&data),
+        )),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => warn!("reading {lrc}: {e}"),
+    }
+
+    match tag {
+        Some(Tag::Id3(tag)) => {
+            for sylt in tag.synchronised_lyrics() {
+                // MPEG frames would need the frame rate, which isn't worth it
+                if sylt.timestamp_format != TimestampFormat::Ms
+                    || !matches!(
+                        sylt.content_type,
+                        SynchronisedLyricsType::Lyrics | SynchronisedLyricsType::Transcription
+                    )
+                {
+                    continue;
+                }
+                let lines: Vec<_> = sylt
+                    .content
+                    .iter()
+                    .map(|(start, value)| Line {
+                        start: Some(*start),
+                        value: value.trim().to_string(),
+                    })
+                    .collect();
+                if !lines.is_empty() {
+                    candidates.extend(found(language(&sylt.lang), &format(&lines)));
+                }
+            }
+            for uslt in tag.lyrics() {
+                candidates.extend(found(language(&uslt.lang), &uslt.text));
+            }
+        }
+        Some(Tag::Ffprobe(tag)) => {
+            if let Some(text) = &tag.lyrics {
+                candidates.extend(found(UNKNOWN_LANGUAGE.to_string(), text));
+            }
+        }
+        None => {}
+    }
+
+    let mut lyrics: Vec<lyrics::ActiveModel> = vec![];
+    for candidate in candidates {
+        let existing = lyrics
+            .iter_mut()
+            .find(|lyrics| lyrics.lang.as_ref() == candidate.lang.as_ref());
+        match existing {
+            None => lyrics.push(candidate),
+            Some(existing) if !existing.synced.as_ref() && *candidate.synced.as_ref() => {
+                *existing = candidate;
+            }
+            Some(_) => {}
+        }
+    }
+    lyrics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(start: Option<u32>, value: &str) -> Line {
+        Line {
+            start,
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(parse_timestamp("01:02"), Some(62_000));
+        assert_eq!(parse_timestamp("01:02.3"), Some(62_300));
+        assert_eq!(parse_timestamp("01:02.34"), Some(62_340));
+        assert_eq!(parse_timestamp("01:02.345"), Some(62_345));
+        assert_eq!(parse_timestamp("01:02:34"), Some(62_340));
+        assert_eq!(parse_timestamp("01:02.3456"), None);
+        assert_eq!(parse_timestamp("Chorus"), None);
+    }
+
+    #[test]
+    fn repeated_lines() {
+        let lrc = parse("[00:05.00][00:01.00]again\n[00:03.00]once");
+        assert_eq!(
+            lrc.lines,
+            [
+                line(Some(1000), "again"),
+                line(Some(3000), "once"),
+                line(Some(5000), "again"),
+            ]
+        );
+    }
+
+    #[test]
+    fn offset() {
+        let lrc = parse("[offset:500]\n[00:00.20]first\n[00:01.00]second");
+        assert_eq!(
+            lrc.lines,
+            [line(Some(0), "first"), line(Some(500), "second")]
+        );
+
+        let lrc = parse("[offset:-500]\n[00:01.00]later");
+        assert_eq!(lrc.lines, [line(Some(1500), "later")]);
+    }
+
+    #[test]
+    fn metadata() {
+        let lrc = parse("[ar:Someone]\n[ti:Something]\n[by:me]\n[00:01.00]sung");
+        assert_eq!(lrc.artist.as_deref(), Some("Someone"));
+        assert_eq!(lrc.title.as_deref(), Some("Something"));
+        assert_eq!(lrc.lines, [line(Some(1000), "sung")]);
+    }
+
+    #[test]
+    fn bracketed_text() {
+        let lrc = parse("[Chorus]\nla la\n\n");
+        assert!(!lrc.synced());
+        assert_eq!(lrc.lines, [line(None, "[Chorus]"), line(None, "la la")]);
+
+        let lrc = parse("[00:01.00][Chorus] la la");
+        assert_eq!(lrc.lines, [line(Some(1000), "[Chorus] la la")]);
+    }
+
+    #[test]
+    fn unsynced_text_without_metadata() {
+        let lyrics = found(
+            UNKNOWN_LANGUAGE.to_string(),
+            "[ar:Someone]\n[ti:Something]\n\nfirst\nsecond\n",
+        )
+        .unwrap();
+        assert_eq!(lyrics.synced.as_ref(), &false);
+        assert_eq!(lyrics.text.as_ref(), "first\nsecond");
+        assert_eq!(lyrics.display_artist.as_ref().as_deref(), Some("Someone"));
+    }
+}
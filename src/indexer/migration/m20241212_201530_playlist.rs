@@ -0,0 +1,90 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::entity::{playlist::Playlist, song::Song, user::User};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Playlist::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Playlist::Id))
+                    .col(string(Playlist::Name))
+                    .col(string_null(Playlist::Comment))
+                    .col(integer(Playlist::Owner))
+                    .col(boolean(Playlist::Public).default(false))
+                    .col(big_integer(Playlist::Created))
+                    .col(big_integer(Playlist::Changed))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Playlist::Table, Playlist::Owner)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(PlaylistEntry::Table)
+                    .if_not_exists()
+                    .col(pk_auto(PlaylistEntry::Id))
+                    .col(integer(PlaylistEntry::Playlist))
+                    .col(integer(PlaylistEntry::Song))
+                    .col(integer(PlaylistEntry::Position))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PlaylistEntry::Table, PlaylistEntry::Playlist)
+                            .to(Playlist::Table, Playlist::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PlaylistEntry::Table, PlaylistEntry::Song)
+                            .to(Song::Table, Song::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-playlist_entry-playlist-position")
+                    .table(PlaylistEntry::Table)
+                    .col(PlaylistEntry::Playlist)
+                    .col(PlaylistEntry::Position)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PlaylistEntry::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Playlist::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PlaylistEntry {
+    Table,
+    Id,
+    Playlist,
+    Song,
+    Position,
+}
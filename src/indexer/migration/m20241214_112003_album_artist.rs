@@ -0,0 +1,123 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::entity::{album::Album, artist::Artist, song::Song};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Artist::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Artist::Id))
+                    .col(string_uniq(Artist::Name))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Album::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Album::Id))
+                    .col(string(Album::Name))
+                    .col(integer_null(Album::ArtistId))
+                    .col(string_null(Album::Artist))
+                    .col(string(Album::Directory))
+                    .col(integer_null(Album::Year))
+                    .col(string_null(Album::Genre))
+                    .col(integer_null(Album::CoverArt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Album::Table, Album::ArtistId)
+                            .to(Artist::Table, Artist::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Song::Table)
+                    .add_column(string_null(Song::AlbumArtist))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Song::Table)
+                    .add_column(integer_null(Song::Disc))
+                    .to_owned(),
+            )
+            .await?;
+
+        // sea-query can't add foreign keys to an existing table on SQLite, but
+        // SQLite itself is fine with it as long as the column defaults to NULL
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "ALTER TABLE song ADD COLUMN album_id INTEGER REFERENCES album(id) ON DELETE SET NULL ON UPDATE CASCADE",
+        )
+        .await?;
+        db.execute_unprepared(
+            "ALTER TABLE song ADD COLUMN artist_id INTEGER REFERENCES artist(id) ON DELETE SET NULL ON UPDATE CASCADE",
+        )
+        .await?;
+
+        for (name, col) in [
+            ("idx-song-album_id", Song::AlbumId),
+            ("idx-song-artist_id", Song::ArtistId),
+        ] {
+            manager
+                .create_index(
+                    Index::create()
+                        .name(name)
+                        .table(Song::Table)
+                        .col(col)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-album-artist_id")
+                    .table(Album::Table)
+                    .col(Album::ArtistId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for name in ["idx-song-album_id", "idx-song-artist_id"] {
+            manager
+                .drop_index(Index::drop().name(name).table(Song::Table).to_owned())
+                .await?;
+        }
+        for col in [Song::AlbumArtist, Song::Disc, Song::AlbumId, Song::ArtistId] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Song::Table)
+                        .drop_column(col)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        manager
+            .drop_table(Table::drop().table(Album::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Artist::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,67 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::entity::{directory::Directory, song::Song};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Directory::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Directory::Id))
+                    .col(string_uniq(Directory::Path))
+                    .col(string(Directory::Name))
+                    .col(integer_null(Directory::Parent))
+                    .col(big_integer(Directory::Modified))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Directory::Table, Directory::Parent)
+                            .to(Directory::Table, Directory::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-directory-parent")
+                    .table(Directory::Table)
+                    .col(Directory::Parent)
+                    .to_owned(),
+            )
+            .await?;
+
+        // `song.parent` holds the (prefixed) directory ID
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-song-parent")
+                    .table(Song::Table)
+                    .col(Song::Parent)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-song-parent")
+                    .table(Song::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Directory::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,127 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::entity::{album::Album, play_history::PlayHistory, song::Song, user::User};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PlayHistory::Table)
+                    .if_not_exists()
+                    .col(pk_auto(PlayHistory::Id))
+                    .col(integer(PlayHistory::User))
+                    .col(integer(PlayHistory::Song))
+                    .col(big_integer(PlayHistory::Time))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PlayHistory::Table, PlayHistory::User)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PlayHistory::Table, PlayHistory::Song)
+                            .to(Song::Table, Song::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-play_history-user-time")
+                    .table(PlayHistory::Table)
+                    .col(PlayHistory::User)
+                    .col(PlayHistory::Time)
+                    .to_owned(),
+            )
+            .await?;
+
+        for (table, play_count, played) in play_columns() {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table.clone())
+                        .add_column(integer(play_count).default(0))
+                        .to_owned(),
+                )
+                .await?;
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .add_column(big_integer_null(played))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // for the `frequent` and `recent` album lists
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-album-play_count")
+                    .table(Album::Table)
+                    .col(Album::PlayCount)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-album-played")
+                    .table(Album::Table)
+                    .col(Album::Played)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for name in ["idx-album-play_count", "idx-album-played"] {
+            manager
+                .drop_index(Index::drop().name(name).table(Album::Table).to_owned())
+                .await?;
+        }
+        for (table, play_count, played) in play_columns() {
+            for col in [play_count, played] {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(table.clone())
+                            .drop_column(col)
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+        manager
+            .drop_table(Table::drop().table(PlayHistory::Table).to_owned())
+            .await
+    }
+}
+
+// (table, play count, last played) of songs and albums
+fn play_columns() -> [(DynIden, DynIden, DynIden); 2] {
+    [
+        (
+            Song::Table.into_iden(),
+            Song::PlayCount.into_iden(),
+            Song::Played.into_iden(),
+        ),
+        (
+            Album::Table.into_iden(),
+            Album::PlayCount.into_iden(),
+            Album::Played.into_iden(),
+        ),
+    ]
+}
@@ -0,0 +1,96 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::entity::{
+    album::Album, annotation::Annotation, artist::Artist, directory::Directory, song::Song,
+    user::User,
+};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut table = Table::create()
+            .table(Annotation::Table)
+            .if_not_exists()
+            .col(pk_auto(Annotation::Id))
+            .col(integer(Annotation::User))
+            .col(integer_null(Annotation::Song))
+            .col(integer_null(Annotation::Album))
+            .col(integer_null(Annotation::Artist))
+            .col(integer_null(Annotation::Directory))
+            .col(big_integer_null(Annotation::Starred))
+            .col(integer_null(Annotation::Rating))
+            .foreign_key(
+                ForeignKey::create()
+                    .from(Annotation::Table, Annotation::User)
+                    .to(User::Table, User::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade),
+            )
+            .to_owned();
+        for (col, target, target_id) in items() {
+            table.foreign_key(
+                ForeignKey::create()
+                    .from(Annotation::Table, col)
+                    .to(target, target_id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade),
+            );
+        }
+        manager.create_table(table).await?;
+
+        // one annotation per user and item (NULLs don't collide)
+        for (col, _, _) in items() {
+            manager
+                .create_index(
+                    Index::create()
+                        .name(index_name(&col))
+                        .table(Annotation::Table)
+                        .col(Annotation::User)
+                        .col(col)
+                        .unique()
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Annotation::Table).to_owned())
+            .await
+    }
+}
+
+// (annotation column, referenced table, referenced column)
+fn items() -> [(DynIden, DynIden, DynIden); 4] {
+    [
+        (
+            Annotation::Song.into_iden(),
+            Song::Table.into_iden(),
+            Song::Id.into_iden(),
+        ),
+        (
+            Annotation::Album.into_iden(),
+            Album::Table.into_iden(),
+            Album::Id.into_iden(),
+        ),
+        (
+            Annotation::Artist.into_iden(),
+            Artist::Table.into_iden(),
+            Artist::Id.into_iden(),
+        ),
+        (
+            Annotation::Directory.into_iden(),
+            Directory::Table.into_iden(),
+            Directory::Id.into_iden(),
+        ),
+    ]
+}
+
+fn index_name(col: &DynIden) -> String {
+    format!("idx-annotation-user-{}", col.to_string())
+}
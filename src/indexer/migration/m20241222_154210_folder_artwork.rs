@@ -0,0 +1,106 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::entity::{artist::Artist, cover_art::CoverArt, song::Song};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// SQLite can't relax a NOT NULL constraint in place, so the table is rebuilt
+async fn rebuild_cover_art(manager: &SchemaManager<'_>, folder: bool) -> Result<(), DbErr> {
+    let old = Alias::new("cover_art_old");
+    manager
+        .rename_table(
+            Table::rename()
+                .table(CoverArt::Table, old.clone())
+                .to_owned(),
+        )
+        .await?;
+
+    let mut table = Table::create();
+    table
+        .table(CoverArt::Table)
+        .col(pk_auto(CoverArt::Id))
+        .col(integer(CoverArt::Shard))
+        .col(string(CoverArt::MimeType));
+    if folder {
+        table
+            .col(integer_null(CoverArt::Song))
+            .col(string_null(CoverArt::Path).unique_key());
+    } else {
+        table.col(integer(CoverArt::Song));
+    }
+    table.foreign_key(
+        ForeignKey::create()
+            .from(CoverArt::Table, CoverArt::Song)
+            .to(Song::Table, Song::Id)
+            .on_delete(ForeignKeyAction::Cascade)
+            .on_update(ForeignKeyAction::Cascade),
+    );
+    manager.create_table(table.to_owned()).await?;
+
+    let columns = || {
+        [
+            CoverArt::Id,
+            CoverArt::Shard,
+            CoverArt::MimeType,
+            CoverArt::Song,
+        ]
+    };
+    let mut rows = Query::select();
+    rows.columns(columns()).from(old.clone());
+    if !folder {
+        // folder art has no song to hang on to
+        rows.and_where(Expr::col(CoverArt::Song).is_not_null());
+    }
+    let copy = Query::insert()
+        .into_table(CoverArt::Table)
+        .columns(columns())
+        .select_from(rows)
+        .map_err(|e| DbErr::Migration(e.to_string()))?
+        .to_owned();
+    manager.exec_stmt(copy).await?;
+
+    manager
+        .drop_table(Table::drop().table(old).to_owned())
+        .await
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        rebuild_cover_art(manager, true).await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-cover_art-song")
+                    .table(CoverArt::Table)
+                    .col(CoverArt::Song)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Artist::Table)
+                    .add_column(integer_null(Artist::CoverArt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Artist::Table)
+                    .drop_column(Artist::CoverArt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // idx-cover_art-song goes along with the old table
+        rebuild_cover_art(manager, false).await
+    }
+}
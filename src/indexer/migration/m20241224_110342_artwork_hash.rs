@@ -0,0 +1,51 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::entity::cover_art::CoverArt;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // existing files are hashed and moved by the garbage collector, which
+        // knows where the data lives
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CoverArt::Table)
+                    .add_column(string_null(CoverArt::Hash))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-cover_art-hash")
+                    .table(CoverArt::Table)
+                    .col(CoverArt::Hash)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-cover_art-hash")
+                    .table(CoverArt::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CoverArt::Table)
+                    .drop_column(CoverArt::Hash)
+                    .to_owned(),
+            )
+            .await
+    }
+}
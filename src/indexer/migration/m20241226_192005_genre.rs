@@ -0,0 +1,79 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::entity::{genre::Genre, song::Song, song_genre::SongGenre};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Genre::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Genre::Id))
+                    // "Rock" and "rock" are the same genre
+                    .col(string_uniq(Genre::Name).extra("COLLATE NOCASE"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SongGenre::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SongGenre::Id))
+                    .col(integer(SongGenre::Song))
+                    .col(integer(SongGenre::Genre))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(SongGenre::Table, SongGenre::Song)
+                            .to(Song::Table, Song::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(SongGenre::Table, SongGenre::Genre)
+                            .to(Genre::Table, Genre::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-song_genre-song-genre")
+                    .table(SongGenre::Table)
+                    .col(SongGenre::Song)
+                    .col(SongGenre::Genre)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-song_genre-genre")
+                    .table(SongGenre::Table)
+                    .col(SongGenre::Genre)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SongGenre::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Genre::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,70 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::entity::{lyrics::Lyrics, song::Song};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Lyrics::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Lyrics::Id))
+                    .col(integer(Lyrics::Song))
+                    .col(string(Lyrics::Lang))
+                    .col(boolean(Lyrics::Synced))
+                    .col(text(Lyrics::Text))
+                    .col(string_null(Lyrics::DisplayArtist))
+                    .col(string_null(Lyrics::DisplayTitle))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Lyrics::Table, Lyrics::Song)
+                            .to(Song::Table, Song::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-lyrics-song-lang")
+                    .table(Lyrics::Table)
+                    .col(Lyrics::Song)
+                    .col(Lyrics::Lang)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // 0 for every existing song, so their tags are read again
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Song::Table)
+                    .add_column(integer(Song::TagVersion).default(0))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Song::Table)
+                    .drop_column(Song::TagVersion)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Lyrics::Table).to_owned())
+            .await
+    }
+}
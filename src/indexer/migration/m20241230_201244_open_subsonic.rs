@@ -0,0 +1,107 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::entity::{artist::Artist, song::Song, song_artist::SongArtist};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only adds one column at a time
+        for column in [
+            integer_null(Song::BitRate),
+            integer_null(Song::SamplingRate),
+            integer_null(Song::ChannelCount),
+            double_null(Song::TrackGain),
+            double_null(Song::TrackPeak),
+            double_null(Song::AlbumGain),
+            double_null(Song::AlbumPeak),
+            string_null(Song::MusicBrainzId),
+            string_null(Song::SortName),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Song::Table)
+                        .add_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SongArtist::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SongArtist::Id))
+                    .col(integer(SongArtist::Song))
+                    .col(integer(SongArtist::Artist))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(SongArtist::Table, SongArtist::Song)
+                            .to(Song::Table, Song::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(SongArtist::Table, SongArtist::Artist)
+                            .to(Artist::Table, Artist::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-song_artist-song-artist")
+                    .table(SongArtist::Table)
+                    .col(SongArtist::Song)
+                    .col(SongArtist::Artist)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-song_artist-artist")
+                    .table(SongArtist::Table)
+                    .col(SongArtist::Artist)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SongArtist::Table).to_owned())
+            .await?;
+        for column in [
+            Song::BitRate,
+            Song::SamplingRate,
+            Song::ChannelCount,
+            Song::TrackGain,
+            Song::TrackPeak,
+            Song::AlbumGain,
+            Song::AlbumPeak,
+            Song::MusicBrainzId,
+            Song::SortName,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Song::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
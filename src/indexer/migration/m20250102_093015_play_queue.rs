@@ -0,0 +1,82 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::entity::{
+    play_queue::PlayQueue, play_queue_entry::PlayQueueEntry, song::Song, user::User,
+};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PlayQueue::Table)
+                    .if_not_exists()
+                    .col(pk_auto(PlayQueue::Id))
+                    .col(integer_uniq(PlayQueue::User))
+                    .col(integer_null(PlayQueue::Current))
+                    .col(big_integer(PlayQueue::Position).default(0))
+                    .col(big_integer(PlayQueue::Changed))
+                    .col(string(PlayQueue::ChangedBy))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PlayQueue::Table, PlayQueue::User)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(PlayQueueEntry::Table)
+                    .if_not_exists()
+                    .col(pk_auto(PlayQueueEntry::Id))
+                    .col(integer(PlayQueueEntry::PlayQueue))
+                    .col(integer(PlayQueueEntry::Song))
+                    .col(integer(PlayQueueEntry::Position))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PlayQueueEntry::Table, PlayQueueEntry::PlayQueue)
+                            .to(PlayQueue::Table, PlayQueue::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PlayQueueEntry::Table, PlayQueueEntry::Song)
+                            .to(Song::Table, Song::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-play_queue_entry-play_queue-position")
+                    .table(PlayQueueEntry::Table)
+                    .col(PlayQueueEntry::PlayQueue)
+                    .col(PlayQueueEntry::Position)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PlayQueueEntry::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(PlayQueue::Table).to_owned())
+            .await
+    }
+}
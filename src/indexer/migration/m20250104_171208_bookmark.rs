@@ -0,0 +1,59 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::entity::{bookmark::Bookmark, song::Song, user::User};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Bookmark::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Bookmark::Id))
+                    .col(integer(Bookmark::User))
+                    .col(integer(Bookmark::Song))
+                    .col(big_integer(Bookmark::Position).default(0))
+                    .col(string_null(Bookmark::Comment))
+                    .col(big_integer(Bookmark::Created))
+                    .col(big_integer(Bookmark::Changed))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Bookmark::Table, Bookmark::User)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Bookmark::Table, Bookmark::Song)
+                            .to(Song::Table, Song::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-bookmark-user-song")
+                    .table(Bookmark::Table)
+                    .col(Bookmark::User)
+                    .col(Bookmark::Song)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Bookmark::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,56 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::entity::{internet_radio_station::InternetRadioStation, user::User};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(InternetRadioStation::Table)
+                    .if_not_exists()
+                    .col(pk_auto(InternetRadioStation::Id))
+                    .col(string(InternetRadioStation::Name))
+                    .col(string(InternetRadioStation::StreamUrl))
+                    .col(string_null(InternetRadioStation::HomepageUrl))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(boolean(User::Admin).default(false))
+                    .to_owned(),
+            )
+            .await?;
+        // until now, everybody could do everything
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(User::Table)
+                    .value(User::Admin, true)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::Admin)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(InternetRadioStation::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,81 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::entity::{share::Share, share_entry::ShareEntry, song::Song, user::User};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Share::Table)
+                    .if_not_exists()
+                    .col(string(Share::Id).primary_key())
+                    .col(integer(Share::User))
+                    .col(string_null(Share::Description))
+                    .col(big_integer(Share::Created))
+                    .col(big_integer_null(Share::Expires))
+                    .col(big_integer_null(Share::LastVisited))
+                    .col(integer(Share::VisitCount).default(0))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Share::Table, Share::User)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ShareEntry::Table)
+                    .if_not_exists()
+                    .col(pk_auto(ShareEntry::Id))
+                    .col(string(ShareEntry::Share))
+                    .col(integer(ShareEntry::Song))
+                    .col(integer(ShareEntry::Position))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(ShareEntry::Table, ShareEntry::Share)
+                            .to(Share::Table, Share::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(ShareEntry::Table, ShareEntry::Song)
+                            .to(Song::Table, Song::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-share_entry-share-position")
+                    .table(ShareEntry::Table)
+                    .col(ShareEntry::Share)
+                    .col(ShareEntry::Position)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ShareEntry::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Share::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,59 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::entity::user::User;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// everyone so far could do everything, and keeps it that way
+const ROLES: [User; 9] = [
+    User::Settings,
+    User::Download,
+    User::Upload,
+    User::Playlist,
+    User::CoverArt,
+    User::Comment,
+    User::Share,
+    User::Jukebox,
+    User::Stream,
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(string_null(User::Email))
+                    .to_owned(),
+            )
+            .await?;
+        // SQLite only adds one column at a time
+        for role in ROLES {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(User::Table)
+                        .add_column(boolean(role).default(true))
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in ROLES.into_iter().chain([User::Email]) {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(User::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
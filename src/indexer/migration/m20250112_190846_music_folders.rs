@@ -0,0 +1,105 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::entity::{
+    music_folder::MusicFolder, song::Song, user::User, user_music_folder::UserMusicFolder,
+};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MusicFolder::Table)
+                    .if_not_exists()
+                    .col(pk_auto(MusicFolder::Id))
+                    .col(string_uniq(MusicFolder::Path))
+                    .col(string(MusicFolder::Name))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserMusicFolder::Table)
+                    .if_not_exists()
+                    .col(pk_auto(UserMusicFolder::Id))
+                    .col(integer(UserMusicFolder::User))
+                    .col(integer(UserMusicFolder::Folder))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(UserMusicFolder::Table, UserMusicFolder::User)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(UserMusicFolder::Table, UserMusicFolder::Folder)
+                            .to(MusicFolder::Table, MusicFolder::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-user_music_folder-user-folder")
+                    .table(UserMusicFolder::Table)
+                    .col(UserMusicFolder::User)
+                    .col(UserMusicFolder::Folder)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // see `m20241214_112003_album_artist`. Existing songs get their folder
+        // once the media paths are known, at startup
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "ALTER TABLE song ADD COLUMN folder INTEGER REFERENCES music_folder(id) ON DELETE SET NULL ON UPDATE CASCADE",
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-song-folder")
+                    .table(Song::Table)
+                    .col(Song::Folder)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-song-folder")
+                    .table(Song::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Song::Table)
+                    .drop_column(Song::Folder)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(UserMusicFolder::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(MusicFolder::Table).to_owned())
+            .await
+    }
+}
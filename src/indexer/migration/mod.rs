@@ -2,6 +2,23 @@ pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
 mod m20241203_162956_cover_art;
+mod m20241210_184512_user;
+mod m20241212_201530_playlist;
+mod m20241214_112003_album_artist;
+mod m20241216_093710_directory;
+mod m20241218_204105_play_history;
+mod m20241220_173342_annotation;
+mod m20241222_154210_folder_artwork;
+mod m20241224_110342_artwork_hash;
+mod m20241226_192005_genre;
+mod m20241228_140517_lyrics;
+mod m20241230_201244_open_subsonic;
+mod m20250102_093015_play_queue;
+mod m20250104_171208_bookmark;
+mod m20250106_084511_internet_radio;
+mod m20250108_201733_share;
+mod m20250110_143027_user_roles;
+mod m20250112_190846_music_folders;
 
 pub struct Migrator;
 
@@ -11,6 +28,23 @@ impl MigratorTrait for Migrator {
         vec![
             Box::new(m20220101_000001_create_table::Migration),
             Box::new(m20241203_162956_cover_art::Migration),
+            Box::new(m20241210_184512_user::Migration),
+            Box::new(m20241212_201530_playlist::Migration),
+            Box::new(m20241214_112003_album_artist::Migration),
+            Box::new(m20241216_093710_directory::Migration),
+            Box::new(m20241218_204105_play_history::Migration),
+            Box::new(m20241220_173342_annotation::Migration),
+            Box::new(m20241222_154210_folder_artwork::Migration),
+            Box::new(m20241224_110342_artwork_hash::Migration),
+            Box::new(m20241226_192005_genre::Migration),
+            Box::new(m20241228_140517_lyrics::Migration),
+            Box::new(m20241230_201244_open_subsonic::Migration),
+            Box::new(m20250102_093015_play_queue::Migration),
+            Box::new(m20250104_171208_bookmark::Migration),
+            Box::new(m20250106_084511_internet_radio::Migration),
+            Box::new(m20250108_201733_share::Migration),
+            Box::new(m20250110_143027_user_roles::Migration),
+            Box::new(m20250112_190846_music_folders::Migration),
         ]
     }
 }
@@ -1,27 +1,37 @@
-use std::{collections::HashSet, num::NonZero, sync::Arc, time::Duration};
+use std::{
+    collections::HashSet,
+    num::NonZero,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
 
+use audio::Properties;
 use camino::{Utf8Path, Utf8PathBuf};
 use db::DB;
-use ffprobe::{metadata, Tag as FFProbeTag};
+use ffprobe::{leading_number, metadata, Tag as FFProbeTag};
 use filesize::PathExt;
+use folder_art::FolderArt;
 use id3::{frame::Picture, Tag as Id3Tag, TagLike};
 use mime_guess::{
     mime::{AUDIO, MPEG},
     Mime,
 };
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use sea_orm::{ActiveValue as AV, EntityTrait};
+use sea_orm::{sea_query::OnConflict, ActiveValue as AV, EntityTrait};
 use tokio::{
     spawn,
     sync::mpsc::{self, Sender},
 };
-use tracing::{debug, error, info, trace, warn};
+use tracing::{error, info, trace, warn};
 
 use crate::{
-    config::{Config, Indexer as IndexerConfig},
-    entity::{cover_art, song},
+    config::{ArtworkSource, Config, Indexer as IndexerConfig},
+    entity::{self, cover_art, directory, music_folder, song},
     load,
-    util::{Pwn, Unpwn},
+    util::Pwn,
     FileVisitor,
 };
 
@@ -33,9 +43,35 @@ pub(crate) mod types;
 
 mod ffprobe;
 
+mod folder_art;
+
+mod gc;
+
+mod genre;
+
+mod audio;
+
+pub(crate) mod lyrics;
+
+/// bumped whenever more is read from tags, or songs are grouped into albums
+/// differently, so songs indexed before are read again
+const TAG_VERSION: i32 = 4;
+
+// `CD1`, `Disc 2`, `disk_03`, ...
+fn is_disc_dir(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ["cd", "disc", "disk"].iter().any(|prefix| {
+        name.strip_prefix(prefix).is_some_and(|rest| {
+            rest.trim_start_matches([' ', '_', '-', '.'])
+                .starts_with(|c: char| c.is_ascii_digit())
+        })
+    })
+}
+
 #[derive(Clone)]
 struct Visitor {
     tx: Sender<Utf8PathBuf>,
+    status: Arc<ScanStatus>,
 }
 
 impl FileVisitor for Visitor {
@@ -44,6 +80,7 @@ impl FileVisitor for Visitor {
         entry: impl AsRef<Utf8Path>,
     ) -> impl std::future::Future<Output = ()> + Send {
         let entry: Utf8PathBuf = entry.as_ref().to_owned();
+        *self.status.folder.lock().unwrap() = entry.parent().map(Utf8Path::to_owned);
         async {
             if let Err(e) = self.tx.send(entry).await {
                 error!("queue error: {e:?}")
@@ -55,7 +92,8 @@ impl FileVisitor for Visitor {
 
 #[derive(Debug, Clone)]
 enum Tag {
-    Ffprobe(FFProbeTag),
+    // boxed, it's a lot bigger than the ID3 tag
+    Ffprobe(Box<FFProbeTag>),
     Id3(Id3Tag),
 }
 
@@ -67,11 +105,39 @@ impl Tag {
         }
     }
 
-    fn artist(&self) -> Option<&str> {
+    // for display; ID3v2.4 separates multiple values with NUL
+    fn artist(&self) -> Option<String> {
         match self {
-            Tag::Ffprobe(tag) => tag.artist.unpwn(),
-            Tag::Id3(tag) => tag.artist(),
+            Tag::Ffprobe(tag) => tag.artist.clone(),
+            Tag::Id3(tag) => tag.artists().map(|artists| artists.join(", ")),
+        }
+    }
+
+    // each on its own, as opposed to `artist`
+    fn artists(&self) -> Vec<String> {
+        let artists: Vec<&str> = match self {
+            // ffprobe joins repeated Vorbis comments with ";"
+            Tag::Ffprobe(tag) => tag
+                .artists
+                .as_deref()
+                .or(tag.artist.as_deref())
+                .map(|artists| artists.split(';').collect())
+                .unwrap_or_default(),
+            // Picard puts them into TXXX:ARTISTS when TPE1 is "A feat. B"
+            Tag::Id3(tag) => tag
+                .extended_texts()
+                .find(|text| text.description.eq_ignore_ascii_case("ARTISTS"))
+                .map(|text| text.value.split('\0').collect())
+                .or_else(|| tag.artists())
+                .unwrap_or_default(),
+        };
+        let mut unique: Vec<String> = vec![];
+        for artist in artists.into_iter().map(str::trim).filter(|a| !a.is_empty()) {
+            if !unique.iter().any(|a| a == artist) {
+                unique.push(artist.to_string());
+            }
         }
+        unique
     }
 
     fn album(&self) -> Option<&str> {
@@ -80,12 +146,121 @@ impl Tag {
             Tag::Id3(tag) => tag.album(),
         }
     }
+
+    fn album_artist(&self) -> Option<&str> {
+        match self {
+            Tag::Ffprobe(tag) => tag.album_artist.as_deref(),
+            Tag::Id3(tag) => tag.album_artist(),
+        }
+    }
+
+    fn track(&self) -> Option<u32> {
+        match self {
+            Tag::Ffprobe(tag) => tag.track.as_deref().and_then(leading_number),
+            Tag::Id3(tag) => tag.track(),
+        }
+    }
+
+    fn disc(&self) -> Option<u32> {
+        match self {
+            Tag::Ffprobe(tag) => tag.disc.as_deref().and_then(leading_number),
+            Tag::Id3(tag) => tag.disc(),
+        }
+    }
+
+    fn year(&self) -> Option<u32> {
+        match self {
+            Tag::Ffprobe(tag) => tag.date.as_deref().and_then(leading_number),
+            // TYER (v2.3) or TDRC (v2.4)
+            Tag::Id3(tag) => tag
+                .year()
+                .or_else(|| tag.date_recorded().map(|ts| ts.year))
+                .and_then(|year| year.try_into().ok()),
+        }
+    }
+
+    fn sort_name(&self) -> Option<&str> {
+        match self {
+            Tag::Ffprobe(tag) => tag.titlesort.as_deref(),
+            Tag::Id3(tag) => tag.text_for_frame_id("TSOT"),
+        }
+    }
+
+    // of the recording, which is what a song is to MusicBrainz
+    fn music_brainz_id(&self) -> Option<String> {
+        match self {
+            Tag::Ffprobe(tag) => tag.musicbrainz_trackid.clone(),
+            Tag::Id3(tag) => tag
+                .unique_file_identifiers()
+                .find(|ufid| ufid.owner_identifier == "http://musicbrainz.org")
+                .and_then(|ufid| String::from_utf8(ufid.identifier.clone()).ok()),
+        }
+    }
+
+    fn replay_gain(&self) -> ReplayGain {
+        // "-6.50 dB"
+        fn parse(value: Option<&str>) -> Option<f64> {
+            let value = value?.trim();
+            let number = value
+                .strip_suffix("dB")
+                .or_else(|| value.strip_suffix("db"))
+                .unwrap_or(value);
+            number.trim().parse().ok().filter(|n: &f64| n.is_finite())
+        }
+
+        let value = |name: &str| -> Option<&str> {
+            match self {
+                Tag::Ffprobe(tag) => match name {
+                    "REPLAYGAIN_TRACK_GAIN" => tag.replaygain_track_gain.as_deref(),
+                    "REPLAYGAIN_TRACK_PEAK" => tag.replaygain_track_peak.as_deref(),
+                    "REPLAYGAIN_ALBUM_GAIN" => tag.replaygain_album_gain.as_deref(),
+                    "REPLAYGAIN_ALBUM_PEAK" => tag.replaygain_album_peak.as_deref(),
+                    _ => None,
+                },
+                Tag::Id3(tag) => tag
+                    .extended_texts()
+                    .find(|text| text.description.eq_ignore_ascii_case(name))
+                    .map(|text| text.value.as_str()),
+            }
+        };
+        ReplayGain {
+            track_gain: parse(value("REPLAYGAIN_TRACK_GAIN")),
+            track_peak: parse(value("REPLAYGAIN_TRACK_PEAK")),
+            album_gain: parse(value("REPLAYGAIN_ALBUM_GAIN")),
+            album_peak: parse(value("REPLAYGAIN_ALBUM_PEAK")),
+        }
+    }
+
+    // with ID3v1 references resolved, but not split yet
+    fn genres(&self) -> Vec<String> {
+        match self {
+            Tag::Ffprobe(tag) => tag.genre.iter().cloned().collect(),
+            // TCON can hold several values in v2.4
+            Tag::Id3(tag) => tag
+                .genres()
+                .unwrap_or_default()
+                .into_iter()
+                .flat_map(genre::resolve_tcon)
+                .collect(),
+        }
+    }
 }
+
+#[derive(Debug, Default)]
+struct ReplayGain {
+    track_gain: Option<f64>,
+    track_peak: Option<f64>,
+    album_gain: Option<f64>,
+    album_peak: Option<f64>,
+}
+
 #[derive(Debug)]
 struct IndexerResult {
     path: Utf8PathBuf,
     tag: Option<Tag>,
     mime_type: Option<Mime>,
+    lyrics: Vec<entity::lyrics::ActiveModel>,
+    audio: Properties,
 }
 
 impl IndexerResult {
@@ -97,14 +272,70 @@ impl IndexerResult {
             .unwrap_or(self.path.file_name().expect("not a file?"))
     }
 
-    fn artist(&self) -> Option<&str> {
-        self.tag.as_ref().map(|t| t.artist()).flatten()
+    fn artist(&self) -> Option<String> {
+        self.tag.as_ref().and_then(|t| t.artist())
+    }
+
+    // never empty if there is an `artist`
+    fn artists(&self) -> Vec<String> {
+        let artists = self.tag.as_ref().map(|t| t.artists()).unwrap_or_default();
+        if artists.is_empty() {
+            return self.artist().into_iter().collect();
+        }
+        artists
     }
 
     fn album(&self) -> Option<&str> {
         self.tag.as_ref().map(|t| t.album()).flatten()
     }
 
+    fn album_artist(&self) -> Option<&str> {
+        self.tag.as_ref().and_then(|t| t.album_artist())
+    }
+
+    fn track(&self) -> Option<u32> {
+        self.tag.as_ref().and_then(|t| t.track())
+    }
+
+    fn disc(&self) -> Option<u32> {
+        self.tag.as_ref().and_then(|t| t.disc())
+    }
+
+    fn year(&self) -> Option<u32> {
+        self.tag.as_ref().and_then(|t| t.year())
+    }
+
+    fn sort_name(&self) -> Option<&str> {
+        self.tag.as_ref().and_then(|t| t.sort_name())
+    }
+
+    fn music_brainz_id(&self) -> Option<String> {
+        self.tag.as_ref().and_then(|t| t.music_brainz_id())
+    }
+
+    fn replay_gain(&self) -> ReplayGain {
+        self.tag
+            .as_ref()
+            .map(|t| t.replay_gain())
+            .unwrap_or_default()
+    }
+
+    fn genres(&self, separators: &[String]) -> Vec<String> {
+        let genres = self.tag.as_ref().map(|t| t.genres()).unwrap_or_default();
+        genre::normalize(&genres, separators)
+    }
+
+    // the album's directory: the song's, or the one above a disc directory
+    fn directory(&self) -> &str {
+        let Some(dir) = self.path.parent() else {
+            return "";
+        };
+        match dir.parent() {
+            Some(parent) if dir.file_name().is_some_and(is_disc_dir) => parent.as_str(),
+            _ => dir.as_str(),
+        }
+    }
+
     fn size(&self) -> Option<u64> {
         self.path.as_std_path().size_on_disk().ok()
     }
@@ -124,23 +355,224 @@ impl IndexerResult {
             .unwrap_or_default()
     }
 }
+// upserts the song's artists, and its album along with the album artist;
+// returns the IDs of the artists (the first one standing in for all of them),
+// album artist and album
+async fn link_artists_and_album(
+    db: &DB,
+    info: &IndexerResult,
+    genre: Option<&str>,
+) -> (Vec<i32>, Option<i32>, Option<i32>) {
+    async fn artist(db: &DB, name: Option<&str>) -> Option<i32> {
+        db.upsert_artist(name?)
+            .await
+            .inspect_err(|e| warn!("upserting artist: {e}"))
+            .ok()
+    }
+
+    let artists = info.artists();
+    let mut artist_ids = vec![];
+    for name in &artists {
+        artist_ids.extend(artist(db, Some(name)).await);
+    }
+
+    let album_artist = info.album_artist().or(artists.first().map(String::as_str));
+    let album_artist_id = match info.album_artist() {
+        Some(_) => artist(db, album_artist).await,
+        None => artist_ids.first().copied(),
+    };
+
+    let Some(album) = info.album() else {
+        return (artist_ids, album_artist_id, None);
+    };
+    match db
+        .upsert_album(
+            album,
+            album_artist_id.zip(album_artist),
+            info.directory(),
+            info.year(),
+            genre,
+            // without an album artist tag, the directory holds the album together
+            info.album_artist().is_none(),
+        )
+        .await
+    {
+        Ok((album_id, album_artist_id)) => (artist_ids, album_artist_id, Some(album_id)),
+        Err(e) => {
+            warn!("upserting album: {e}");
+            (artist_ids, album_artist_id, None)
+        }
+    }
+}
+
+// shows `cover_art` for the album, unless it has more preferred art already
+async fn offer_album_cover_art(
+    db: &DB,
+    folder_art: &FolderArt,
+    album_id: i32,
+    cover_art: &cover_art::Model,
+) {
+    if let Some(current) = db.get_album_cover_art(album_id).await {
+        if folder_art.rank(&current) <= folder_art.rank(cover_art) {
+            return;
+        }
+    }
+    db.set_album_cover_art(album_id, cover_art.id)
+        .await
+        .inspect_err(|e| warn!("album cover art: {e}"))
+        .ok();
+}
+
+// the song's own picture, read from its tags the first time around
+async fn embedded_cover_art(
+    db: &DB,
+    song_id: i32,
+    info: &IndexerResult,
+) -> Option<cover_art::Model> {
+    if let Some(existing) = db.get_cover_art_for_song(song_id).await {
+        return Some(existing);
+    }
+    let pictures = info.pictures();
+    let pic = pictures.first()?;
+
+    // the file goes first, so there never is a row without one
+    let (shard, hash) = cover_art::Model::write(&pic.data, db.data_path())
+        .await
+        .inspect_err(|e| error!("writing cover art: {e}"))
+        .ok()?;
+    let cover_art = cover_art::ActiveModel {
+        shard: AV::Set(shard),
+        mime_type: AV::Set(pic.mime_type.clone()),
+        song: AV::Set(Some(song_id)),
+        hash: AV::Set(Some(hash)),
+        ..Default::default()
+    };
+    let id = match cover_art::Entity::insert(cover_art)
+        .exec(db.connection())
+        .await
+    {
+        Ok(res) => res.last_insert_id,
+        Err(e) => {
+            trace!("inserting cover art: {e}");
+            return None;
+        }
+    };
+    db.get_cover_art(format!("{id}")).await
+}
+
+// upserts the song's directory (and its parents), marking it as modified
+async fn link_directory(db: &DB, media_paths: &[Utf8PathBuf], info: &IndexerResult) -> Option<i32> {
+    let root = media_paths
+        .iter()
+        .find(|root| info.path.starts_with(root))?;
+    let dir = info.path.parent()?;
+    let id = db
+        .upsert_directory(dir, root)
+        .await
+        .inspect_err(|e| warn!("upserting directory: {e}"))
+        .ok()?;
+    db.touch_directory(id)
+        .await
+        .inspect_err(|e| warn!("touching directory: {e}"))
+        .ok();
+    Some(id)
+}
+
+/// what the indexer is up to, updated as it goes
+#[derive(Debug, Default)]
+struct ScanStatus {
+    scanning: AtomicBool,
+    // files handed to the indexer
+    count: AtomicU32,
+    folder: Mutex<Option<Utf8PathBuf>>,
+}
+
+/// a snapshot of `ScanStatus`
+#[derive(Debug, Clone)]
+pub struct ScanProgress {
+    pub scanning: bool,
+    pub count: u32,
+    pub folder: Option<Utf8PathBuf>,
+}
+
+// marks the scan as done when dropped, even if it panicked
+struct Scanning<'a>(&'a ScanStatus);
+
+impl Drop for Scanning<'_> {
+    fn drop(&mut self) {
+        *self.0.folder.lock().unwrap() = None;
+        self.0.scanning.store(false, Ordering::SeqCst);
+    }
+}
+
+/// lives as long as the server, and scans whenever asked to
+#[derive(Debug)]
 pub struct Indexer {
     media_paths: Vec<Utf8PathBuf>,
+    // one per media path, in the same order
+    music_folders: Vec<music_folder::Model>,
     db: Arc<DB>,
     config: IndexerConfig,
+    status: Arc<ScanStatus>,
 }
 impl Indexer {
     pub async fn new(config: &Config) -> Result<Self, db::Error> {
+        let db = DB::new(
+            &config.system.data_path,
+            config.system.admin_password.as_deref(),
+        )
+        .await?;
+        let music_folders = db.sync_music_folders(&config.media.paths).await?;
         Ok(Indexer {
-            media_paths: config.media.paths.clone(),
-            db: Arc::new(DB::new(&config.system.data_path).await?),
+            media_paths: config
+                .media
+                .paths
+                .iter()
+                .map(|media_path| media_path.path().to_owned())
+                .collect(),
+            music_folders,
+            db: Arc::new(db),
             config: config.indexer.clone(),
+            status: Default::default(),
         })
     }
     pub fn db(&self) -> Arc<DB> {
         self.db.clone()
     }
-    pub async fn run(&self) {
+
+    pub(crate) fn music_folders(&self) -> &[music_folder::Model] {
+        &self.music_folders
+    }
+
+    pub fn status(&self) -> ScanProgress {
+        ScanProgress {
+            scanning: self.status.scanning.load(Ordering::SeqCst),
+            count: self.status.count.load(Ordering::Relaxed),
+            folder: self.status.folder.lock().unwrap().clone(),
+        }
+    }
+
+    /// scans in the background, `full` reading every file again instead of only
+    /// new and outdated ones; `false` if a scan is running already
+    pub fn start_scan(self: &Arc<Self>, full: bool) -> bool {
+        if self
+            .status
+            .scanning
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return false;
+        }
+        self.status.count.store(0, Ordering::Relaxed);
+        let indexer = self.clone();
+        spawn(async move {
+            let _scanning = Scanning(&indexer.status);
+            indexer.run(full).await;
+        });
+        true
+    }
+
+    async fn run(&self, full: bool) {
         // SAFETY: 4 is non zero
         const DEFAULT_PAR: NonZero<usize> = unsafe { NonZero::new_unchecked(4) };
         let par = std::thread::available_parallelism().unwrap_or_else(|_| {
@@ -150,10 +582,8 @@ impl Indexer {
         info!("gotta go this fast: {par}");
         let par = par.into();
 
-        let enable = self.config.enable;
-        if !enable {
-            warn!("indexer disabled! (just running dirwalk)");
-        }
+        // before anything new is written, so files and rows are in a known state
+        gc::collect_garbage(&self.db).await;
 
         let (indexer_tx, mut indexer_rx) = mpsc::channel::<Utf8PathBuf>(par);
 
@@ -165,20 +595,38 @@ impl Indexer {
         let (db_tx, mut db_rx) = mpsc::channel::<IndexerResult>(io_par);
 
         let db = self.db.clone();
+        let media_paths = self.media_paths.clone();
+        let music_folders = self.music_folders.clone();
+        let mut folder_art = FolderArt::new(self.config.artwork.clone(), self.media_paths.clone());
+        let genre_separators = self.config.genre_separators.clone();
 
         let mut known = HashSet::new();
-        let everything = self.db.all_songs().await;
-        known.extend(everything.into_iter().map(|song| song.path));
-
-        spawn(async move {
+        // a full scan knows nothing
+        let everything = if full {
+            vec![]
+        } else {
+            self.db.all_songs().await
+        };
+        let with_genres = self.db.songs_with_genres().await;
+        // songs indexed before albums, artists, directories and genres were tracked need their tags read again
+        known.extend(
+            everything
+                .into_iter()
+                .filter(|song| {
+                    !(song.album.is_some() && song.album_id.is_none()
+                        || song.artist.is_some() && song.artist_id.is_none()
+                        || song.parent.is_none()
+                        || song.genre.is_some() && !with_genres.contains(&song.id)
+                        || song.tag_version < TAG_VERSION)
+                })
+                .map(|song| song.path),
+        );
+
+        let db_worker = spawn(async move {
             let mut entries = Vec::with_capacity(io_par);
 
-            loop {
-                db_rx.recv_many(&mut entries, io_par).await;
-                if db_rx.is_closed() {
-                    warn!("FIXME: db channel has shut down");
-                    return;
-                }
+            // 0 once the channel is closed and drained
+            while db_rx.recv_many(&mut entries, io_par).await > 0 {
                 for info in &entries {
                     {
                         let Ok(size) = info.size().map(|sz| sz.try_into()).transpose() else {
@@ -195,65 +643,134 @@ impl Indexer {
 
                         // TODO transaction
 
+                        let genres = info.genres(&genre_separators);
+                        // the first one stands in for all of them where only one fits
+                        let genre = genres.first();
+
+                        let (artist_ids, album_artist_id, album_id) =
+                            link_artists_and_album(&db, info, genre.map(String::as_str)).await;
+                        let replay_gain = info.replay_gain();
+                        let directory_id = link_directory(&db, &media_paths, info).await;
+                        let folder = music_folders
+                            .iter()
+                            .find(|folder| info.path.starts_with(&folder.path))
+                            .map(|folder| folder.id);
+
                         let song = song::ActiveModel {
-                            // parent: todo!(),
+                            parent: AV::Set(directory_id.map(directory::format_id)),
                             title: AV::Set(info.title().to_string()),
                             path: AV::Set(info.path.to_string()),
                             album: AV::Set(info.album().to_pwned()),
-                            artist: AV::Set(info.artist().to_pwned()),
-                            // track: todo!(),
+                            artist: AV::Set(info.artist()),
+                            album_artist: AV::Set(info.album_artist().to_pwned()),
+                            track: AV::Set(info.track()),
+                            disc: AV::Set(info.disc()),
                             duration: AV::Set(info.duration().map(|d| d.as_secs() as u32)),
-                            // year: todo!(),
-                            // genre: todo!(),
-                            // cover_art: todo!(),
+                            year: AV::Set(info.year()),
+                            genre: AV::Set(genre.cloned()),
                             size: AV::Set(size),
                             content_type: AV::Set(
                                 info.mime_type.as_ref().map(|inner| inner.to_string()),
                             ),
+                            album_id: AV::Set(album_id),
+                            artist_id: AV::Set(artist_ids.first().copied()),
+                            tag_version: AV::Set(TAG_VERSION),
+                            bit_rate: AV::Set(info.audio.bit_rate),
+                            sampling_rate: AV::Set(info.audio.sampling_rate),
+                            channel_count: AV::Set(info.audio.channel_count),
+                            track_gain: AV::Set(replay_gain.track_gain),
+                            track_peak: AV::Set(replay_gain.track_peak),
+                            album_gain: AV::Set(replay_gain.album_gain),
+                            album_peak: AV::Set(replay_gain.album_peak),
+                            music_brainz_id: AV::Set(info.music_brainz_id()),
+                            sort_name: AV::Set(info.sort_name().to_pwned()),
+                            folder: AV::Set(folder),
                             ..Default::default()
                         };
 
-                        let song_id = match song::Entity::insert(song).exec(db.connection()).await {
-                            Ok(inner_res) => Some(inner_res.last_insert_id),
+                        // songs indexed before they had an album/artist are updated in place
+                        let upsert = OnConflict::column(song::Column::Path)
+                            .update_columns([
+                                song::Column::Title,
+                                song::Column::Album,
+                                song::Column::Artist,
+                                song::Column::AlbumArtist,
+                                song::Column::Track,
+                                song::Column::Disc,
+                                song::Column::Duration,
+                                song::Column::Year,
+                                song::Column::Genre,
+                                song::Column::Size,
+                                song::Column::ContentType,
+                                song::Column::AlbumId,
+                                song::Column::ArtistId,
+                                song::Column::Parent,
+                                song::Column::TagVersion,
+                                song::Column::BitRate,
+                                song::Column::SamplingRate,
+                                song::Column::ChannelCount,
+                                song::Column::TrackGain,
+                                song::Column::TrackPeak,
+                                song::Column::AlbumGain,
+                                song::Column::AlbumPeak,
+                                song::Column::MusicBrainzId,
+                                song::Column::SortName,
+                                song::Column::Folder,
+                            ])
+                            .to_owned();
+                        // looked up by path: SQLite's last insert ID isn't set by the update
+                        let song_id = match song::Entity::insert(song)
+                            .on_conflict(upsert)
+                            .exec_without_returning(db.connection())
+                            .await
+                        {
+                            Ok(_) => db.get_song_id(info.path.as_str()).await,
                             Err(e) => {
                                 warn!("inserting song: {e}");
                                 None
                             }
                         };
 
-                        if let Some(song_id) = song_id {
-                            let pictures = info.pictures();
-                            if !pictures.is_empty() {
-                                let pic = pictures[0];
-
-                                // 512 shards ought to be enough for anybody
-                                let shard = (rand::random::<u32>() % 512) as _;
-                                let cover_art = cover_art::ActiveModel {
-                                    shard: AV::Set(shard as _),
-                                    mime_type: AV::Set(pic.mime_type.clone()),
-                                    song: AV::Set(song_id),
-                                    ..Default::default()
-                                };
-
-                                match cover_art::Entity::insert(cover_art)
-                                    .exec(db.connection())
+                        let Some(song_id) = song_id else {
+                            continue;
+                        };
+
+                        db.set_song_artists(song_id, &artist_ids)
+                            .await
+                            .inspect_err(|e| warn!("song artists: {e}"))
+                            .ok();
+                        db.set_song_genres(song_id, &genres)
+                            .await
+                            .inspect_err(|e| warn!("song genres: {e}"))
+                            .ok();
+                        db.set_song_lyrics(song_id, info.lyrics.clone())
+                            .await
+                            .inspect_err(|e| warn!("song lyrics: {e}"))
+                            .ok();
+
+                        let mut candidates = vec![];
+                        if folder_art.uses(ArtworkSource::Embedded) {
+                            candidates.extend(embedded_cover_art(&db, song_id, info).await);
+                        }
+                        candidates.extend(folder_art.album(&db, &info.path).await);
+                        if let Some(cover_art) =
+                            candidates.iter().min_by_key(|art| folder_art.rank(art))
+                        {
+                            db.set_song_cover_art(song_id, cover_art.id)
+                                .await
+                                .inspect_err(|e| warn!("song cover art: {e}"))
+                                .ok();
+                            if let Some(album_id) = album_id {
+                                offer_album_cover_art(&db, &folder_art, album_id, cover_art).await;
+                            }
+                        }
+
+                        if let Some(artist_id) = album_artist_id {
+                            if let Some(cover_art) = folder_art.artist(&db, &info.path).await {
+                                db.set_artist_cover_art(artist_id, cover_art.id)
                                     .await
-                                {
-                                    Ok(res) => {
-                                        let data_path = db.data_path();
-                                        if let Err(e) = cover_art::Model::write(
-                                            &pic.data,
-                                            res.last_insert_id,
-                                            shard,
-                                            data_path,
-                                        )
-                                        .await
-                                        {
-                                            error!("writing cover art: {e}");
-                                        }
-                                    }
-                                    Err(e) => trace!("inserting cover art: {e}"),
-                                }
+                                    .inspect_err(|e| warn!("artist cover art: {e}"))
+                                    .ok();
                             }
                         }
                     }
@@ -265,19 +782,10 @@ impl Indexer {
         let mut exclude_files = HashSet::<String>::new();
         exclude_files.extend(self.config.exclude.files.iter().map(|s| s.to_string()));
 
-        spawn(async move {
+        let tag_worker = spawn(async move {
             let mut entries = Vec::with_capacity(par);
 
-            loop {
-                indexer_rx.recv_many(&mut entries, par).await;
-                if !enable {
-                    // TODO why do we need this? (remove it -> busy waiting, but tx-chan should be dropped?)
-                    indexer_rx.close();
-                }
-                if indexer_rx.is_closed() {
-                    warn!("FIXME: indexer channel has shut down");
-                    return;
-                }
+            while indexer_rx.recv_many(&mut entries, par).await > 0 {
                 // trace!("workload {}", entries.len());
 
                 // collect is wasteful but we need an async context for queue send
@@ -297,19 +805,28 @@ impl Indexer {
                         trace!("processing {path} {:?}", path.file_name());
                         let mime_type = mime_guess::from_path(path).first();
 
+                        let mut audio = Properties::default();
                         let tag = match mime_type.as_ref().map(|m| (m.type_(), m.subtype())) {
-                            Some((AUDIO, MPEG)) => match Id3Tag::read_from_path(path) {
-                                Ok(tag) => Some(Tag::Id3(tag)),
-                                Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => None,
-                                Err(e) => {
-                                    warn!("error reading Id3: {e:?}");
-                                    None
+                            Some((AUDIO, MPEG)) => {
+                                audio = audio::mpeg(path)
+                                    .inspect_err(|e| warn!("reading MPEG frames of {path}: {e}"))
+                                    .unwrap_or_default();
+                                match Id3Tag::read_from_path(path) {
+                                    Ok(tag) => Some(Tag::Id3(tag)),
+                                    Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => None,
+                                    Err(e) => {
+                                        warn!("error reading Id3: {e:?}");
+                                        None
+                                    }
                                 }
-                            },
+                            }
                             Some((t, s)) => {
                                 info!("{path} is not an mp3: {t}/{s} - using ffprobe");
                                 match metadata(path) {
-                                    Ok(md) => Some(Tag::Ffprobe(md.into_tag())),
+                                    Ok(md) => {
+                                        audio = md.properties();
+                                        Some(Tag::Ffprobe(Box::new(md.into_tag())))
+                                    }
                                     // deser error means mostly either "no suitable metadata", which is ok, go `None` then
                                     // or NonUtf8, which we still need to handle
                                     Err(ffprobe::Error::Deser(e)) => {
@@ -326,7 +843,10 @@ impl Indexer {
                             None => {
                                 warn!("could not determine mime type for {path}");
                                 match metadata(path) {
-                                    Ok(md) => Some(Tag::Ffprobe(md.into_tag())),
+                                    Ok(md) => {
+                                        audio = md.properties();
+                                        Some(Tag::Ffprobe(Box::new(md.into_tag())))
+                                    }
                                     // deser error means mostly either "no suitable metadata", which is ok, go `None` then
                                     // or NonUtf8, which we still need to handle
                                     Err(ffprobe::Error::Deser(e)) => {
@@ -342,9 +862,11 @@ impl Indexer {
                         };
 
                         IndexerResult {
+                            lyrics: lyrics::extract(path, tag.as_ref()),
                             path: path.to_owned(),
                             tag,
                             mime_type,
+                            audio,
                         }
                     })
                     .collect();
@@ -359,18 +881,27 @@ impl Indexer {
             }
         });
 
-        let visitor = Visitor { tx: indexer_tx };
+        let visitor = Visitor {
+            tx: indexer_tx,
+            status: self.status.clone(),
+        };
 
-        let count = Default::default();
-        debug!("indexer::start");
-        if !enable {
-            drop(visitor);
-        } else {
-            // TODO why do we need to guard this on `enable`? (remove -> teh errorz)
-            for path in &self.media_paths {
-                load(path, visitor.clone(), &count).await;
+        info!("scan started{}", if full { " (full)" } else { "" });
+        for path in &self.media_paths {
+            load(path, visitor.clone(), &self.status.count).await;
+        }
+        // the workers are done once the channels are closed and drained, which
+        // starts with the last sender going away
+        drop(visitor);
+        for worker in [tag_worker, db_worker] {
+            if let Err(e) = worker.await {
+                error!("indexer worker: {e}");
             }
         }
-        debug!("indexer::finish {count:?}");
+        gc::collect_albums(&self.db).await;
+        info!(
+            "scan finished, {} files",
+            self.status.count.load(Ordering::Relaxed)
+        );
     }
 }
@@ -1,10 +1,18 @@
 use sea_orm::FromQueryResult;
 use subsonic_types::{
-    common::DateTime,
-    response::{AlbumID3, ArtistID3},
+    common::Seconds,
+    response::{AlbumID3, ArtistID3, Child, Genre as GenreResponse},
 };
 
-use crate::entity::song;
+use crate::entity::{album, artist, play_queue, playlist, share, song, user};
+
+/// a song along with everything it has more than one of
+#[derive(Debug)]
+pub(crate) struct SongDetails {
+    pub(crate) song: song::Model,
+    pub(crate) artists: Vec<artist::Model>,
+    pub(crate) genres: Vec<String>,
+}
 
 #[derive(Debug)]
 pub(crate) struct QueryResult {
@@ -13,85 +21,134 @@ pub(crate) struct QueryResult {
     pub(crate) songs: Vec<song::Model>,
 }
 
+/// an artist, with its album count
 #[derive(Debug, FromQueryResult)]
 pub(crate) struct Artist {
-    name: String,
-}
-
-impl Artist {
-    pub(crate) fn id(&self) -> String {
-        self.name.clone()
-    }
-
-    pub(crate) fn album_count(&self) -> u32 {
-        1
-    }
-
-    pub(crate) fn cover_art(&self) -> Option<String> {
-        None
-    }
-
-    pub(crate) fn artist_image_url(&self) -> Option<String> {
-        None
-    }
-
-    pub(crate) fn starred(&self) -> Option<DateTime> {
-        None
-    }
-
-    pub(crate) fn name(&self) -> String {
-        self.name.clone()
-    }
+    pub(crate) id: i32,
+    pub(crate) name: String,
+    pub(crate) album_count: i64,
+    // from any of the artist's albums
+    pub(crate) cover_art: Option<i32>,
 }
 
 impl From<Artist> for ArtistID3 {
     fn from(artist: Artist) -> Self {
         ArtistID3 {
-            id: artist.id(),
-            name: artist.name(),
-            cover_art: artist.cover_art(),
-            artist_image_url: artist.artist_image_url(),
-            album_count: artist.album_count(),
-            starred: artist.starred(),
-        }
-    }
-}
-
-impl From<song::Model> for Artist {
-    fn from(song: song::Model) -> Self {
-        Artist {
-            // TODO h4x
-            name: song.artist.unwrap_or_default(),
+            id: artist::format_id(artist.id),
+            name: artist.name,
+            cover_art: artist.cover_art.map(|id| format!("{id}")),
+            artist_image_url: None,
+            album_count: artist.album_count as _,
+            starred: None,
         }
     }
 }
 
+/// an album, with song count and total duration of its songs
 #[derive(Debug, FromQueryResult)]
 pub(crate) struct Album {
-    title: String,
-    artist: String,
+    pub(crate) id: i32,
+    pub(crate) name: String,
+    pub(crate) artist_id: Option<i32>,
+    pub(crate) artist: Option<String>,
+    pub(crate) year: Option<u32>,
+    pub(crate) genre: Option<String>,
+    pub(crate) cover_art: Option<i32>,
+    pub(crate) song_count: i64,
+    pub(crate) duration: Option<i64>,
+    pub(crate) play_count: u32,
 }
 
-// TODO h4x
 impl From<Album> for AlbumID3 {
     fn from(album: Album) -> Self {
-        // TODO h4x
-        let artist = Artist { name: album.artist };
         AlbumID3 {
-            id: album.title.clone(),
-            name: album.title.clone(),
-            // TODO h4x
-            artist: Some(artist.name()),
-            // TODO h4x
-            artist_id: Some(artist.id()),
-            cover_art: None,
-            song_count: 1,
-            duration: 1,
-            play_count: None,
+            id: album::format_id(album.id),
+            name: album.name,
+            artist: album.artist,
+            artist_id: album.artist_id.map(artist::format_id),
+            cover_art: album.cover_art.map(|id| format!("{id}")),
+            song_count: album.song_count as _,
+            duration: album.duration.unwrap_or_default() as _,
+            play_count: Some(album.play_count.into()),
             created: None,
             starred: None,
-            year: None,
-            genre: None,
+            year: album.year,
+            genre: album.genre,
         }
     }
 }
+
+// the pre-ID3 representation, as used by `getAlbumList`
+impl From<Album> for Child {
+    fn from(album: Album) -> Self {
+        Child {
+            id: album::format_id(album.id),
+            parent: album.artist_id.map(artist::format_id),
+            is_dir: true,
+            title: album.name.clone(),
+            album: Some(album.name),
+            artist: album.artist,
+            year: album.year,
+            genre: album.genre,
+            cover_art: album.cover_art.map(|id| format!("{id}")),
+            duration: album.duration.map(|d| Seconds::new(d as _)),
+            play_count: Some(album.play_count.into()),
+            album_id: Some(album::format_id(album.id)),
+            artist_id: album.artist_id.map(artist::format_id),
+            ..Default::default()
+        }
+    }
+}
+
+/// a genre, with the number of songs and albums in it
+#[derive(Debug, FromQueryResult)]
+pub(crate) struct Genre {
+    pub(crate) name: String,
+    pub(crate) song_count: i64,
+    pub(crate) album_count: i64,
+}
+
+impl From<Genre> for GenreResponse {
+    fn from(genre: Genre) -> Self {
+        GenreResponse {
+            song_count: genre.song_count as _,
+            album_count: genre.album_count as _,
+            name: genre.name,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct PlaylistWithEntries {
+    pub(crate) playlist: playlist::Model,
+    pub(crate) owner: Option<user::Model>,
+    // in playlist order
+    pub(crate) songs: Vec<song::Model>,
+}
+
+#[derive(Debug)]
+pub(crate) struct PlayQueueWithEntries {
+    pub(crate) play_queue: play_queue::Model,
+    // in queue order
+    pub(crate) songs: Vec<song::Model>,
+}
+
+#[derive(Debug)]
+pub(crate) struct ShareWithEntries {
+    pub(crate) share: share::Model,
+    pub(crate) owner: Option<user::Model>,
+    // in the order they were shared
+    pub(crate) songs: Vec<song::Model>,
+}
+
+/// changes to apply to a playlist; `songs` replaces all entries and is applied
+/// before `remove` (indices into the existing entries) and `add`
+#[derive(Debug, Default)]
+pub(crate) struct PlaylistUpdate {
+    pub(crate) name: Option<String>,
+    pub(crate) comment: Option<String>,
+    pub(crate) public: Option<bool>,
+    pub(crate) songs: Option<Vec<i32>>,
+    pub(crate) remove: Vec<u32>,
+    pub(crate) add: Vec<i32>,
+}
@@ -15,6 +15,12 @@ pub mod entity;
 
 pub mod config;
 
+pub mod transcode;
+
+pub mod artwork;
+
+pub(crate) mod zip;
+
 pub(crate) mod util;
 pub trait FileVisitor: Clone {
     fn visit(
@@ -23,6 +29,11 @@ pub trait FileVisitor: Clone {
     ) -> impl std::future::Future<Output = ()> + Send;
 }
 
+// MP3s are read directly, everything else with ffprobe
+const AUDIO_EXTENSIONS: &[&str] = &[
+    "mp3", "flac", "ogg", "oga", "opus", "m4a", "aac", "wav", "aif", "aiff", "wma", "ape", "wv",
+];
+
 // TODO not parallel enough!!!
 async fn load(root: impl AsRef<Utf8Path>, mut action: impl FileVisitor, count: &AtomicU32) {
     for entry in WalkDir::new(root.as_ref()) {
@@ -37,17 +48,19 @@ async fn load(root: impl AsRef<Utf8Path>, mut action: impl FileVisitor, count: &
             return;
         };
 
-        let val: u32 = count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        if val % 100 == 0 {
-            debug!("indexer:: {val}");
-        }
-
         // TODO symlinks yes no maybe
-        // TODO hardcoded mp3 extension
         if path.is_file()
-            && path.extension().map(|ext| ext.to_lowercase()) == Some("mp3".to_string())
+            && path.extension().is_some_and(|ext| {
+                AUDIO_EXTENSIONS
+                    .iter()
+                    .any(|audio| ext.eq_ignore_ascii_case(audio))
+            })
         {
-            count.fetch_add(1, Ordering::Relaxed);
+            // the files visited, for `getScanStatus`
+            let val = count.fetch_add(1, Ordering::Relaxed);
+            if val % 100 == 0 {
+                debug!("indexer:: {val}");
+            }
             action.visit(path).await;
         }
     }
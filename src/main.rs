@@ -1,6 +1,6 @@
-use std::env;
+use std::{env, sync::Arc};
 
-use tokio::spawn;
+use tracing::warn;
 use udrome::{api::serve, config::Config, indexer::Indexer};
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -8,10 +8,13 @@ async fn main() -> anyhow::Result<()> {
 
     let config = Config::new(env::args().nth(1))?;
 
-    let ixr = Indexer::new(&config).await?;
-    let db = ixr.db();
-    spawn(async move { ixr.run().await });
+    let ixr = Arc::new(Indexer::new(&config).await?);
+    if config.indexer.enable {
+        ixr.start_scan(false);
+    } else {
+        warn!("indexer disabled, no scan until `startScan`");
+    }
 
-    serve(db, &config).await;
+    serve(ixr, &config).await;
     Ok(())
 }
@@ -0,0 +1,340 @@
+use std::{
+    collections::HashMap,
+    io,
+    process::Stdio,
+    sync::{Arc, Mutex},
+    time::UNIX_EPOCH,
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncWriteExt, DuplexStream},
+    process::{Child, Command},
+};
+use tracing::{debug, error, warn};
+
+const PIPE_SIZE: usize = 64 * 1024;
+
+// files still being written, not part of the cache yet
+const PART_SUFFIX: &str = ".part";
+
+/// what ffmpeg can turn songs into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Mp3,
+    Opus,
+    Aac,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "mp3" => Some(Format::Mp3),
+            "opus" => Some(Format::Opus),
+            "aac" => Some(Format::Aac),
+            _ => None,
+        }
+    }
+
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Format::Mp3 => "mp3",
+            Format::Opus => "opus",
+            Format::Aac => "aac",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Format::Mp3 => "audio/mpeg",
+            Format::Opus => "audio/ogg",
+            Format::Aac => "audio/aac",
+        }
+    }
+
+    /// in kbit/s
+    pub fn default_bit_rate(self) -> u32 {
+        match self {
+            Format::Mp3 => 192,
+            Format::Opus => 128,
+            Format::Aac => 192,
+        }
+    }
+
+    // encoder and (streamable) container
+    fn ffmpeg_args(self) -> &'static [&'static str] {
+        match self {
+            Format::Mp3 => &["-c:a", "libmp3lame", "-f", "mp3"],
+            Format::Opus => &["-c:a", "libopus", "-f", "ogg"],
+            Format::Aac => &["-c:a", "aac", "-f", "adts"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transcode {
+    pub format: Format,
+    /// in kbit/s
+    pub bit_rate: u32,
+    /// where to start, in seconds
+    pub offset: u32,
+}
+
+/// runs ffmpeg, and keeps finished transcodes around for as long as they fit
+/// into `system.cache_mb`
+#[derive(Debug)]
+pub struct Transcoder {
+    // `None` if caching is disabled
+    cache: Option<Arc<Cache>>,
+}
+
+#[derive(Debug)]
+struct Cache {
+    dir: Utf8PathBuf,
+    // in bytes
+    limit: u64,
+    lru: Mutex<Lru>,
+}
+
+#[derive(Debug, Default)]
+struct Lru {
+    // file name -> (size, last use)
+    files: HashMap<String, (u64, u64)>,
+    total: u64,
+    clock: u64,
+}
+
+impl Lru {
+    fn touch(&mut self, name: &str) -> bool {
+        self.clock += 1;
+        match self.files.get_mut(name) {
+            Some((_, used)) => {
+                *used = self.clock;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn insert(&mut self, name: String, size: u64) {
+        self.clock += 1;
+        if let Some((old, _)) = self.files.insert(name, (size, self.clock)) {
+            self.total -= old;
+        }
+        self.total += size;
+    }
+
+    // least recently used first, until everything fits into `limit`
+    fn evict(&mut self, limit: u64) -> Vec<String> {
+        let mut evicted = vec![];
+        while self.total > limit {
+            let Some(oldest) = self
+                .files
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(name, _)| name.clone())
+            else {
+                break;
+            };
+            if let Some((size, _)) = self.files.remove(&oldest) {
+                self.total -= size;
+            }
+            evicted.push(oldest);
+        }
+        evicted
+    }
+}
+
+impl Cache {
+    async fn open(dir: Utf8PathBuf, limit: u64) -> io::Result<Self> {
+        tokio::fs::create_dir_all(&dir).await?;
+
+        let mut files = vec![];
+        let mut entries = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            // left over from an interrupted transcode
+            if name.ends_with(PART_SUFFIX) {
+                let _ = tokio::fs::remove_file(entry.path()).await;
+                continue;
+            }
+            let metadata = entry.metadata().await?;
+            let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+            files.push((modified, name, metadata.len()));
+        }
+
+        // without access times, the oldest files count as least recently used
+        files.sort();
+        let mut lru = Lru::default();
+        for (_, name, size) in files {
+            lru.insert(name, size);
+        }
+        let cache = Cache {
+            dir,
+            limit,
+            lru: Mutex::new(lru),
+        };
+        cache.evict().await;
+        Ok(cache)
+    }
+
+    async fn evict(&self) {
+        let evicted = self.lru.lock().unwrap().evict(self.limit);
+        for name in evicted {
+            debug!("evicting {name} from the transcode cache");
+            if let Err(e) = tokio::fs::remove_file(self.dir.join(&name)).await {
+                warn!("removing {name} from the transcode cache: {e}");
+            }
+        }
+    }
+}
+
+// cached transcodes are told apart by source file (and its state) and target
+async fn cache_key(path: &Utf8Path, transcode: &Transcode) -> io::Result<String> {
+    let metadata = tokio::fs::metadata(path).await?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let digest = md5::compute(format!(
+        "{path}\0{modified}\0{}\0{}",
+        metadata.len(),
+        transcode.bit_rate
+    ));
+    Ok(format!("{digest:x}.{}", transcode.format.suffix()))
+}
+
+impl Transcoder {
+    pub async fn new(data_path: impl AsRef<Utf8Path>, cache_mb: u16) -> Self {
+        if cache_mb == 0 {
+            return Transcoder { cache: None };
+        }
+
+        let dir = data_path.as_ref().join("cache").join("transcode");
+        let cache = match Cache::open(dir.clone(), u64::from(cache_mb) * 1024 * 1024).await {
+            Ok(cache) => Some(Arc::new(cache)),
+            Err(e) => {
+                error!("transcode cache at {dir} unavailable: {e}");
+                None
+            }
+        };
+        Transcoder { cache }
+    }
+
+    /// a finished transcode of `path`, if there is one
+    pub async fn cached(&self, path: &Utf8Path, transcode: &Transcode) -> Option<Utf8PathBuf> {
+        // only whole songs are cached
+        if transcode.offset > 0 {
+            return None;
+        }
+        let cache = self.cache.as_ref()?;
+        let key = cache_key(path, transcode).await.ok()?;
+        cache
+            .lru
+            .lock()
+            .unwrap()
+            .touch(&key)
+            .then(|| cache.dir.join(key))
+    }
+
+    /// starts transcoding `path`; the result can be read from the returned stream
+    /// while ffmpeg is still at it, and is cached once complete
+    pub async fn start(&self, path: &Utf8Path, transcode: &Transcode) -> io::Result<DuplexStream> {
+        let key = match &self.cache {
+            Some(cache) if transcode.offset == 0 => {
+                Some((cache.clone(), cache_key(path, transcode).await?))
+            }
+            _ => None,
+        };
+
+        let child = Command::new("ffmpeg")
+            .args(["-v", "error", "-nostdin"])
+            // seeking on the input is quick, and exact enough for audio
+            .args(["-ss", &transcode.offset.to_string()])
+            .args(["-i", path.as_str()])
+            // the audio only, no cover art
+            .args(["-map", "0:a:0"])
+            .args(["-b:a", &format!("{}k", transcode.bit_rate)])
+            .args(transcode.format.ffmpeg_args())
+            .arg("-")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let (reader, writer) = tokio::io::duplex(PIPE_SIZE);
+        let path = path.to_owned();
+        tokio::spawn(async move {
+            if let Err(e) = pump(child, writer, key).await {
+                // most likely the client went away
+                debug!("transcoding {path}: {e}");
+            }
+        });
+        Ok(reader)
+    }
+}
+
+// copies ffmpeg's output to `out`, and to the cache if there is one
+async fn pump(
+    mut child: Child,
+    mut out: DuplexStream,
+    cache: Option<(Arc<Cache>, String)>,
+) -> io::Result<()> {
+    let Some(mut stdout) = child.stdout.take() else {
+        return Err(io::Error::other("no stdout"));
+    };
+    // concurrent transcodes of the same song each write their own file
+    let part = cache.as_ref().map(|(cache, key)| {
+        cache
+            .dir
+            .join(format!("{key}.{:08x}{PART_SUFFIX}", rand::random::<u32>()))
+    });
+    let mut file = match &part {
+        Some(part) => Some(File::create(part).await?),
+        None => None,
+    };
+
+    let copied: io::Result<u64> = async {
+        let mut buf = vec![0; PIPE_SIZE];
+        let mut size = 0;
+        loop {
+            let n = stdout.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            if let Some(file) = file.as_mut() {
+                file.write_all(&buf[..n]).await?;
+            }
+            out.write_all(&buf[..n]).await?;
+            size += n as u64;
+        }
+        let status = child.wait().await?;
+        if !status.success() {
+            return Err(io::Error::other(format!("ffmpeg failed: {status}")));
+        }
+        if let Some(file) = file.as_mut() {
+            file.flush().await?;
+        }
+        Ok(size)
+    }
+    .await;
+
+    let (Some((cache, key)), Some(part)) = (cache, part) else {
+        return copied.map(|_| ());
+    };
+    let size = match copied {
+        Ok(size) => size,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&part).await;
+            return Err(e);
+        }
+    };
+    tokio::fs::rename(&part, cache.dir.join(&key)).await?;
+    cache.lru.lock().unwrap().insert(key, size);
+    cache.evict().await;
+    Ok(())
+}
@@ -21,19 +21,3 @@ impl Pwn for Option<&str> {
 //         self.as_ref().map(|inner| (*inner).to_owned())
 //     }
 // }
-
-pub(crate) trait Unpwn {
-    fn unpwn(&self) -> Option<&str>;
-}
-
-impl Unpwn for Option<&String> {
-    fn unpwn(&self) -> Option<&str> {
-        self.map(|s| s.as_str())
-    }
-}
-
-impl Unpwn for Option<String> {
-    fn unpwn(&self) -> Option<&str> {
-        self.as_deref()
-    }
-}
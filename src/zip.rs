@@ -0,0 +1,162 @@
+//! just enough of the ZIP format to stream uncompressed archives: audio and
+//! images are compressed already, and this way nothing needs to be buffered
+
+use std::io;
+
+use camino::Utf8Path;
+use time::OffsetDateTime;
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const DATA_DESCRIPTOR: u32 = 0x08074b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+
+// 2.0: data descriptors
+const VERSION: u16 = 20;
+// sizes and CRC follow the data, names are UTF-8
+const FLAGS: u16 = (1 << 3) | (1 << 11);
+const STORED: u16 = 0;
+
+struct Entry {
+    name: String,
+    time: u16,
+    date: u16,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+pub(crate) struct ZipWriter<W> {
+    out: W,
+    written: u64,
+    entries: Vec<Entry>,
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "archive exceeds 4 GiB")
+}
+
+// MS-DOS date and time, which can't go back further than 1980
+fn dos_date_time(modified: OffsetDateTime) -> (u16, u16) {
+    if modified.year() < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    let time = ((modified.hour() as u16) << 11)
+        | ((modified.minute() as u16) << 5)
+        | (modified.second() as u16 / 2);
+    let date = (((modified.year() - 1980) as u16) << 9)
+        | ((modified.month() as u16) << 5)
+        | modified.day() as u16;
+    (time, date)
+}
+
+impl<W: AsyncWrite + Unpin> ZipWriter<W> {
+    pub(crate) fn new(out: W) -> Self {
+        ZipWriter {
+            out,
+            written: 0,
+            entries: vec![],
+        }
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes).await?;
+        self.written += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// adds the contents of `path` as `name`
+    pub(crate) async fn add_file(&mut self, name: &str, path: &Utf8Path) -> io::Result<()> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let modified = file.metadata().await?.modified()?;
+        let (time, date) = dos_date_time(modified.into());
+        let offset = u32::try_from(self.written).map_err(|_| too_large())?;
+
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend(LOCAL_HEADER.to_le_bytes());
+        header.extend(VERSION.to_le_bytes());
+        header.extend(FLAGS.to_le_bytes());
+        header.extend(STORED.to_le_bytes());
+        header.extend(time.to_le_bytes());
+        header.extend(date.to_le_bytes());
+        // CRC, compressed and uncompressed size are in the data descriptor
+        header.extend([0; 12]);
+        header.extend((name.len() as u16).to_le_bytes());
+        header.extend(0u16.to_le_bytes());
+        header.extend(name.as_bytes());
+        self.write(&header).await?;
+
+        let mut crc = crc32fast::Hasher::new();
+        let mut size = 0u64;
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            crc.update(&buf[..n]);
+            self.write(&buf[..n]).await?;
+            size += n as u64;
+        }
+        let crc = crc.finalize();
+        let size = u32::try_from(size).map_err(|_| too_large())?;
+
+        let mut descriptor = Vec::with_capacity(16);
+        descriptor.extend(DATA_DESCRIPTOR.to_le_bytes());
+        descriptor.extend(crc.to_le_bytes());
+        descriptor.extend(size.to_le_bytes());
+        descriptor.extend(size.to_le_bytes());
+        self.write(&descriptor).await?;
+
+        self.entries.push(Entry {
+            name: name.to_string(),
+            time,
+            date,
+            crc,
+            size,
+            offset,
+        });
+        Ok(())
+    }
+
+    /// writes the central directory
+    pub(crate) async fn finish(mut self) -> io::Result<W> {
+        let start = u32::try_from(self.written).map_err(|_| too_large())?;
+        let mut directory = vec![];
+        for entry in &self.entries {
+            directory.extend(CENTRAL_HEADER.to_le_bytes());
+            // made by, needed
+            directory.extend(VERSION.to_le_bytes());
+            directory.extend(VERSION.to_le_bytes());
+            directory.extend(FLAGS.to_le_bytes());
+            directory.extend(STORED.to_le_bytes());
+            directory.extend(entry.time.to_le_bytes());
+            directory.extend(entry.date.to_le_bytes());
+            directory.extend(entry.crc.to_le_bytes());
+            directory.extend(entry.size.to_le_bytes());
+            directory.extend(entry.size.to_le_bytes());
+            directory.extend((entry.name.len() as u16).to_le_bytes());
+            // extra field, comment, disk, internal and external attributes
+            directory.extend([0; 12]);
+            directory.extend(entry.offset.to_le_bytes());
+            directory.extend(entry.name.as_bytes());
+        }
+        let size = directory.len() as u32;
+        let count = self.entries.len() as u16;
+
+        directory.extend(END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+        // this disk, disk with the central directory
+        directory.extend([0; 4]);
+        directory.extend(count.to_le_bytes());
+        directory.extend(count.to_le_bytes());
+        directory.extend(size.to_le_bytes());
+        directory.extend(start.to_le_bytes());
+        // comment
+        directory.extend(0u16.to_le_bytes());
+        self.write(&directory).await?;
+
+        self.out.flush().await?;
+        Ok(self.out)
+    }
+}